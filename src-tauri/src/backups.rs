@@ -0,0 +1,713 @@
+//! Timestamped snapshots of the Claude Code configuration
+//!
+//! Each snapshot is a directory in ~/.ccconfig/backups holding a copy of the
+//! config files at the top of ~/.claude, the agents, commands and hooks
+//! directories, ~/.claude.json and the disabled MCP servers, plus a
+//! `snapshot.json` manifest. The first snapshot is pinned as the original
+//! configuration and never pruned; the others are pruned according to the
+//! retention policy in `policy.json`.
+
+use crate::managed_file;
+use crate::mcp::stash::STASH_FILE;
+use crate::settings_diff::{diff_settings, SettingsChange};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+const BACKUPS_DIR: &str = ".ccconfig/backups";
+const POLICY_FILE: &str = "policy.json";
+const MANIFEST_FILE: &str = "snapshot.json";
+const FILES_DIR: &str = "files";
+
+/// Snapshots from before backups were timestamped
+const LEGACY_BACKUP_DIR: &str = ".ccconfig/claude_backup";
+
+/// Files directly in ~/.claude that are backed up. Everything else there, such
+/// as .credentials.json and history.jsonl, holds credentials or user data.
+const CONFIG_FILES: [&str; 3] = ["settings.json", "settings.local.json", "CLAUDE.md"];
+
+/// Directories of ~/.claude that are backed up with everything inside them
+const RECURSIVE_DIRS: [&str; 3] = ["agents", "commands", "hooks"];
+
+/// Guards against symlink loops inside the recursive directories
+const MAX_DEPTH: usize = 16;
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct RetentionPolicy {
+    /// Unpinned snapshots beyond this count are pruned, oldest first
+    #[serde(rename = "maxSnapshots")]
+    pub max_snapshots: usize,
+    /// Unpinned snapshots older than this are pruned
+    #[serde(rename = "maxAgeDays")]
+    pub max_age_days: Option<u64>,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        RetentionPolicy {
+            max_snapshots: 20,
+            max_age_days: None,
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct BackupFile {
+    /// Path relative to the home directory, e.g. `.claude/agents/reviewer.md`
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct BackupSnapshot {
+    pub id: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: u64,
+    pub reason: String,
+    /// Pinned snapshots (the original configuration) are never pruned
+    #[serde(default)]
+    pub pinned: bool,
+    pub files: Vec<BackupFile>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum FileStatus {
+    /// Present in both, with different content
+    Modified,
+    /// In the snapshot, but deleted since
+    Missing,
+    /// Created since the snapshot was taken
+    Added,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct BackupFileDiff {
+    pub path: String,
+    pub status: FileStatus,
+    /// Key-level changes from the snapshot to the current file, for JSON files
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub changes: Option<Vec<SettingsChange>>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct RestoreReport {
+    #[serde(rename = "snapshotId")]
+    pub snapshot_id: String,
+    /// Snapshot of the state just before restoring, to undo the restore
+    #[serde(rename = "safetySnapshotId")]
+    pub safety_snapshot_id: String,
+    pub restored: Vec<String>,
+    /// Files created after the snapshot that a full restore deleted
+    pub removed: Vec<String>,
+}
+
+fn now_secs() -> Result<u64, String> {
+    Ok(std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_err(|e| format!("Failed to get timestamp: {}", e))?
+        .as_secs())
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+fn get_backups_dir(home_dir: &Path) -> PathBuf {
+    home_dir.join(BACKUPS_DIR)
+}
+
+fn get_snapshot_dir(home_dir: &Path, snapshot_id: &str) -> Result<PathBuf, String> {
+    // Snapshot IDs come from the frontend, so guard against path traversal
+    if snapshot_id.is_empty()
+        || snapshot_id.contains("..")
+        || snapshot_id.contains('/')
+        || snapshot_id.contains('\\')
+    {
+        return Err(format!("Invalid snapshot id: {}", snapshot_id));
+    }
+
+    Ok(get_backups_dir(home_dir).join(snapshot_id))
+}
+
+/// Resolve a manifest path against `root`, refusing anything that escapes it
+fn join_relative(root: &Path, relative: &str) -> Result<PathBuf, String> {
+    let mut path = root.to_path_buf();
+    for part in relative.split('/') {
+        if part.is_empty() || part == "." || part == ".." {
+            return Err(format!("Invalid backup path: {}", relative));
+        }
+        path.push(part);
+    }
+    Ok(path)
+}
+
+fn collect_dir(
+    dir: &Path,
+    relative: &str,
+    depth: usize,
+    files: &mut Vec<String>,
+) -> Result<(), String> {
+    if depth > MAX_DEPTH {
+        return Ok(());
+    }
+
+    let entries =
+        std::fs::read_dir(dir).map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?;
+
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        let child = format!("{}/{}", relative, name);
+
+        if path.is_dir() {
+            collect_dir(&path, &child, depth + 1, files)?;
+        } else if path.is_file() {
+            files.push(child);
+        }
+    }
+
+    Ok(())
+}
+
+/// Paths (relative to home) of every file a snapshot covers, as they exist now
+fn collect_tracked_files(home_dir: &Path) -> Result<Vec<String>, String> {
+    let mut files = Vec::new();
+    let claude_dir = home_dir.join(".claude");
+
+    if claude_dir.is_dir() {
+        for entry in std::fs::read_dir(&claude_dir)
+            .map_err(|e| format!("Failed to read Claude directory: {}", e))?
+        {
+            let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().to_string();
+
+            if path.is_file() && CONFIG_FILES.contains(&name.as_str()) {
+                files.push(format!(".claude/{}", name));
+            } else if path.is_dir() && RECURSIVE_DIRS.contains(&name.as_str()) {
+                collect_dir(&path, &format!(".claude/{}", name), 0, &mut files)?;
+            }
+        }
+    }
+
+    for file in [".claude.json", STASH_FILE] {
+        if home_dir.join(file).is_file() {
+            files.push(file.to_string());
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+/// Whether `path` is removed by a full restore when the snapshot lacks it
+fn is_in_recursive_dir(path: &str) -> bool {
+    RECURSIVE_DIRS
+        .iter()
+        .any(|dir| path.starts_with(&format!(".claude/{}/", dir)))
+}
+
+fn read_manifest(snapshot_dir: &Path) -> Result<BackupSnapshot, String> {
+    let path = snapshot_dir.join(MANIFEST_FILE);
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    serde_json::from_str(&content).map_err(|e| format!("Failed to parse {}: {}", path.display(), e))
+}
+
+fn write_manifest(snapshot_dir: &Path, snapshot: &BackupSnapshot) -> Result<(), String> {
+    let json_content = serde_json::to_string_pretty(snapshot)
+        .map_err(|e| format!("Failed to serialize snapshot manifest: {}", e))?;
+    managed_file::write(&snapshot_dir.join(MANIFEST_FILE), &json_content)
+}
+
+/// Sortable by time, with a random suffix in case two land in the same millisecond
+fn new_snapshot_id() -> String {
+    format!(
+        "{}-{}",
+        chrono::Utc::now().format("%Y%m%dT%H%M%S%3fZ"),
+        &uuid::Uuid::new_v4().simple().to_string()[..6]
+    )
+}
+
+/// Copy each `(source, relative path)` pair into a new snapshot
+fn write_snapshot(
+    home_dir: &Path,
+    files: &[(PathBuf, String)],
+    reason: &str,
+    pinned: bool,
+    created_at: u64,
+) -> Result<BackupSnapshot, String> {
+    let id = new_snapshot_id();
+    let snapshot_dir = get_snapshot_dir(home_dir, &id)?;
+    let files_dir = snapshot_dir.join(FILES_DIR);
+
+    let mut backup_files = Vec::new();
+    for (source, relative) in files {
+        let bytes = std::fs::read(source)
+            .map_err(|e| format!("Failed to read {}: {}", source.display(), e))?;
+
+        let dest = join_relative(&files_dir, relative)?;
+        if let Some(parent) = dest.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create backup directory: {}", e))?;
+        }
+        // Copy rather than write, so permissions such as a hook's executable bit survive
+        std::fs::copy(source, &dest)
+            .map_err(|e| format!("Failed to copy file {}: {}", source.display(), e))?;
+
+        backup_files.push(BackupFile {
+            path: relative.clone(),
+            size: bytes.len() as u64,
+            sha256: sha256_hex(&bytes),
+        });
+    }
+
+    let snapshot = BackupSnapshot {
+        id,
+        created_at,
+        reason: reason.to_string(),
+        pinned,
+        files: backup_files,
+    };
+    write_manifest(&snapshot_dir, &snapshot)?;
+
+    Ok(snapshot)
+}
+
+/// Snapshot the current configuration and prune old snapshots
+pub fn create_snapshot(home_dir: &Path, reason: &str) -> Result<BackupSnapshot, String> {
+    let files: Vec<(PathBuf, String)> = collect_tracked_files(home_dir)?
+        .into_iter()
+        .map(|relative| Ok((join_relative(home_dir, &relative)?, relative)))
+        .collect::<Result<_, String>>()?;
+
+    // The first snapshot is the original configuration that `reset` goes back to
+    let pinned = list_snapshots(home_dir)?.is_empty();
+    let snapshot = write_snapshot(home_dir, &files, reason, pinned, now_secs()?)?;
+
+    if let Err(e) = apply_retention(home_dir, &read_policy(home_dir)?) {
+        eprintln!("Warning: Failed to prune backups: {}", e);
+    }
+
+    Ok(snapshot)
+}
+
+/// Turn the old untimestamped `claude_backup` directory into a pinned
+/// snapshot. Returns `None` when there's nothing to import.
+pub fn import_legacy_backup(home_dir: &Path) -> Result<Option<BackupSnapshot>, String> {
+    let legacy_dir = home_dir.join(LEGACY_BACKUP_DIR);
+    if !legacy_dir.is_dir() || !list_snapshots(home_dir)?.is_empty() {
+        return Ok(None);
+    }
+
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(&legacy_dir)
+        .map_err(|e| format!("Failed to read legacy backup: {}", e))?
+    {
+        let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if entry.path().is_file() && CONFIG_FILES.contains(&name.as_str()) {
+            files.push((entry.path(), format!(".claude/{}", name)));
+        }
+    }
+    files.sort_by(|a, b| a.1.cmp(&b.1));
+
+    let created_at = std::fs::metadata(&legacy_dir)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .map_or_else(now_secs, Ok)?;
+
+    write_snapshot(home_dir, &files, "Original configuration", true, created_at).map(Some)
+}
+
+/// All snapshots, newest first
+pub fn list_snapshots(home_dir: &Path) -> Result<Vec<BackupSnapshot>, String> {
+    let backups_dir = get_backups_dir(home_dir);
+    if !backups_dir.is_dir() {
+        return Ok(vec![]);
+    }
+
+    let mut snapshots = Vec::new();
+    for entry in std::fs::read_dir(&backups_dir)
+        .map_err(|e| format!("Failed to read backups directory: {}", e))?
+    {
+        let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
+        if !entry.path().join(MANIFEST_FILE).is_file() {
+            continue;
+        }
+        match read_manifest(&entry.path()) {
+            Ok(snapshot) => snapshots.push(snapshot),
+            Err(e) => eprintln!("Warning: Skipping unreadable backup: {}", e),
+        }
+    }
+
+    snapshots.sort_by(|a, b| b.created_at.cmp(&a.created_at).then(b.id.cmp(&a.id)));
+    Ok(snapshots)
+}
+
+pub fn get_snapshot(home_dir: &Path, snapshot_id: &str) -> Result<BackupSnapshot, String> {
+    let snapshot_dir = get_snapshot_dir(home_dir, snapshot_id)?;
+    if !snapshot_dir.join(MANIFEST_FILE).is_file() {
+        return Err(format!("Backup '{}' not found", snapshot_id));
+    }
+    read_manifest(&snapshot_dir)
+}
+
+/// Contents of one file in a snapshot, or `None` if the snapshot doesn't have it
+pub fn read_snapshot_file(
+    home_dir: &Path,
+    snapshot_id: &str,
+    path: &str,
+) -> Result<Option<Vec<u8>>, String> {
+    let snapshot = get_snapshot(home_dir, snapshot_id)?;
+    if !snapshot.files.iter().any(|file| file.path == path) {
+        return Ok(None);
+    }
+
+    let source = join_relative(
+        &get_snapshot_dir(home_dir, snapshot_id)?.join(FILES_DIR),
+        path,
+    )?;
+    std::fs::read(&source)
+        .map(Some)
+        .map_err(|e| format!("Failed to read {}: {}", source.display(), e))
+}
+
+/// The pinned snapshot of the configuration from before the app changed anything
+pub fn original_snapshot(home_dir: &Path) -> Result<Option<BackupSnapshot>, String> {
+    Ok(list_snapshots(home_dir)?
+        .into_iter()
+        .filter(|snapshot| snapshot.pinned)
+        .min_by_key(|snapshot| snapshot.created_at))
+}
+
+pub fn read_policy(home_dir: &Path) -> Result<RetentionPolicy, String> {
+    let path = get_backups_dir(home_dir).join(POLICY_FILE);
+    if !path.exists() {
+        return Ok(RetentionPolicy::default());
+    }
+
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read backup policy: {}", e))?;
+    serde_json::from_str(&content).map_err(|e| format!("Failed to parse backup policy: {}", e))
+}
+
+pub fn write_policy(home_dir: &Path, policy: &RetentionPolicy) -> Result<(), String> {
+    let json_content = serde_json::to_string_pretty(policy)
+        .map_err(|e| format!("Failed to serialize backup policy: {}", e))?;
+    managed_file::write(&get_backups_dir(home_dir).join(POLICY_FILE), &json_content)
+}
+
+/// Delete unpinned snapshots beyond the policy's count or age. Returns the pruned ids.
+pub fn apply_retention(home_dir: &Path, policy: &RetentionPolicy) -> Result<Vec<String>, String> {
+    let now = now_secs()?;
+    let max_age_secs = policy.max_age_days.map(|days| days * 24 * 60 * 60);
+
+    let mut pruned = Vec::new();
+    let unpinned = list_snapshots(home_dir)?
+        .into_iter()
+        .filter(|snapshot| !snapshot.pinned);
+
+    for (index, snapshot) in unpinned.enumerate() {
+        let too_many = index >= policy.max_snapshots;
+        let too_old = max_age_secs.is_some_and(|max| now.saturating_sub(snapshot.created_at) > max);
+
+        if too_many || too_old {
+            let snapshot_dir = get_snapshot_dir(home_dir, &snapshot.id)?;
+            std::fs::remove_dir_all(&snapshot_dir)
+                .map_err(|e| format!("Failed to delete backup '{}': {}", snapshot.id, e))?;
+            pruned.push(snapshot.id);
+        }
+    }
+
+    Ok(pruned)
+}
+
+fn json_changes(old: &[u8], new: &[u8]) -> Option<Vec<SettingsChange>> {
+    let old: Value = serde_json::from_slice(old).ok()?;
+    let new: Value = serde_json::from_slice(new).ok()?;
+    Some(diff_settings(&old, &new))
+}
+
+/// What changed between a snapshot and the current files. Unchanged files are omitted.
+pub fn diff_snapshot(home_dir: &Path, snapshot_id: &str) -> Result<Vec<BackupFileDiff>, String> {
+    let snapshot = get_snapshot(home_dir, snapshot_id)?;
+    let files_dir = get_snapshot_dir(home_dir, snapshot_id)?.join(FILES_DIR);
+    let current_files = collect_tracked_files(home_dir)?;
+
+    let mut diffs = Vec::new();
+    for file in &snapshot.files {
+        let current_path = join_relative(home_dir, &file.path)?;
+        let Ok(current) = std::fs::read(&current_path) else {
+            diffs.push(BackupFileDiff {
+                path: file.path.clone(),
+                status: FileStatus::Missing,
+                changes: None,
+            });
+            continue;
+        };
+
+        if sha256_hex(&current) == file.sha256 {
+            continue;
+        }
+
+        let changes = if file.path.ends_with(".json") {
+            std::fs::read(join_relative(&files_dir, &file.path)?)
+                .ok()
+                .and_then(|backed_up| json_changes(&backed_up, &current))
+        } else {
+            None
+        };
+
+        diffs.push(BackupFileDiff {
+            path: file.path.clone(),
+            status: FileStatus::Modified,
+            changes,
+        });
+    }
+
+    for path in current_files {
+        if !snapshot.files.iter().any(|file| file.path == path) {
+            diffs.push(BackupFileDiff {
+                path,
+                status: FileStatus::Added,
+                changes: None,
+            });
+        }
+    }
+
+    Ok(diffs)
+}
+
+/// Put files from a snapshot back in place. With `paths`, only those files are
+/// restored; otherwise the whole snapshot is, and agents, commands and hooks
+/// created since are removed. The current state is snapshotted first.
+pub fn restore_snapshot(
+    home_dir: &Path,
+    snapshot_id: &str,
+    paths: Option<&[String]>,
+) -> Result<RestoreReport, String> {
+    let snapshot = get_snapshot(home_dir, snapshot_id)?;
+    let files_dir = get_snapshot_dir(home_dir, snapshot_id)?.join(FILES_DIR);
+
+    let selected: Vec<&BackupFile> = match paths {
+        Some(paths) => paths
+            .iter()
+            .map(|path| {
+                snapshot
+                    .files
+                    .iter()
+                    .find(|file| &file.path == path)
+                    .ok_or_else(|| format!("'{}' is not part of backup '{}'", path, snapshot_id))
+            })
+            .collect::<Result<_, _>>()?,
+        None => snapshot.files.iter().collect(),
+    };
+
+    // Read everything up front, since the safety snapshot may prune this one
+    let contents: Vec<(&BackupFile, Vec<u8>, Option<std::fs::Permissions>)> = selected
+        .into_iter()
+        .map(|file| {
+            let source = join_relative(&files_dir, &file.path)?;
+            let bytes = std::fs::read(&source)
+                .map_err(|e| format!("Failed to read {}: {}", source.display(), e))?;
+            let permissions = std::fs::metadata(&source).ok().map(|m| m.permissions());
+            Ok((file, bytes, permissions))
+        })
+        .collect::<Result<_, String>>()?;
+
+    let safety_snapshot = create_snapshot(
+        home_dir,
+        &format!("Before restoring backup {}", snapshot_id),
+    )?;
+
+    let mut restored = Vec::new();
+    for (file, bytes, permissions) in contents {
+        let target = join_relative(home_dir, &file.path)?;
+
+        let existed = target.exists();
+        managed_file::write_bytes(&target, &bytes)?;
+
+        // A recreated file gets the permissions it was backed up with
+        if let (false, Some(permissions)) = (existed, permissions) {
+            let _ = std::fs::set_permissions(&target, permissions);
+        }
+
+        restored.push(file.path.clone());
+    }
+
+    let mut removed = Vec::new();
+    if paths.is_none() {
+        for path in collect_tracked_files(home_dir)? {
+            let in_snapshot = snapshot.files.iter().any(|file| file.path == path);
+            if !in_snapshot && is_in_recursive_dir(&path) {
+                std::fs::remove_file(join_relative(home_dir, &path)?)
+                    .map_err(|e| format!("Failed to remove {}: {}", path, e))?;
+                removed.push(path);
+            }
+        }
+    }
+
+    Ok(RestoreReport {
+        snapshot_id: snapshot_id.to_string(),
+        safety_snapshot_id: safety_snapshot.id,
+        restored,
+        removed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(home: &Path, relative: &str, content: &str) {
+        let path = home.join(relative);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_create_snapshot_is_recursive() {
+        let home = tempfile::tempdir().unwrap();
+        write(home.path(), ".claude/settings.json", "{}");
+        write(home.path(), ".claude/agents/nested/reviewer.md", "agent");
+        write(
+            home.path(),
+            ".claude/projects/p/session.jsonl",
+            "not backed up",
+        );
+        write(home.path(), ".claude/.credentials.json", "{}");
+        write(home.path(), ".claude/history.jsonl", "not backed up");
+        write(home.path(), ".claude/CLAUDE.md", "# Memory");
+        write(home.path(), ".claude.json", "{}");
+        write(home.path(), STASH_FILE, r#"{"servers":[]}"#);
+
+        let snapshot = create_snapshot(home.path(), "test").unwrap();
+        let paths: Vec<&str> = snapshot.files.iter().map(|f| f.path.as_str()).collect();
+
+        assert!(snapshot.pinned);
+        assert_eq!(
+            paths,
+            vec![
+                STASH_FILE,
+                ".claude.json",
+                ".claude/CLAUDE.md",
+                ".claude/agents/nested/reviewer.md",
+                ".claude/settings.json"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_and_restore_snapshot() {
+        let home = tempfile::tempdir().unwrap();
+        write(home.path(), ".claude/settings.json", r#"{"model":"opus"}"#);
+        write(home.path(), ".claude/commands/a.md", "a");
+        let snapshot = create_snapshot(home.path(), "test").unwrap();
+
+        write(
+            home.path(),
+            ".claude/settings.json",
+            r#"{"model":"sonnet"}"#,
+        );
+        std::fs::remove_file(home.path().join(".claude/commands/a.md")).unwrap();
+        write(home.path(), ".claude/commands/b.md", "b");
+
+        let diffs = diff_snapshot(home.path(), &snapshot.id).unwrap();
+        let status_of = |path: &str| diffs.iter().find(|d| d.path == path).map(|d| d.status);
+        assert_eq!(
+            status_of(".claude/settings.json"),
+            Some(FileStatus::Modified)
+        );
+        assert_eq!(
+            status_of(".claude/commands/a.md"),
+            Some(FileStatus::Missing)
+        );
+        assert_eq!(status_of(".claude/commands/b.md"), Some(FileStatus::Added));
+        let settings_diff = diffs
+            .iter()
+            .find(|d| d.path == ".claude/settings.json")
+            .unwrap();
+        assert_eq!(settings_diff.changes.as_ref().map(Vec::len), Some(1));
+
+        let report = restore_snapshot(home.path(), &snapshot.id, None).unwrap();
+
+        assert_eq!(report.removed, vec![".claude/commands/b.md"]);
+        assert_eq!(
+            std::fs::read_to_string(home.path().join(".claude/settings.json")).unwrap(),
+            r#"{"model":"opus"}"#
+        );
+        assert!(home.path().join(".claude/commands/a.md").exists());
+        assert!(diff_snapshot(home.path(), &snapshot.id).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_restore_snapshot_selected_files_only() {
+        let home = tempfile::tempdir().unwrap();
+        write(home.path(), ".claude/settings.json", "{}");
+        write(home.path(), ".claude/CLAUDE.md", "old");
+        let snapshot = create_snapshot(home.path(), "test").unwrap();
+
+        write(home.path(), ".claude/settings.json", r#"{"a":1}"#);
+        write(home.path(), ".claude/CLAUDE.md", "new");
+
+        let paths = vec![".claude/CLAUDE.md".to_string()];
+        restore_snapshot(home.path(), &snapshot.id, Some(&paths)).unwrap();
+
+        let read = |p: &str| std::fs::read_to_string(home.path().join(p)).unwrap();
+        assert_eq!(read(".claude/CLAUDE.md"), "old");
+        assert_eq!(read(".claude/settings.json"), r#"{"a":1}"#);
+
+        let outside = vec!["../etc/passwd".to_string()];
+        assert!(restore_snapshot(home.path(), &snapshot.id, Some(&outside)).is_err());
+    }
+
+    #[test]
+    fn test_apply_retention_keeps_pinned_original() {
+        let home = tempfile::tempdir().unwrap();
+        write(home.path(), ".claude/settings.json", "{}");
+
+        let original = create_snapshot(home.path(), "original").unwrap();
+        for i in 0..4 {
+            create_snapshot(home.path(), &format!("snapshot {}", i)).unwrap();
+        }
+
+        let policy = RetentionPolicy {
+            max_snapshots: 2,
+            max_age_days: None,
+        };
+        let pruned = apply_retention(home.path(), &policy).unwrap();
+
+        let remaining = list_snapshots(home.path()).unwrap();
+        assert_eq!(pruned.len(), 2);
+        assert_eq!(remaining.len(), 3);
+        assert!(remaining.iter().any(|s| s.id == original.id));
+        assert_eq!(
+            original_snapshot(home.path()).unwrap().map(|s| s.id),
+            Some(original.id)
+        );
+    }
+
+    #[test]
+    fn test_import_legacy_backup() {
+        let home = tempfile::tempdir().unwrap();
+        write(home.path(), ".ccconfig/claude_backup/settings.json", "{}");
+        write(
+            home.path(),
+            ".ccconfig/claude_backup/.credentials.json",
+            "{}",
+        );
+
+        let snapshot = import_legacy_backup(home.path()).unwrap().unwrap();
+
+        assert!(snapshot.pinned);
+        assert_eq!(snapshot.files.len(), 1);
+        assert_eq!(snapshot.files[0].path, ".claude/settings.json");
+        assert!(import_legacy_backup(home.path()).unwrap().is_none());
+    }
+}
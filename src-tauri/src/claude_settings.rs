@@ -0,0 +1,512 @@
+//! Typed model of Claude Code's settings.json
+//!
+//! Covers the keys documented in specs/claude-code-settings-doc.md. Unknown
+//! keys are kept in `extra` so settings written by newer Claude Code versions
+//! survive a round trip. `validate_settings` checks a raw value before it is
+//! written and reports every problem with the JSON path it was found at. Only
+//! type mismatches on known keys block a save; unknown keys that look like a
+//! typo of a known one come back as warnings.
+
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ClaudeSettings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_key_helper: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cleanup_period_days: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub env: Option<BTreeMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include_co_authored_by: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub permissions: Option<Permissions>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hooks: Option<BTreeMap<String, Vec<HookMatcher>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disable_all_hooks: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status_line: Option<StatusLine>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_style: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub force_login_method: Option<ForceLoginMethod>,
+    #[serde(rename = "forceLoginOrgUUID", skip_serializing_if = "Option::is_none")]
+    pub force_login_org_uuid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enable_all_project_mcp_servers: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled_mcpjson_servers: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disabled_mcpjson_servers: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub use_enterprise_mcp_config_only: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aws_auth_refresh: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aws_credential_export: Option<String>,
+    /// Keys this model doesn't know about, kept as-is
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Permissions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ask: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deny: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub additional_directories: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_mode: Option<PermissionMode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disable_bypass_permissions_mode: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum PermissionMode {
+    Default,
+    AcceptEdits,
+    Plan,
+    BypassPermissions,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ForceLoginMethod {
+    Claudeai,
+    Console,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct HookMatcher {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub matcher: Option<String>,
+    pub hooks: Vec<HookCommand>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct HookCommand {
+    #[serde(rename = "type")]
+    pub hook_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+    /// e.g. the `__ccfoundation__` marker on hooks this app installs
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct StatusLine {
+    #[serde(rename = "type")]
+    pub status_line_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub padding: Option<u32>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+const SETTINGS_KEYS: [&str; 18] = [
+    "apiKeyHelper",
+    "cleanupPeriodDays",
+    "env",
+    "includeCoAuthoredBy",
+    "permissions",
+    "hooks",
+    "disableAllHooks",
+    "model",
+    "statusLine",
+    "outputStyle",
+    "forceLoginMethod",
+    "forceLoginOrgUUID",
+    "enableAllProjectMcpServers",
+    "enabledMcpjsonServers",
+    "disabledMcpjsonServers",
+    "useEnterpriseMcpConfigOnly",
+    "awsAuthRefresh",
+    "awsCredentialExport",
+];
+
+const PERMISSIONS_KEYS: [&str; 6] = [
+    "allow",
+    "ask",
+    "deny",
+    "additionalDirectories",
+    "defaultMode",
+    "disableBypassPermissionsMode",
+];
+
+/// A single problem found in a settings value
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct ValidationError {
+    /// JSON path of the offending value, e.g. `$.permissions.allow[0]`
+    pub path: String,
+    pub message: String,
+}
+
+/// Checked value and its warnings, or the errors that stop a save
+pub type Validation<T> = Result<(T, Vec<ValidationError>), Vec<ValidationError>>;
+
+/// Error returned by commands that validate settings before writing them
+#[derive(serde::Serialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum SettingsError {
+    Invalid {
+        message: String,
+        errors: Vec<ValidationError>,
+    },
+    Other(String),
+}
+
+impl From<String> for SettingsError {
+    fn from(message: String) -> Self {
+        SettingsError::Other(message)
+    }
+}
+
+impl From<&str> for SettingsError {
+    fn from(message: &str) -> Self {
+        SettingsError::Other(message.to_string())
+    }
+}
+
+impl From<Vec<ValidationError>> for SettingsError {
+    fn from(errors: Vec<ValidationError>) -> Self {
+        SettingsError::Invalid {
+            message: format!("Settings are invalid ({} problems)", errors.len()),
+            errors,
+        }
+    }
+}
+
+/// Check `value` against the settings schema, collecting every problem found
+pub fn validate_settings(value: &Value) -> Validation<ClaudeSettings> {
+    let mut errors = Vec::new();
+    let mut warnings = Vec::new();
+
+    let Some(obj) = value.as_object() else {
+        return Err(vec![error_at("$", "Settings must be a JSON object")]);
+    };
+
+    for (key, value) in obj {
+        let path = format!("$.{}", key);
+        match key.as_str() {
+            "apiKeyHelper"
+            | "model"
+            | "outputStyle"
+            | "forceLoginOrgUUID"
+            | "awsAuthRefresh"
+            | "awsCredentialExport" => check::<String>(value, &path, &mut errors),
+            "includeCoAuthoredBy"
+            | "disableAllHooks"
+            | "enableAllProjectMcpServers"
+            | "useEnterpriseMcpConfigOnly" => check::<bool>(value, &path, &mut errors),
+            "cleanupPeriodDays" => check::<u32>(value, &path, &mut errors),
+            "env" => check::<BTreeMap<String, String>>(value, &path, &mut errors),
+            "enabledMcpjsonServers" | "disabledMcpjsonServers" => {
+                check::<Vec<String>>(value, &path, &mut errors)
+            }
+            "forceLoginMethod" => check::<ForceLoginMethod>(value, &path, &mut errors),
+            "permissions" => validate_permissions(value, &path, &mut errors, &mut warnings),
+            "hooks" => validate_hooks(value, &path, &mut errors),
+            "statusLine" => validate_status_line(value, &path, &mut errors),
+            _ => check_unknown_key(key, &SETTINGS_KEYS, &path, &mut warnings),
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    serde_json::from_value(value.clone())
+        .map(|settings| (settings, warnings))
+        .map_err(|e| vec![error_at("$", e.to_string())])
+}
+
+fn error_at(path: &str, message: impl Into<String>) -> ValidationError {
+    ValidationError {
+        path: path.to_string(),
+        message: message.into(),
+    }
+}
+
+/// Deserialize `value` as `T`, recording where inside it deserialization failed
+fn check<T: DeserializeOwned>(value: &Value, path: &str, errors: &mut Vec<ValidationError>) {
+    if let Err(e) = serde_path_to_error::deserialize::<_, T>(value.clone()) {
+        let mut full_path = path.to_string();
+        for segment in e.path().iter() {
+            match segment {
+                serde_path_to_error::Segment::Seq { index } => {
+                    full_path.push_str(&format!("[{}]", index))
+                }
+                serde_path_to_error::Segment::Map { key } => {
+                    full_path.push_str(&format!(".{}", key))
+                }
+                _ => {}
+            }
+        }
+        errors.push(error_at(&full_path, e.into_inner().to_string()));
+    }
+}
+
+fn expect_object<'a>(
+    value: &'a Value,
+    path: &str,
+    errors: &mut Vec<ValidationError>,
+) -> Option<&'a Map<String, Value>> {
+    let obj = value.as_object();
+    if obj.is_none() {
+        errors.push(error_at(path, "Expected an object"));
+    }
+    obj
+}
+
+/// Unknown keys are allowed, but ones that look like a typo of a known key get a warning
+fn check_unknown_key(key: &str, known: &[&str], path: &str, warnings: &mut Vec<ValidationError>) {
+    let suggestion = known.iter().find(|candidate| {
+        candidate.eq_ignore_ascii_case(key)
+            || edit_distance(&candidate.to_lowercase(), &key.to_lowercase()) <= 2
+    });
+
+    if let Some(suggestion) = suggestion {
+        warnings.push(error_at(
+            path,
+            format!(
+                "Unknown setting \"{}\", did you mean \"{}\"?",
+                key, suggestion
+            ),
+        ));
+    }
+}
+
+fn validate_permissions(
+    value: &Value,
+    path: &str,
+    errors: &mut Vec<ValidationError>,
+    warnings: &mut Vec<ValidationError>,
+) {
+    let Some(obj) = expect_object(value, path, errors) else {
+        return;
+    };
+
+    for (key, value) in obj {
+        let path = format!("{}.{}", path, key);
+        match key.as_str() {
+            "allow" | "ask" | "deny" | "additionalDirectories" => {
+                check::<Vec<String>>(value, &path, errors)
+            }
+            "defaultMode" => check::<PermissionMode>(value, &path, errors),
+            "disableBypassPermissionsMode" => check::<String>(value, &path, errors),
+            _ => check_unknown_key(key, &PERMISSIONS_KEYS, &path, warnings),
+        }
+    }
+}
+
+fn validate_hooks(value: &Value, path: &str, errors: &mut Vec<ValidationError>) {
+    let Some(obj) = expect_object(value, path, errors) else {
+        return;
+    };
+
+    for (event, matchers) in obj {
+        let event_path = format!("{}.{}", path, event);
+        let Some(matchers) = matchers.as_array() else {
+            errors.push(error_at(&event_path, "Expected an array of hook matchers"));
+            continue;
+        };
+
+        for (i, matcher) in matchers.iter().enumerate() {
+            let matcher_path = format!("{}[{}]", event_path, i);
+            let Some(matcher_obj) = expect_object(matcher, &matcher_path, errors) else {
+                continue;
+            };
+
+            if let Some(pattern) = matcher_obj.get("matcher") {
+                check::<String>(pattern, &format!("{}.matcher", matcher_path), errors);
+            }
+
+            let hooks_path = format!("{}.hooks", matcher_path);
+            let Some(hooks) = matcher_obj.get("hooks").and_then(|h| h.as_array()) else {
+                errors.push(error_at(&hooks_path, "Expected an array of hooks"));
+                continue;
+            };
+
+            for (j, hook) in hooks.iter().enumerate() {
+                validate_hook_command(hook, &format!("{}[{}]", hooks_path, j), errors);
+            }
+        }
+    }
+}
+
+fn validate_hook_command(value: &Value, path: &str, errors: &mut Vec<ValidationError>) {
+    let Some(obj) = expect_object(value, path, errors) else {
+        return;
+    };
+
+    match obj.get("type").and_then(|t| t.as_str()) {
+        Some("command") => match obj.get("command") {
+            Some(command) => check::<String>(command, &format!("{}.command", path), errors),
+            None => errors.push(error_at(
+                &format!("{}.command", path),
+                "Command hooks need a command",
+            )),
+        },
+        Some(_) => {}
+        None => errors.push(error_at(&format!("{}.type", path), "Hook type is required")),
+    }
+
+    if let Some(timeout) = obj.get("timeout") {
+        check::<u64>(timeout, &format!("{}.timeout", path), errors);
+    }
+}
+
+fn validate_status_line(value: &Value, path: &str, errors: &mut Vec<ValidationError>) {
+    let Some(obj) = expect_object(value, path, errors) else {
+        return;
+    };
+
+    match obj.get("type") {
+        Some(status_type) => check::<String>(status_type, &format!("{}.type", path), errors),
+        None => errors.push(error_at(
+            &format!("{}.type", path),
+            "Status line type is required",
+        )),
+    }
+    if let Some(command) = obj.get("command") {
+        check::<String>(command, &format!("{}.command", path), errors);
+    }
+    if let Some(padding) = obj.get("padding") {
+        check::<u32>(padding, &format!("{}.padding", path), errors);
+    }
+}
+
+/// Levenshtein distance, used to spot misspelled keys
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b_chars: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b_chars.len()).collect();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b_chars.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+
+    previous[b_chars.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_validate_settings_accepts_documented_keys() {
+        let settings = json!({
+            "model": "opus",
+            "env": { "ANTHROPIC_BASE_URL": "http://proxy" },
+            "permissions": { "allow": ["Bash(ls)"], "defaultMode": "acceptEdits" },
+            "statusLine": { "type": "command", "command": "~/.claude/statusline.sh" },
+            "cleanupPeriodDays": 20,
+            "hooks": {
+                "Stop": [{ "hooks": [{ "type": "command", "command": "echo", "__ccfoundation__": true }] }]
+            }
+        });
+
+        let (parsed, warnings) = validate_settings(&settings).unwrap();
+
+        assert!(warnings.is_empty());
+        assert_eq!(parsed.model.as_deref(), Some("opus"));
+        assert_eq!(
+            parsed.permissions.unwrap().default_mode,
+            Some(PermissionMode::AcceptEdits)
+        );
+        assert_eq!(
+            parsed.hooks.unwrap()["Stop"][0].hooks[0].extra["__ccfoundation__"],
+            json!(true)
+        );
+    }
+
+    #[test]
+    fn test_validate_settings_keeps_unknown_keys() {
+        let settings = json!({ "model": "opus", "someFutureSetting": { "a": 1 } });
+
+        let (parsed, warnings) = validate_settings(&settings).unwrap();
+
+        assert!(warnings.is_empty());
+        assert_eq!(parsed.extra["someFutureSetting"], json!({ "a": 1 }));
+        assert_eq!(serde_json::to_value(&parsed).unwrap(), settings);
+    }
+
+    #[test]
+    fn test_validate_settings_reports_paths() {
+        let settings = json!({
+            "permissions": { "allow": ["Bash(ls)", 5], "defaultMode": "yolo" },
+            "env": { "API_TIMEOUT_MS": 1000 },
+            "cleanupPeriodDays": "20"
+        });
+
+        let errors = validate_settings(&settings).unwrap_err();
+        let paths: Vec<&str> = errors.iter().map(|e| e.path.as_str()).collect();
+
+        assert_eq!(errors.len(), 4);
+        assert!(paths.contains(&"$.permissions.allow[1]"));
+        assert!(paths.contains(&"$.permissions.defaultMode"));
+        assert!(paths.contains(&"$.env.API_TIMEOUT_MS"));
+        assert!(paths.contains(&"$.cleanupPeriodDays"));
+    }
+
+    #[test]
+    fn test_validate_settings_warns_about_typos() {
+        let settings = json!({ "permisions": { "allow": [] }, "permissions": { "alow": [] } });
+
+        let (parsed, warnings) = validate_settings(&settings).unwrap();
+
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(warnings[0].path, "$.permisions");
+        assert!(warnings[0].message.contains("\"permissions\""));
+        assert_eq!(warnings[1].path, "$.permissions.alow");
+        assert_eq!(parsed.extra["permisions"], json!({ "allow": [] }));
+    }
+
+    #[test]
+    fn test_validate_settings_errors_outrank_typo_warnings() {
+        let settings = json!({ "modle": "opus", "cleanupPeriodDays": "20" });
+
+        let errors = validate_settings(&settings).unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, "$.cleanupPeriodDays");
+    }
+
+    #[test]
+    fn test_validate_settings_rejects_malformed_hooks() {
+        let settings = json!({
+            "hooks": { "PreToolUse": [{ "matcher": "Bash", "hooks": [{ "type": "command" }] }] }
+        });
+
+        let errors = validate_settings(&settings).unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, "$.hooks.PreToolUse[0].hooks[0].command");
+    }
+}
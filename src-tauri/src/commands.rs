@@ -1,6 +1,40 @@
+use crate::backups::{self, BackupFileDiff, BackupSnapshot, RestoreReport, RetentionPolicy};
+use crate::bundle::{
+    self, BundleItemKind, BundleManifest, BundleSelection, ConflictStrategy, ImportAction,
+    ImportOptions, ImportReport,
+};
+use crate::claude_settings::{validate_settings, SettingsError, ValidationError};
+use crate::hook_auth::{self, SECRET_HEADER};
+use crate::hook_log::{self, HookLogPage, HookLogQuery};
+use crate::hook_notifications::{self, NotificationRule, NotificationSettings, QuietHours};
+use crate::hook_policy::{self, PolicyRule};
+use crate::hook_server::{self, HookEndpoint, HookServerStatus};
+use crate::hook_settings::{self, HookSettings};
+use crate::journal::{self, JournalEntry};
+use crate::managed_file::{self, Snapshot};
+use crate::mcp::import::{self as mcp_import, McpImportFormat, McpImportPreview};
+use crate::mcp::probe::{self, McpProbeResult};
+use crate::mcp::project::{self as project_mcp, McpMigrationReport};
+use crate::mcp::resolver::{self, McpScope, ResolvedMcpServer};
+use crate::mcp::server::{invalid_server_error, validate_changed_servers, validate_server};
+use crate::mcp::stash::{self, DisabledMcpServer, McpServerToggle};
+use crate::mcp::transfer::{
+    self, McpLocation, McpTransferItem, McpTransferMode, McpTransferResult,
+};
+use crate::settings_diff::{diff_settings, SettingsChange};
+use crate::settings_drift::{
+    detect_drift, detect_store_drift, reapply_store, revert_drift, strip_managed_hooks,
+};
+use crate::settings_merge::{apply_settings, KeyPath};
+use crate::store_history::{self, StoreRevision};
+use crate::store_inheritance::{
+    resolve_chain, resolve_settings, strip_inherited, ResolvedSettings,
+};
+use crate::vault::{self, SecretInfo, VaultStatus};
 use serde_json::Value;
 // sha2 no longer needed since old project config system was removed
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use tauri_plugin_updater::UpdaterExt;
 use uuid::Uuid;
 
@@ -39,13 +73,17 @@ pub async fn initialize_app_config() -> Result<(), String> {
     );
 
     if claude_dir.exists() {
+        // Older versions kept a single untimestamped backup; keep it as the original snapshot
+        if let Some(snapshot) = backups::import_legacy_backup(&home_dir)? {
+            println!("Imported legacy Claude backup as snapshot {}", snapshot.id);
+        }
+
         // Check if we already have a backup
-        let backup_dir = app_config_path.join("claude_backup");
-        if backup_dir.exists() {
+        if !backups::list_snapshots(&home_dir)?.is_empty() {
             println!("Claude backup already exists, skipping backup");
         } else {
             println!("Claude directory exists but no backup found, backing up...");
-            if let Err(e) = backup_claude_configs_internal(&app_config_path, &claude_dir) {
+            if let Err(e) = backups::create_snapshot(&home_dir, "Original configuration") {
                 return Err(format!("Failed to backup Claude configs: {}", e));
             }
             println!("Claude configs backed up successfully");
@@ -72,18 +110,38 @@ pub struct ConfigStore {
     pub created_at: u64,
     pub settings: Value,
     pub using: bool,
+    /// Store whose settings this one inherits and overrides
+    #[serde(rename = "parentId", default, skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<String>,
+}
+
+/// A saved store plus the problems in its settings that didn't block the save
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct SavedConfigStore {
+    #[serde(flatten)]
+    pub store: ConfigStore,
+    pub warnings: Vec<ValidationError>,
 }
 
+/// A server as saved, so definitions that no longer validate can still be
+/// listed and fixed. New definitions go through `mcp::server::validate_server`.
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
 pub struct McpServer {
     #[serde(flatten)]
     pub config: serde_json::Value,
 }
 
-#[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[derive(serde::Serialize, serde::Deserialize, Debug, Default)]
 pub struct StoresData {
     pub configs: Vec<ConfigStore>,
     pub distinct_id: Option<String>,
+    /// Key paths the active store contributed to ~/.claude/settings.json
+    #[serde(
+        rename = "appliedPaths",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub applied_paths: Vec<KeyPath>,
 }
 
 #[tauri::command]
@@ -127,20 +185,28 @@ pub async fn read_config_file(config_type: String) -> Result<ConfigFile, String>
 }
 
 #[tauri::command]
-pub async fn write_config_file(config_type: String, content: Value) -> Result<(), String> {
+pub async fn write_config_file(
+    config_type: String,
+    content: Value,
+) -> Result<Vec<ValidationError>, SettingsError> {
     let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
 
     let path = match config_type.as_str() {
         "user" => home_dir.join(".claude/settings.json"),
-        _ => return Err("Cannot write to enterprise configuration files".to_string()),
+        _ => return Err("Cannot write to enterprise configuration files".into()),
     };
 
+    let (_, warnings) = validate_settings(&content)?;
+
+    journal::record("write_config_file", &[&path])?;
+
     let json_content = serde_json::to_string_pretty(&content)
         .map_err(|e| format!("Failed to serialize JSON: {}", e))?;
 
-    std::fs::write(&path, json_content).map_err(|e| format!("Failed to write file: {}", e))?;
+    managed_file::write(&path, &json_content)
+        .map_err(|e| format!("Failed to write file: {}", e))?;
 
-    Ok(())
+    Ok(warnings)
 }
 
 #[tauri::command]
@@ -210,53 +276,144 @@ pub async fn create_app_config_dir() -> Result<(), String> {
     Ok(())
 }
 
-fn backup_claude_configs_internal(
-    app_config_path: &std::path::Path,
-    claude_dir: &std::path::Path,
-) -> Result<(), String> {
-    // Create backup directory
-    let backup_dir = app_config_path.join("claude_backup");
-
-    std::fs::create_dir_all(&backup_dir)
-        .map_err(|e| format!("Failed to create backup directory: {}", e))?;
-
-    // Copy all files from .claude directory to backup
-    for entry in std::fs::read_dir(claude_dir)
-        .map_err(|e| format!("Failed to read Claude directory: {}", e))?
-    {
-        let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
-        let source_path = entry.path();
-        let file_name = source_path.file_name().ok_or("Invalid file name")?;
-        let dest_path = backup_dir.join(file_name);
+/// Take a timestamped snapshot of ~/.claude and ~/.claude.json
+#[tauri::command]
+pub async fn backup_claude_configs() -> Result<BackupSnapshot, String> {
+    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
+    let claude_dir = home_dir.join(".claude");
 
-        if source_path.is_file() {
-            std::fs::copy(&source_path, &dest_path)
-                .map_err(|e| format!("Failed to copy file {}: {}", source_path.display(), e))?;
-        }
+    if !claude_dir.exists() {
+        return Err("Claude configuration directory does not exist".to_string());
     }
 
-    Ok(())
+    backups::create_snapshot(&home_dir, "Manual backup")
 }
 
+/// List backup snapshots, newest first
 #[tauri::command]
-pub async fn backup_claude_configs() -> Result<(), String> {
+pub async fn list_backups() -> Result<Vec<BackupSnapshot>, String> {
     let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
-    let claude_dir = home_dir.join(".claude");
-    let app_config_path = home_dir.join(APP_CONFIG_DIR);
+    backups::list_snapshots(&home_dir)
+}
 
-    if !claude_dir.exists() {
-        return Err("Claude configuration directory does not exist".to_string());
-    }
+/// Files that changed since a snapshot was taken
+#[tauri::command]
+pub async fn diff_backup(snapshot_id: String) -> Result<Vec<BackupFileDiff>, String> {
+    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
+    backups::diff_snapshot(&home_dir, &snapshot_id)
+}
 
-    // Ensure app config directory exists
-    std::fs::create_dir_all(&app_config_path)
-        .map_err(|e| format!("Failed to create app config directory: {}", e))?;
+/// Restore a whole snapshot, or only `paths` from it
+#[tauri::command]
+pub async fn restore_backup(
+    snapshot_id: String,
+    paths: Option<Vec<String>>,
+) -> Result<RestoreReport, String> {
+    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
+    backups::restore_snapshot(&home_dir, &snapshot_id, paths.as_deref())
+}
+
+#[tauri::command]
+pub async fn get_backup_policy() -> Result<RetentionPolicy, String> {
+    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
+    backups::read_policy(&home_dir)
+}
 
-    backup_claude_configs_internal(&app_config_path, &claude_dir)
+/// Save the retention policy and prune snapshots it no longer allows
+#[tauri::command]
+pub async fn set_backup_policy(policy: RetentionPolicy) -> Result<Vec<String>, String> {
+    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
+    backups::write_policy(&home_dir, &policy)?;
+    backups::apply_retention(&home_dir, &policy)
 }
 
 // Store management functions
 
+/// Read stores.json, along with the snapshot needed to write it back safely
+pub(crate) fn read_stores_data(
+    stores_file: &std::path::Path,
+) -> Result<(StoresData, Snapshot), String> {
+    let snapshot = managed_file::read(stores_file)?;
+
+    let stores_data = match snapshot.content() {
+        Some(content) => serde_json::from_str(content)
+            .map_err(|e| format!("Failed to parse stores file: {}", e))?,
+        None => StoresData::default(),
+    };
+
+    Ok((stores_data, snapshot))
+}
+
+/// Write stores.json, failing if it changed since `snapshot` was read
+pub(crate) fn write_stores_data<T: serde::Serialize>(
+    stores_file: &std::path::Path,
+    snapshot: &Snapshot,
+    stores_data: &T,
+) -> Result<(), String> {
+    let json_content = serde_json::to_string_pretty(stores_data)
+        .map_err(|e| format!("Failed to serialize stores: {}", e))?;
+
+    managed_file::write_if_unchanged(stores_file, snapshot, &json_content)
+        .map_err(|e| format!("Failed to write stores file: {}", e))
+}
+
+/// Deep-merge a store's settings into ~/.claude/settings.json, first removing the
+/// keys the previously applied store contributed. Secret references are
+/// resolved here, the only place their plaintext is written. Records the new
+/// footprint in `stores_data`, so callers must write stores.json afterwards.
+fn apply_store_settings(
+    home_dir: &std::path::Path,
+    stores_data: &mut StoresData,
+    settings: &Value,
+) -> Result<(), String> {
+    let user_settings_path = home_dir.join(".claude/settings.json");
+    let settings = &vault::resolve_refs(settings)?;
+
+    // Merge against the live file, re-merging if Claude Code rewrites it meanwhile
+    let applied_paths = managed_file::update_json(&user_settings_path, |existing_settings| {
+        let (merged_settings, applied_paths) =
+            apply_settings(existing_settings, &stores_data.applied_paths, settings);
+        *existing_settings = merged_settings;
+        Ok(applied_paths)
+    })
+    .map_err(|e| format!("Failed to write user settings: {}", e))?;
+
+    stores_data.applied_paths = applied_paths;
+    Ok(())
+}
+
+/// Re-apply the active store's resolved settings if `changed_store_id` is part
+/// of its inheritance chain
+fn reapply_if_inherited(
+    home_dir: &std::path::Path,
+    stores_data: &mut StoresData,
+    changed_store_id: &str,
+) -> Result<(), String> {
+    let Some(active_id) = stores_data
+        .configs
+        .iter()
+        .find(|store| store.using)
+        .map(|store| store.id.clone())
+    else {
+        return Ok(());
+    };
+
+    let resolved = resolve_settings(&stores_data.configs, &active_id)?;
+    if resolved.chain.iter().any(|id| id == changed_store_id) {
+        apply_store_settings(home_dir, stores_data, &resolved.settings)?;
+    }
+
+    Ok(())
+}
+
+/// The files store commands change, for the undo journal
+fn store_paths(home_dir: &std::path::Path) -> Vec<PathBuf> {
+    vec![
+        home_dir.join(APP_CONFIG_DIR).join("stores.json"),
+        home_dir.join(".claude/settings.json"),
+    ]
+}
+
 #[tauri::command]
 pub async fn get_stores() -> Result<Vec<ConfigStore>, String> {
     let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
@@ -267,11 +424,7 @@ pub async fn get_stores() -> Result<Vec<ConfigStore>, String> {
         return Ok(vec![]);
     }
 
-    let content = std::fs::read_to_string(&stores_file)
-        .map_err(|e| format!("Failed to read stores file: {}", e))?;
-
-    let stores_data: StoresData = serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse stores file: {}", e))?;
+    let (stores_data, _) = read_stores_data(&stores_file)?;
 
     let mut stores_vec = stores_data.configs;
     // Sort by createdAt in ascending order (oldest first)
@@ -285,28 +438,22 @@ pub async fn create_config(
     id: String,
     title: String,
     settings: Value,
-) -> Result<ConfigStore, String> {
+    parent_id: Option<String>,
+) -> Result<SavedConfigStore, SettingsError> {
+    let (_, warnings) = validate_settings(&settings)?;
+
     let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
     let app_config_path = home_dir.join(APP_CONFIG_DIR);
     let stores_file = app_config_path.join("stores.json");
 
+    journal::record("create_config", &store_paths(&home_dir))?;
+
     // Ensure app config directory exists
     std::fs::create_dir_all(&app_config_path)
         .map_err(|e| format!("Failed to create app config directory: {}", e))?;
 
     // Read existing stores
-    let mut stores_data = if stores_file.exists() {
-        let content = std::fs::read_to_string(&stores_file)
-            .map_err(|e| format!("Failed to read stores file: {}", e))?;
-
-        serde_json::from_str::<StoresData>(&content)
-            .map_err(|e| format!("Failed to parse stores file: {}", e))?
-    } else {
-        StoresData {
-            configs: vec![],
-            distinct_id: None,
-        }
-    };
+    let (mut stores_data, stores_snapshot) = read_stores_data(&stores_file)?;
 
     // Determine if this should be the active store (true if no other stores exist)
     let should_be_active = stores_data.configs.is_empty();
@@ -332,58 +479,21 @@ pub async fn create_config(
                     .as_secs(),
                 settings: settings_json,
                 using: false, // Original Config should not be active by default
+                parent_id: None,
             };
 
+            if let Err(e) =
+                store_history::record_revision(&original_store.id, &original_store.settings, None)
+            {
+                eprintln!("Warning: Failed to record revision: {}", e);
+            }
+
             // Add the Original Config store to the collection
             stores_data.configs.push(original_store);
             println!("Created Original Config store from existing settings.json");
         }
     }
 
-    // If this is the first store (and therefore active), write its settings to the user's actual settings.json with partial update
-    if should_be_active {
-        let user_settings_path = home_dir.join(".claude/settings.json");
-
-        // Create .claude directory if it doesn't exist
-        if let Some(parent) = user_settings_path.parent() {
-            std::fs::create_dir_all(parent)
-                .map_err(|e| format!("Failed to create .claude directory: {}", e))?;
-        }
-
-        // Read existing settings if file exists, otherwise start with empty object
-        let mut existing_settings = if user_settings_path.exists() {
-            let content = std::fs::read_to_string(&user_settings_path)
-                .map_err(|e| format!("Failed to read existing settings: {}", e))?;
-            serde_json::from_str(&content)
-                .map_err(|e| format!("Failed to parse existing settings: {}", e))?
-        } else {
-            serde_json::Value::Object(serde_json::Map::new())
-        };
-
-        // Merge the new settings into existing settings (partial update)
-        if let Some(settings_obj) = settings.as_object() {
-            if let Some(existing_obj) = existing_settings.as_object_mut() {
-                // Update only the keys present in the stored settings
-                for (key, value) in settings_obj {
-                    existing_obj.insert(key.clone(), value.clone());
-                }
-            } else {
-                // If existing settings is not an object, replace it entirely
-                existing_settings = settings.clone();
-            }
-        } else {
-            // If stored settings is not an object, replace existing entirely
-            existing_settings = settings.clone();
-        }
-
-        // Write the merged settings back to file
-        let json_content = serde_json::to_string_pretty(&existing_settings)
-            .map_err(|e| format!("Failed to serialize merged settings: {}", e))?;
-
-        std::fs::write(&user_settings_path, json_content)
-            .map_err(|e| format!("Failed to write user settings: {}", e))?;
-    }
-
     // Create new store
     let new_store = ConfigStore {
         id: id.clone(),
@@ -394,24 +504,37 @@ pub async fn create_config(
             .as_secs(),
         settings,
         using: should_be_active,
+        parent_id,
     };
 
     // Add store to collection
     stores_data.configs.push(new_store.clone());
 
+    // Also checks that the parent exists
+    let resolved = resolve_settings(&stores_data.configs, &id)?;
+
+    // If this is the first store (and therefore active), write its settings to the user's actual settings.json
+    if should_be_active {
+        apply_store_settings(&home_dir, &mut stores_data, &resolved.settings)?;
+    }
+
     // Write back to stores file
-    let json_content = serde_json::to_string_pretty(&stores_data)
-        .map_err(|e| format!("Failed to serialize stores: {}", e))?;
+    write_stores_data(&stores_file, &stores_snapshot, &stores_data)?;
 
-    std::fs::write(&stores_file, json_content)
-        .map_err(|e| format!("Failed to write stores file: {}", e))?;
+    // Start the revision history of the new store
+    if let Err(e) = store_history::record_revision(&new_store.id, &new_store.settings, None) {
+        eprintln!("Warning: Failed to record revision: {}", e);
+    }
 
     // Automatically unlock CC extension when creating new config
     if let Err(e) = unlock_cc_ext().await {
         eprintln!("Warning: Failed to unlock CC extension: {}", e);
     }
 
-    Ok(new_store)
+    Ok(SavedConfigStore {
+        store: new_store,
+        warnings,
+    })
 }
 
 #[tauri::command]
@@ -420,16 +543,26 @@ pub async fn delete_config(store_id: String) -> Result<(), String> {
     let app_config_path = home_dir.join(APP_CONFIG_DIR);
     let stores_file = app_config_path.join("stores.json");
 
+    journal::record("delete_config", &store_paths(&home_dir))?;
+
     if !stores_file.exists() {
         return Err("Stores file does not exist".to_string());
     }
 
     // Read existing stores
-    let content = std::fs::read_to_string(&stores_file)
-        .map_err(|e| format!("Failed to read stores file: {}", e))?;
+    let (mut stores_data, stores_snapshot) = read_stores_data(&stores_file)?;
 
-    let mut stores_data: StoresData = serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse stores file: {}", e))?;
+    // Children would silently lose their inherited settings
+    if let Some(child) = stores_data
+        .configs
+        .iter()
+        .find(|store| store.parent_id.as_deref() == Some(store_id.as_str()))
+    {
+        return Err(format!(
+            "Store is the parent of '{}', change its parent first",
+            child.title
+        ));
+    }
 
     // Find and remove store by ID
     let original_len = stores_data.configs.len();
@@ -440,11 +573,11 @@ pub async fn delete_config(store_id: String) -> Result<(), String> {
     }
 
     // Write back to file
-    let json_content = serde_json::to_string_pretty(&stores_data)
-        .map_err(|e| format!("Failed to serialize stores: {}", e))?;
+    write_stores_data(&stores_file, &stores_snapshot, &stores_data)?;
 
-    std::fs::write(&stores_file, json_content)
-        .map_err(|e| format!("Failed to write stores file: {}", e))?;
+    if let Err(e) = store_history::delete_revisions(&store_id) {
+        eprintln!("Warning: Failed to delete store revisions: {}", e);
+    }
 
     Ok(())
 }
@@ -455,16 +588,14 @@ pub async fn set_using_config(store_id: String) -> Result<(), String> {
     let app_config_path = home_dir.join(APP_CONFIG_DIR);
     let stores_file = app_config_path.join("stores.json");
 
+    journal::record("set_using_config", &store_paths(&home_dir))?;
+
     if !stores_file.exists() {
         return Err("Stores file does not exist".to_string());
     }
 
     // Read existing stores
-    let content = std::fs::read_to_string(&stores_file)
-        .map_err(|e| format!("Failed to read stores file: {}", e))?;
-
-    let mut stores_data: StoresData = serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse stores file: {}", e))?;
+    let (mut stores_data, stores_snapshot) = read_stores_data(&stores_file)?;
 
     // Find the store and check if it exists
     let store_found = stores_data.configs.iter().any(|store| store.id == store_id);
@@ -473,59 +604,13 @@ pub async fn set_using_config(store_id: String) -> Result<(), String> {
     }
 
     // Set all stores to not using, then set the selected one to using
-    let mut selected_store_settings: Option<Value> = None;
     for store in &mut stores_data.configs {
-        if store.id == store_id {
-            store.using = true;
-            selected_store_settings = Some(store.settings.clone());
-        } else {
-            store.using = false;
-        }
+        store.using = store.id == store_id;
     }
 
-    // Write the selected store's settings to the user's actual settings.json with partial update
-    if let Some(settings) = selected_store_settings {
-        let user_settings_path = home_dir.join(".claude/settings.json");
-
-        // Create .claude directory if it doesn't exist
-        if let Some(parent) = user_settings_path.parent() {
-            std::fs::create_dir_all(parent)
-                .map_err(|e| format!("Failed to create .claude directory: {}", e))?;
-        }
-
-        // Read existing settings if file exists, otherwise start with empty object
-        let mut existing_settings = if user_settings_path.exists() {
-            let content = std::fs::read_to_string(&user_settings_path)
-                .map_err(|e| format!("Failed to read existing settings: {}", e))?;
-            serde_json::from_str(&content)
-                .map_err(|e| format!("Failed to parse existing settings: {}", e))?
-        } else {
-            serde_json::Value::Object(serde_json::Map::new())
-        };
-
-        // Merge the new settings into existing settings (partial update)
-        if let Some(settings_obj) = settings.as_object() {
-            if let Some(existing_obj) = existing_settings.as_object_mut() {
-                // Update only the keys present in the stored settings
-                for (key, value) in settings_obj {
-                    existing_obj.insert(key.clone(), value.clone());
-                }
-            } else {
-                // If existing settings is not an object, replace it entirely
-                existing_settings = settings.clone();
-            }
-        } else {
-            // If stored settings is not an object, replace existing entirely
-            existing_settings = settings.clone();
-        }
-
-        // Write the merged settings back to file
-        let json_content = serde_json::to_string_pretty(&existing_settings)
-            .map_err(|e| format!("Failed to serialize merged settings: {}", e))?;
-
-        std::fs::write(&user_settings_path, json_content)
-            .map_err(|e| format!("Failed to write user settings: {}", e))?;
-    }
+    // Write the selected store's settings, including inherited ones, to the user's actual settings.json
+    let resolved = resolve_settings(&stores_data.configs, &store_id)?;
+    apply_store_settings(&home_dir, &mut stores_data, &resolved.settings)?;
 
     // Write back to stores file (with active context update)
     // Parse as generic Value to preserve and update active context
@@ -539,11 +624,7 @@ pub async fn set_using_config(store_id: String) -> Result<(), String> {
         "projectPath": null
     });
 
-    let json_content = serde_json::to_string_pretty(&stores_value)
-        .map_err(|e| format!("Failed to serialize stores: {}", e))?;
-
-    std::fs::write(&stores_file, json_content)
-        .map_err(|e| format!("Failed to write stores file: {}", e))?;
+    write_stores_data(&stores_file, &stores_snapshot, &stores_value)?;
 
     Ok(())
 }
@@ -554,57 +635,57 @@ pub async fn reset_to_original_config() -> Result<(), String> {
     let app_config_path = home_dir.join(APP_CONFIG_DIR);
     let stores_file = app_config_path.join("stores.json");
 
+    journal::record("reset_to_original_config", &store_paths(&home_dir))?;
+
     // Set all stores to not using
     if stores_file.exists() {
-        let content = std::fs::read_to_string(&stores_file)
-            .map_err(|e| format!("Failed to read stores file: {}", e))?;
-
-        let mut stores_data: StoresData = serde_json::from_str(&content)
-            .map_err(|e| format!("Failed to parse stores file: {}", e))?;
+        let (mut stores_data, stores_snapshot) = read_stores_data(&stores_file)?;
 
         // Set all stores to not using
         for store in &mut stores_data.configs {
             store.using = false;
         }
+        // No store contributes to settings.json anymore
+        stores_data.applied_paths.clear();
 
         // Write back to stores file
-        let json_content = serde_json::to_string_pretty(&stores_data)
-            .map_err(|e| format!("Failed to serialize stores: {}", e))?;
-
-        std::fs::write(&stores_file, json_content)
-            .map_err(|e| format!("Failed to write stores file: {}", e))?;
+        write_stores_data(&stores_file, &stores_snapshot, &stores_data)?;
     }
 
-    // Clear env field in settings.json
     let user_settings_path = home_dir.join(".claude/settings.json");
 
-    // Create .claude directory if it doesn't exist
-    if let Some(parent) = user_settings_path.parent() {
-        std::fs::create_dir_all(parent)
-            .map_err(|e| format!("Failed to create .claude directory: {}", e))?;
-    }
-
-    // Read existing settings if file exists, otherwise start with empty object
-    let mut existing_settings = if user_settings_path.exists() {
-        let content = std::fs::read_to_string(&user_settings_path)
-            .map_err(|e| format!("Failed to read existing settings: {}", e))?;
-        serde_json::from_str(&content)
-            .map_err(|e| format!("Failed to parse existing settings: {}", e))?
-    } else {
-        serde_json::Value::Object(serde_json::Map::new())
+    // Restore settings.json from the original snapshot, keeping the app's own hooks
+    let original_settings = match backups::original_snapshot(&home_dir)? {
+        Some(snapshot) => {
+            backups::read_snapshot_file(&home_dir, &snapshot.id, ".claude/settings.json")?
+        }
+        None => None,
     };
 
-    // Set env to empty object
-    if let Some(existing_obj) = existing_settings.as_object_mut() {
-        existing_obj.insert("env".to_string(), serde_json::json!({}));
-    }
-
-    // Write the merged settings back to file
-    let json_content = serde_json::to_string_pretty(&existing_settings)
-        .map_err(|e| format!("Failed to serialize merged settings: {}", e))?;
+    match original_settings {
+        Some(content) => {
+            let original: Value = serde_json::from_slice(&content)
+                .map_err(|e| format!("Failed to parse original settings: {}", e))?;
 
-    std::fs::write(&user_settings_path, json_content)
-        .map_err(|e| format!("Failed to write user settings: {}", e))?;
+            managed_file::update_json(&user_settings_path, |existing_settings| {
+                let changes = detect_drift(&original, existing_settings);
+                *existing_settings = revert_drift(existing_settings, &changes);
+                Ok(())
+            })
+            .map_err(|e| format!("Failed to write user settings: {}", e))?;
+        }
+        None => {
+            // Without a backup of settings.json, fall back to clearing env
+            managed_file::update_json(&user_settings_path, |existing_settings| {
+                // Set env to empty object
+                if let Some(existing_obj) = existing_settings.as_object_mut() {
+                    existing_obj.insert("env".to_string(), serde_json::json!({}));
+                }
+                Ok(())
+            })
+            .map_err(|e| format!("Failed to write user settings: {}", e))?;
+        }
+    }
 
     Ok(())
 }
@@ -624,26 +705,89 @@ pub async fn get_store(store_id: String) -> Result<ConfigStore, String> {
         .ok_or_else(|| format!("Store with id '{}' not found", store_id))
 }
 
+/// Effective settings of a store after inheritance, with the store each key came from
+#[tauri::command]
+pub async fn get_resolved_config(store_id: String) -> Result<ResolvedSettings, String> {
+    let stores = get_stores().await?;
+    resolve_settings(&stores, &store_id)
+}
+
+/// Set or clear the store a store inherits from
+#[tauri::command]
+pub async fn set_config_parent(
+    store_id: String,
+    parent_id: Option<String>,
+) -> Result<ConfigStore, String> {
+    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
+    let app_config_path = home_dir.join(APP_CONFIG_DIR);
+    let stores_file = app_config_path.join("stores.json");
+
+    journal::record("set_config_parent", &store_paths(&home_dir))?;
+
+    if !stores_file.exists() {
+        return Err("Stores file does not exist".to_string());
+    }
+
+    let (mut stores_data, stores_snapshot) = read_stores_data(&stores_file)?;
+
+    let store_index = stores_data
+        .configs
+        .iter()
+        .position(|store| store.id == store_id)
+        .ok_or_else(|| format!("Store with id '{}' not found", store_id))?;
+
+    stores_data.configs[store_index].parent_id = parent_id;
+
+    // Rejects missing parents and cycles before anything is written
+    resolve_chain(&stores_data.configs, &store_id)?;
+
+    reapply_if_inherited(&home_dir, &mut stores_data, &store_id)?;
+
+    write_stores_data(&stores_file, &stores_snapshot, &stores_data)?;
+
+    Ok(stores_data.configs[store_index].clone())
+}
+
+/// Record the revisions of a store change once it's been written. The change
+/// has happened by then, so a failure here is only logged.
+fn record_store_change(
+    store_id: &str,
+    previous_settings: Option<&Value>,
+    settings: &Value,
+    message: Option<String>,
+) {
+    let recorded = previous_settings
+        .map_or(Ok(()), |previous| {
+            store_history::record_revision(store_id, previous, None).map(|_| ())
+        })
+        .and_then(|_| store_history::record_revision(store_id, settings, message));
+
+    if let Err(e) = recorded {
+        eprintln!("Warning: Failed to record revision: {}", e);
+    }
+}
+
 #[tauri::command]
 pub async fn update_config(
     store_id: String,
     title: String,
     settings: Value,
-) -> Result<ConfigStore, String> {
+    message: Option<String>,
+) -> Result<SavedConfigStore, SettingsError> {
+    let (_, warnings) = validate_settings(&settings)?;
+
     let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
     let app_config_path = home_dir.join(APP_CONFIG_DIR);
     let stores_file = app_config_path.join("stores.json");
 
+    journal::record("update_config", &store_paths(&home_dir))?;
+
     if !stores_file.exists() {
-        return Err("Stores file does not exist".to_string());
+        return Err("Stores file does not exist".into());
     }
 
     // Read existing stores
-    let content = std::fs::read_to_string(&stores_file)
-        .map_err(|e| format!("Failed to read stores file: {}", e))?;
-
-    let mut stores_data: StoresData = serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse stores file: {}", e))?;
+    let (mut stores_data, stores_snapshot) = read_stores_data(&stores_file)?;
 
     // Find the store by ID
     let store_index = stores_data
@@ -659,68 +803,603 @@ pub async fn update_config(
     //     }
     // }
 
+    let previous_settings = stores_data.configs[store_index].settings.clone();
+
     // Update the store
     let store = &mut stores_data.configs[store_index];
     store.title = title.clone();
     store.settings = settings.clone();
 
-    // If this store is currently in use, also update the user's settings.json with partial update
-    if store.using {
-        let user_settings_path = home_dir.join(".claude/settings.json");
+    // If this store or one it's inherited by is in use, also update the user's settings.json
+    reapply_if_inherited(&home_dir, &mut stores_data, &store_id)?;
+
+    // Write back to stores file
+    write_stores_data(&stores_file, &stores_snapshot, &stores_data)?;
+
+    // Record the settings replaced (seeds the history of stores created before it
+    // existed), then the new ones, now that the change has been written
+    record_store_change(&store_id, Some(&previous_settings), &settings, message);
+
+    // Automatically unlock CC extension when updating config
+    if let Err(e) = unlock_cc_ext().await {
+        eprintln!("Warning: Failed to unlock CC extension: {}", e);
+    }
+
+    Ok(SavedConfigStore {
+        store: stores_data.configs[store_index].clone(),
+        warnings,
+    })
+}
+
+/// List the revision history of a store, oldest first
+#[tauri::command]
+pub async fn list_config_revisions(store_id: String) -> Result<Vec<StoreRevision>, String> {
+    store_history::read_revisions(&store_id)
+}
+
+/// Diff two revisions of a store
+#[tauri::command]
+pub async fn diff_config_revisions(
+    store_id: String,
+    from_revision: u32,
+    to_revision: u32,
+) -> Result<Vec<SettingsChange>, String> {
+    let from = store_history::get_revision(&store_id, from_revision)?;
+    let to = store_history::get_revision(&store_id, to_revision)?;
+    Ok(diff_settings(&from.settings, &to.settings))
+}
+
+/// Restore a store's settings from a revision, re-applying them if the store is active
+#[tauri::command]
+pub async fn rollback_config(
+    store_id: String,
+    revision: u32,
+    message: Option<String>,
+) -> Result<ConfigStore, String> {
+    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
+    let app_config_path = home_dir.join(APP_CONFIG_DIR);
+    let stores_file = app_config_path.join("stores.json");
+
+    journal::record("rollback_config", &store_paths(&home_dir))?;
+
+    if !stores_file.exists() {
+        return Err("Stores file does not exist".to_string());
+    }
+
+    let target = store_history::get_revision(&store_id, revision)?;
+
+    // Read existing stores
+    let (mut stores_data, stores_snapshot) = read_stores_data(&stores_file)?;
+
+    let store_index = stores_data
+        .configs
+        .iter()
+        .position(|store| store.id == store_id)
+        .ok_or_else(|| format!("Store with id '{}' not found", store_id))?;
+
+    stores_data.configs[store_index].settings = target.settings.clone();
+
+    // Active stores (and their children) go through the same apply path as set_using_config
+    reapply_if_inherited(&home_dir, &mut stores_data, &store_id)?;
+
+    write_stores_data(&stores_file, &stores_snapshot, &stores_data)?;
+
+    // The rollback itself becomes the newest revision
+    let message = message.unwrap_or_else(|| format!("Rollback to revision {}", revision));
+    record_store_change(&store_id, None, &target.settings, Some(message));
+
+    Ok(stores_data.configs[store_index].clone())
+}
+
+/// Drift between the active store and the live ~/.claude/settings.json
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct ConfigDrift {
+    #[serde(rename = "storeId")]
+    pub store_id: String,
+    #[serde(rename = "storeTitle")]
+    pub store_title: String,
+    pub changes: Vec<SettingsChange>,
+}
+
+fn read_live_settings(home_dir: &std::path::Path) -> Result<Value, String> {
+    let snapshot = managed_file::read(&home_dir.join(".claude/settings.json"))?;
+
+    match snapshot.content() {
+        Some(content) if !content.trim().is_empty() => serde_json::from_str(content)
+            .map_err(|e| format!("Failed to parse user settings: {}", e)),
+        _ => Ok(serde_json::json!({})),
+    }
+}
+
+/// Compare the active store with the live settings.json. Returns `None` when
+/// no store is active.
+pub(crate) fn read_config_drift() -> Result<Option<ConfigDrift>, String> {
+    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
+    let stores_file = home_dir.join(APP_CONFIG_DIR).join("stores.json");
+
+    let (stores_data, _) = read_stores_data(&stores_file)?;
+    let Some(store) = stores_data.configs.iter().find(|store| store.using) else {
+        return Ok(None);
+    };
+
+    let resolved = resolve_settings(&stores_data.configs, &store.id)?;
+    let live_settings = read_live_settings(&home_dir)?;
+
+    let applied_paths = &stores_data.applied_paths;
+    let changes = match vault::resolve_refs(&resolved.settings) {
+        Ok(store_settings) => detect_store_drift(&store_settings, &live_settings, applied_paths),
+        // With the vault locked, referenced values can't be compared, so skip them
+        Err(_) => detect_store_drift(&resolved.settings, &live_settings, applied_paths)
+            .into_iter()
+            .filter(|change| !change.old_value.as_ref().is_some_and(vault::contains_refs))
+            .collect(),
+    };
 
-        // Create .claude directory if it doesn't exist
-        if let Some(parent) = user_settings_path.parent() {
-            std::fs::create_dir_all(parent)
-                .map_err(|e| format!("Failed to create .claude directory: {}", e))?;
+    Ok(Some(ConfigDrift {
+        changes,
+        store_id: store.id.clone(),
+        store_title: store.title.clone(),
+    }))
+}
+
+/// Swap plaintext in `settings` back to the secret references of the active
+/// store, for settings read back from settings.json
+fn restore_active_refs(stores_data: &StoresData, settings: &Value) -> Result<Value, String> {
+    let Some(active) = stores_data.configs.iter().find(|store| store.using) else {
+        return Ok(settings.clone());
+    };
+
+    let with_refs = resolve_settings(&stores_data.configs, &active.id)?.settings;
+    if !vault::contains_refs(&with_refs) {
+        return Ok(settings.clone());
+    }
+
+    let resolved = vault::resolve_refs(&with_refs)?;
+    Ok(vault::restore_refs(settings, &with_refs, &resolved))
+}
+
+/// Check whether settings.json has drifted from the active store
+#[tauri::command]
+pub async fn check_config_drift() -> Result<Option<ConfigDrift>, String> {
+    read_config_drift()
+}
+
+/// Copy the live settings.json into the active store
+#[tauri::command]
+pub async fn capture_config_drift(message: Option<String>) -> Result<ConfigStore, String> {
+    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
+    let stores_file = home_dir.join(APP_CONFIG_DIR).join("stores.json");
+
+    journal::record("capture_config_drift", &store_paths(&home_dir))?;
+
+    let (mut stores_data, stores_snapshot) = read_stores_data(&stores_file)?;
+
+    let store_index = stores_data
+        .configs
+        .iter()
+        .position(|store| store.using)
+        .ok_or("No active store to capture into")?;
+
+    let live_settings = read_live_settings(&home_dir)?;
+    let live_without_hooks = strip_managed_hooks(&live_settings);
+    let live_with_refs = restore_active_refs(&stores_data, &live_without_hooks)?;
+
+    // Child stores keep only what differs from what they inherit
+    let captured_settings = match &stores_data.configs[store_index].parent_id {
+        Some(parent_id) => {
+            let inherited = resolve_settings(&stores_data.configs, parent_id)?;
+            strip_inherited(&live_with_refs, &inherited.settings)
         }
+        None => live_with_refs,
+    };
 
-        // Read existing settings if file exists, otherwise start with empty object
-        let mut existing_settings = if user_settings_path.exists() {
-            let content = std::fs::read_to_string(&user_settings_path)
-                .map_err(|e| format!("Failed to read existing settings: {}", e))?;
-            serde_json::from_str(&content)
-                .map_err(|e| format!("Failed to parse existing settings: {}", e))?
-        } else {
-            serde_json::Value::Object(serde_json::Map::new())
-        };
+    let store_id = stores_data.configs[store_index].id.clone();
+    let previous_settings = stores_data.configs[store_index].settings.clone();
 
-        // Merge the new settings into existing settings (partial update)
-        if let Some(settings_obj) = settings.as_object() {
-            if let Some(existing_obj) = existing_settings.as_object_mut() {
-                // Update only the keys present in the stored settings
-                for (key, value) in settings_obj {
-                    existing_obj.insert(key.clone(), value.clone());
-                }
-            } else {
-                // If existing settings is not an object, replace it entirely
-                existing_settings = settings.clone();
-            }
-        } else {
-            // If stored settings is not an object, replace existing entirely
-            existing_settings = settings.clone();
+    // The live file already holds these values, so only the footprint changes
+    let (_, applied_paths) = apply_settings(&live_settings, &[], &live_without_hooks);
+    stores_data.applied_paths = applied_paths;
+    stores_data.configs[store_index].settings = captured_settings.clone();
+
+    write_stores_data(&stores_file, &stores_snapshot, &stores_data)?;
+
+    let message = message.unwrap_or_else(|| "Captured live settings".to_string());
+    record_store_change(
+        &store_id,
+        Some(&previous_settings),
+        &captured_settings,
+        Some(message),
+    );
+
+    Ok(stores_data.configs[store_index].clone())
+}
+
+/// Overwrite drifted values in settings.json with the active store's settings.
+/// Only the store's own keys are touched; keys added by hand stay.
+#[tauri::command]
+pub async fn reapply_config_store() -> Result<(), String> {
+    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
+    let stores_file = home_dir.join(APP_CONFIG_DIR).join("stores.json");
+
+    journal::record("reapply_config_store", &store_paths(&home_dir))?;
+
+    let (mut stores_data, stores_snapshot) = read_stores_data(&stores_file)?;
+
+    let active_id = stores_data
+        .configs
+        .iter()
+        .find(|store| store.using)
+        .map(|store| store.id.clone())
+        .ok_or("No active store to re-apply")?;
+    let store_settings =
+        vault::resolve_refs(&resolve_settings(&stores_data.configs, &active_id)?.settings)?;
+
+    let user_settings_path = home_dir.join(".claude/settings.json");
+    let applied_paths = managed_file::update_json(&user_settings_path, |live_settings| {
+        let (merged_settings, applied_paths) =
+            reapply_store(live_settings, &store_settings, &stores_data.applied_paths);
+        *live_settings = merged_settings;
+        Ok(applied_paths)
+    })
+    .map_err(|e| format!("Failed to write user settings: {}", e))?;
+
+    stores_data.applied_paths = applied_paths;
+    write_stores_data(&stores_file, &stores_snapshot, &stores_data)?;
+
+    Ok(())
+}
+
+/// Save the live settings.json as a new store and make it the active one
+#[tauri::command]
+pub async fn fork_config_drift(title: String) -> Result<ConfigStore, String> {
+    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
+    let stores_file = home_dir.join(APP_CONFIG_DIR).join("stores.json");
+
+    journal::record("fork_config_drift", &store_paths(&home_dir))?;
+
+    let (mut stores_data, stores_snapshot) = read_stores_data(&stores_file)?;
+
+    let live_settings = read_live_settings(&home_dir)?;
+    let forked_settings = restore_active_refs(&stores_data, &strip_managed_hooks(&live_settings))?;
+
+    let new_store = ConfigStore {
+        id: nanoid::nanoid!(6),
+        title,
+        created_at: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_err(|e| format!("Failed to get timestamp: {}", e))?
+            .as_secs(),
+        settings: forked_settings,
+        using: true,
+        parent_id: None,
+    };
+
+    for store in &mut stores_data.configs {
+        store.using = false;
+    }
+
+    let (_, applied_paths) =
+        apply_settings(&live_settings, &[], &strip_managed_hooks(&live_settings));
+    stores_data.applied_paths = applied_paths;
+    stores_data.configs.push(new_store.clone());
+
+    write_stores_data(&stores_file, &stores_snapshot, &stores_data)?;
+
+    if let Err(e) = store_history::record_revision(
+        &new_store.id,
+        &new_store.settings,
+        Some("Forked from live settings".to_string()),
+    ) {
+        eprintln!("Warning: Failed to record revision: {}", e);
+    }
+
+    Ok(new_store)
+}
+
+/// Pack stores, agents, commands, MCP servers and CLAUDE.md into a bundle archive
+#[tauri::command]
+pub async fn export_config_bundle(
+    path: String,
+    selection: BundleSelection,
+) -> Result<BundleManifest, String> {
+    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
+    bundle::export_bundle(&home_dir, &PathBuf::from(path), &selection)
+}
+
+/// Read the manifest of a bundle archive
+#[tauri::command]
+pub async fn read_config_bundle(path: String) -> Result<BundleManifest, String> {
+    bundle::read_bundle_manifest(&PathBuf::from(path))
+}
+
+/// Import a bundle archive, or preview the import with `options.dryRun`
+#[tauri::command]
+pub async fn import_config_bundle(
+    path: String,
+    options: ImportOptions,
+) -> Result<ImportReport, String> {
+    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
+
+    if !options.dry_run {
+        let mut journal_paths = store_paths(&home_dir);
+        journal_paths.extend([
+            home_dir.join(".claude/agents"),
+            home_dir.join(".claude/commands"),
+            home_dir.join(".claude/CLAUDE.md"),
+            home_dir.join(".claude.json"),
+        ]);
+        if let Some(project_path) = &options.project_path {
+            journal_paths.extend([
+                get_project_agents_dir(project_path),
+                get_project_commands_dir(project_path),
+                PathBuf::from(project_path).join("CLAUDE.md"),
+            ]);
         }
+        journal::record("import_config_bundle", &journal_paths)?;
+    }
 
-        // Write the merged settings back to file
-        let json_content = serde_json::to_string_pretty(&existing_settings)
-            .map_err(|e| format!("Failed to serialize merged settings: {}", e))?;
+    let report = bundle::import_bundle(&home_dir, &PathBuf::from(path), &options)?;
 
-        std::fs::write(&user_settings_path, json_content)
-            .map_err(|e| format!("Failed to write user settings: {}", e))?;
+    // An overwritten store may be the active one or one of its parents
+    let overwritten_store_ids: Vec<String> = report
+        .items
+        .iter()
+        .filter(|item| item.kind == BundleItemKind::Store && item.action == ImportAction::Overwrite)
+        .filter_map(|item| {
+            item.path
+                .strip_prefix("stores/")
+                .and_then(|name| name.strip_suffix(".json"))
+                .map(String::from)
+        })
+        .collect();
+
+    if !report.dry_run && !overwritten_store_ids.is_empty() {
+        let stores_file = home_dir.join(APP_CONFIG_DIR).join("stores.json");
+        let (mut stores_data, stores_snapshot) = read_stores_data(&stores_file)?;
+        for store_id in &overwritten_store_ids {
+            reapply_if_inherited(&home_dir, &mut stores_data, store_id)?;
+        }
+        write_stores_data(&stores_file, &stores_snapshot, &stores_data)?;
     }
 
-    // Write back to stores file
-    let json_content = serde_json::to_string_pretty(&stores_data)
-        .map_err(|e| format!("Failed to serialize stores: {}", e))?;
+    Ok(report)
+}
 
-    std::fs::write(&stores_file, json_content)
-        .map_err(|e| format!("Failed to write stores file: {}", e))?;
+/// Journal entries of recent changes, newest first
+#[tauri::command]
+pub async fn list_journal() -> Result<Vec<JournalEntry>, String> {
+    journal::list()
+}
 
-    // Automatically unlock CC extension when updating config
-    if let Err(e) = unlock_cc_ext().await {
-        eprintln!("Warning: Failed to unlock CC extension: {}", e);
+/// Undo the most recent change that hasn't been undone
+#[tauri::command]
+pub async fn undo_last() -> Result<JournalEntry, String> {
+    let entry = journal::undo_last()?;
+    check_migration_after_undo()?;
+    Ok(entry)
+}
+
+/// Undo a specific journal entry
+#[tauri::command]
+pub async fn undo_entry(entry_id: String) -> Result<JournalEntry, String> {
+    let entry = journal::undo_entry(&entry_id)?;
+    check_migration_after_undo()?;
+    Ok(entry)
+}
+
+fn store_has_plaintext_secrets(store: &ConfigStore) -> bool {
+    store
+        .settings
+        .get("env")
+        .and_then(|env| env.as_object())
+        .is_some_and(|env| {
+            env.iter()
+                .any(|(key, value)| bundle::looks_secret(key, value))
+        })
+}
+
+/// Undo can put back a stores.json from before the secret migration; if it
+/// did, let the migration run again
+fn check_migration_after_undo() -> Result<(), String> {
+    if !vault::status()?.migrated {
+        return Ok(());
     }
 
-    Ok(stores_data.configs[store_index].clone())
+    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
+    let stores_file = home_dir.join(APP_CONFIG_DIR).join("stores.json");
+    let (stores_data, _) = read_stores_data(&stores_file)?;
+    if stores_data.configs.iter().any(store_has_plaintext_secrets) {
+        vault::unmark_migrated()?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_vault_status() -> Result<VaultStatus, String> {
+    vault::status()
+}
+
+/// Create the secret vault, protected by `passphrase`, and unlock it
+#[tauri::command]
+pub async fn create_vault(passphrase: String) -> Result<VaultStatus, String> {
+    vault::create(&passphrase)?;
+    vault::status()
+}
+
+#[tauri::command]
+pub async fn unlock_vault(passphrase: String) -> Result<VaultStatus, String> {
+    vault::unlock(&passphrase)?;
+    vault::status()
+}
+
+#[tauri::command]
+pub async fn lock_vault() -> Result<(), String> {
+    vault::lock()
+}
+
+/// List secret names and timestamps; values never leave the backend
+#[tauri::command]
+pub async fn list_secrets() -> Result<Vec<SecretInfo>, String> {
+    vault::list_secrets()
+}
+
+/// Add a secret that stores can reference as `{{secret:name}}`
+#[tauri::command]
+pub async fn set_secret(name: String, value: String) -> Result<SecretInfo, String> {
+    vault::set_secret(&name, &value)
+}
+
+/// Replace a secret's value, re-applying the active store if it uses the secret
+#[tauri::command]
+pub async fn rotate_secret(name: String, value: String) -> Result<SecretInfo, String> {
+    let info = vault::rotate_secret(&name, &value)?;
+
+    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
+    let stores_file = home_dir.join(APP_CONFIG_DIR).join("stores.json");
+    let (mut stores_data, stores_snapshot) = read_stores_data(&stores_file)?;
+
+    if let Some(active) = stores_data.configs.iter().find(|store| store.using) {
+        let resolved = resolve_settings(&stores_data.configs, &active.id)?;
+        if vault::referenced_secrets(&resolved.settings).contains(&name) {
+            apply_store_settings(&home_dir, &mut stores_data, &resolved.settings)?;
+            write_stores_data(&stores_file, &stores_snapshot, &stores_data)?;
+        }
+    }
+
+    Ok(info)
+}
+
+/// Delete a secret that no store references
+#[tauri::command]
+pub async fn delete_secret(name: String) -> Result<(), String> {
+    let stores = get_stores().await?;
+    if let Some(store) = stores
+        .iter()
+        .find(|store| vault::referenced_secrets(&store.settings).contains(&name))
+    {
+        return Err(format!(
+            "Secret '{}' is used by store '{}'",
+            name, store.title
+        ));
+    }
+
+    vault::delete_secret(&name)
+}
+
+/// A plaintext store value moved into the vault
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct MigratedSecret {
+    #[serde(rename = "storeId")]
+    pub store_id: String,
+    pub key: String,
+    #[serde(rename = "secretName")]
+    pub secret_name: String,
+}
+
+fn secret_name_part(text: &str) -> String {
+    text.to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Move secret-looking env values of every store into the vault, replacing
+/// them with references. Runs once; the revision history and the journal's
+/// copies of stores.json are rewritten too so no plaintext copy is left
+/// behind. The migration itself isn't journaled, since undoing it would
+/// bring the plaintext back.
+#[tauri::command]
+pub async fn migrate_plaintext_secrets() -> Result<Vec<MigratedSecret>, String> {
+    let status = vault::status()?;
+    if !status.exists {
+        return Err("Create the secret vault before migrating".to_string());
+    }
+    if status.migrated {
+        return Err("Plaintext secrets have already been migrated".to_string());
+    }
+
+    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
+    let stores_file = home_dir.join(APP_CONFIG_DIR).join("stores.json");
+
+    let (mut stores_data, stores_snapshot) = read_stores_data(&stores_file)?;
+
+    // Identical tokens shared by several stores become a single secret
+    let mut name_by_value: HashMap<String, String> = vault::decrypt_secrets()?
+        .into_iter()
+        .map(|(name, value)| (value, name))
+        .collect();
+    let mut taken_names: HashSet<String> = name_by_value.values().cloned().collect();
+    let mut migrated = Vec::new();
+    let mut replacements_by_store: HashMap<String, HashMap<String, String>> = HashMap::new();
+
+    for store in &mut stores_data.configs {
+        let Some(env) = store
+            .settings
+            .get_mut("env")
+            .and_then(|e| e.as_object_mut())
+        else {
+            continue;
+        };
+
+        for (key, value) in env.iter_mut() {
+            if !bundle::looks_secret(key, value) {
+                continue;
+            }
+            let Some(plaintext) = value.as_str().map(String::from) else {
+                continue;
+            };
+
+            let secret_name = match name_by_value.get(&plaintext) {
+                Some(name) => name.clone(),
+                None => {
+                    let base = secret_name_part(&format!("{} {}", store.title, key));
+                    let mut name = base.clone();
+                    let mut suffix = 2;
+                    while taken_names.contains(&name) {
+                        name = format!("{}-{}", base, suffix);
+                        suffix += 1;
+                    }
+                    vault::set_secret(&name, &plaintext)?;
+                    taken_names.insert(name.clone());
+                    name_by_value.insert(plaintext.clone(), name.clone());
+                    name
+                }
+            };
+
+            let reference = vault::secret_ref(&secret_name);
+            *value = Value::String(reference.clone());
+            replacements_by_store
+                .entry(store.id.clone())
+                .or_default()
+                .insert(plaintext, reference);
+            migrated.push(MigratedSecret {
+                store_id: store.id.clone(),
+                key: key.clone(),
+                secret_name,
+            });
+        }
+    }
+
+    write_stores_data(&stores_file, &stores_snapshot, &stores_data)?;
+
+    for (store_id, replacements) in &replacements_by_store {
+        if let Err(e) = store_history::replace_values(store_id, replacements) {
+            eprintln!(
+                "Warning: Failed to scrub revisions of '{}': {}",
+                store_id, e
+            );
+        }
+    }
+
+    let all_replacements: HashMap<String, String> =
+        replacements_by_store.into_values().flatten().collect();
+    journal::replace_values(&stores_file, &all_replacements)
+        .map_err(|e| format!("Failed to scrub journaled copies of stores.json: {}", e))?;
+
+    vault::mark_migrated()?;
+
+    Ok(migrated)
 }
 
 #[tauri::command]
@@ -803,44 +1482,38 @@ pub async fn check_mcp_server_exists(server_name: String) -> Result<bool, String
     Ok(mcp_servers.contains_key(&server_name))
 }
 
+/// Save a user-scope server, returning warnings about its environment
 #[tauri::command]
 pub async fn update_global_mcp_server(
     server_name: String,
     server_config: Value,
-) -> Result<(), String> {
+) -> Result<Vec<ValidationError>, SettingsError> {
+    let (server_config, warnings) =
+        validate_server(&server_config, "$").map_err(invalid_server_error)?;
+
     let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
     let claude_json_path = home_dir.join(".claude.json");
 
-    // Read existing .claude.json or create new structure
-    let mut json_value = if claude_json_path.exists() {
-        let content = std::fs::read_to_string(&claude_json_path)
-            .map_err(|e| format!("Failed to read .claude.json: {}", e))?;
-        serde_json::from_str(&content)
-            .map_err(|e| format!("Failed to parse .claude.json: {}", e))?
-    } else {
-        Value::Object(serde_json::Map::new())
-    };
-
-    // Update mcpServers object
-    let mcp_servers = json_value
-        .as_object_mut()
-        .unwrap()
-        .entry("mcpServers".to_string())
-        .or_insert_with(|| Value::Object(serde_json::Map::new()))
-        .as_object_mut()
-        .unwrap();
-
-    // Update the specific server
-    mcp_servers.insert(server_name, server_config);
-
-    // Write back to file
-    let json_content = serde_json::to_string_pretty(&json_value)
-        .map_err(|e| format!("Failed to serialize JSON: {}", e))?;
-
-    std::fs::write(&claude_json_path, json_content)
-        .map_err(|e| format!("Failed to write .claude.json: {}", e))?;
+    journal::record("update_global_mcp_server", &[&claude_json_path])?;
+
+    // Claude Code rewrites .claude.json constantly, so apply the change to the latest copy
+    managed_file::update_json(&claude_json_path, |json_value| {
+        // Update mcpServers object
+        let mcp_servers = json_value
+            .as_object_mut()
+            .ok_or("Invalid .claude.json: expected an object")?
+            .entry("mcpServers".to_string())
+            .or_insert_with(|| Value::Object(serde_json::Map::new()))
+            .as_object_mut()
+            .ok_or("Invalid .claude.json: mcpServers is not an object")?;
+
+        // Update the specific server
+        mcp_servers.insert(server_name.clone(), server_config.clone());
+        Ok(())
+    })
+    .map_err(|e| format!("Failed to write .claude.json: {}", e))?;
 
-    Ok(())
+    Ok(warnings)
 }
 
 #[tauri::command]
@@ -852,46 +1525,206 @@ pub async fn delete_global_mcp_server(server_name: String) -> Result<(), String>
         return Err("Claude configuration file does not exist".to_string());
     }
 
-    // Read existing .claude.json
-    let content = std::fs::read_to_string(&claude_json_path)
-        .map_err(|e| format!("Failed to read .claude.json: {}", e))?;
+    journal::record("delete_global_mcp_server", &[&claude_json_path])?;
 
-    let mut json_value: Value = serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse .claude.json: {}", e))?;
+    managed_file::update_json(&claude_json_path, |json_value| {
+        // Check if mcpServers exists
+        let mcp_servers = json_value
+            .get_mut("mcpServers")
+            .and_then(|servers| servers.as_object_mut());
 
-    // Check if mcpServers exists
-    let mcp_servers = json_value
-        .as_object_mut()
-        .unwrap()
-        .get_mut("mcpServers")
-        .and_then(|servers| servers.as_object_mut());
+        let mcp_servers = match mcp_servers {
+            Some(servers) => servers,
+            None => return Err("No mcpServers found in .claude.json".to_string()),
+        };
+
+        // Check if the server exists
+        if !mcp_servers.contains_key(&server_name) {
+            return Err(format!("MCP server '{}' not found", server_name));
+        }
+
+        // Remove the server
+        mcp_servers.remove(&server_name);
+
+        // If mcpServers is now empty, we can optionally remove the entire mcpServers object
+        if mcp_servers.is_empty() {
+            if let Some(obj) = json_value.as_object_mut() {
+                obj.remove("mcpServers");
+            }
+        }
+
+        Ok(())
+    })
+}
+
+/// Every MCP server definition Claude Code sees for a project, with the
+/// winning definition of each name and any managed policy blocks
+#[tauri::command]
+pub async fn resolve_mcp_servers(
+    project_path: Option<String>,
+) -> Result<Vec<ResolvedMcpServer>, String> {
+    resolver::resolve(project_path.as_deref())
+}
 
-    let mcp_servers = match mcp_servers {
-        Some(servers) => servers,
-        None => return Err("No mcpServers found in .claude.json".to_string()),
+/// Copy or move one MCP server, optionally under a new name
+#[tauri::command]
+pub async fn transfer_mcp_server(
+    server_name: String,
+    target_name: Option<String>,
+    from: McpLocation,
+    to: McpLocation,
+    mode: McpTransferMode,
+    strategy: ConflictStrategy,
+) -> Result<McpTransferResult, SettingsError> {
+    let item = McpTransferItem {
+        name: server_name,
+        target_name,
     };
+    let mut results = transfer_mcp_servers(vec![item], from, to, mode, strategy).await?;
+    results
+        .pop()
+        .ok_or_else(|| "Nothing was transferred".into())
+}
 
-    // Check if the server exists
-    if !mcp_servers.contains_key(&server_name) {
-        return Err(format!("MCP server '{}' not found", server_name));
+/// Copy or move several MCP servers between scopes at once
+#[tauri::command]
+pub async fn transfer_mcp_servers(
+    items: Vec<McpTransferItem>,
+    from: McpLocation,
+    to: McpLocation,
+    mode: McpTransferMode,
+    strategy: ConflictStrategy,
+) -> Result<Vec<McpTransferResult>, SettingsError> {
+    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
+
+    let from_path = transfer::location_path(&home_dir, &from)?;
+    let to_path = transfer::location_path(&home_dir, &to)?;
+    let mut paths = vec![to_path];
+    if !paths.contains(&from_path) {
+        paths.push(from_path);
     }
+    journal::record("transfer_mcp_servers", &paths)?;
+
+    transfer::transfer_in(&home_dir, &items, &from, &to, mode, strategy)
+}
+
+/// Translate another client's MCP config and show what importing it would do
+#[tauri::command]
+pub async fn preview_mcp_import(
+    file_path: String,
+    format: McpImportFormat,
+    target: McpLocation,
+    strategy: ConflictStrategy,
+) -> Result<McpImportPreview, String> {
+    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
+    let existing = transfer::read_location(&home_dir, &target)?;
 
-    // Remove the server
-    mcp_servers.remove(&server_name);
+    mcp_import::preview_file(Path::new(&file_path), format, &existing, strategy)
+}
+
+/// Import servers from another client's MCP config into the user or project scope.
+/// `names` limits the import to those source entries; servers with errors are left out.
+#[tauri::command]
+pub async fn import_mcp_servers(
+    file_path: String,
+    format: McpImportFormat,
+    target: McpLocation,
+    strategy: ConflictStrategy,
+    names: Option<Vec<String>>,
+) -> Result<McpImportPreview, SettingsError> {
+    let preview = preview_mcp_import(file_path, format, target.clone(), strategy).await?;
+
+    let selected: Vec<_> = preview
+        .candidates
+        .iter()
+        .filter(|c| c.action != ImportAction::Skip && c.errors.is_empty())
+        .filter(|c| names.as_ref().is_none_or(|names| names.contains(&c.name)))
+        .collect();
 
-    // If mcpServers is now empty, we can optionally remove the entire mcpServers object
-    if mcp_servers.is_empty() {
-        json_value.as_object_mut().unwrap().remove("mcpServers");
+    match (target.scope, target.project_path.as_deref()) {
+        (McpScope::User, _) => {
+            for candidate in &selected {
+                update_global_mcp_server(candidate.target_name.clone(), candidate.config.clone())
+                    .await?;
+            }
+        }
+        (McpScope::Project, Some(project_path)) => {
+            let mut servers = project_mcp::read_servers(&project_mcp::mcp_path(project_path))?
+                .unwrap_or_default();
+            for candidate in &selected {
+                servers.insert(candidate.target_name.clone(), candidate.config.clone());
+            }
+            write_project_mcp(project_path.to_string(), Value::Object(servers)).await?;
+        }
+        _ => {
+            return Err("MCP servers can only be imported into the user or a project scope".into())
+        }
     }
 
-    // Write back to file
-    let json_content = serde_json::to_string_pretty(&json_value)
-        .map_err(|e| format!("Failed to serialize JSON: {}", e))?;
+    Ok(preview)
+}
+
+/// Turn an MCP server off, keeping its definition so it can be enabled again
+#[tauri::command]
+pub async fn disable_mcp_server(location: McpLocation, server_name: String) -> Result<(), String> {
+    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
+
+    journal::record(
+        "disable_mcp_server",
+        &[
+            transfer::location_path(&home_dir, &location)?,
+            stash::stash_path(&home_dir),
+        ],
+    )?;
+
+    stash::disable_in(&home_dir, &location, &server_name)
+}
+
+/// Restore a disabled MCP server exactly as it was
+#[tauri::command]
+pub async fn enable_mcp_server(location: McpLocation, server_name: String) -> Result<(), String> {
+    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
+
+    journal::record(
+        "enable_mcp_server",
+        &[
+            transfer::location_path(&home_dir, &location)?,
+            stash::stash_path(&home_dir),
+        ],
+    )?;
+
+    stash::enable_in(&home_dir, &location, &server_name)
+}
+
+/// Disabled MCP servers, optionally only those of one location
+#[tauri::command]
+pub async fn list_disabled_mcp_servers(
+    location: Option<McpLocation>,
+) -> Result<Vec<DisabledMcpServer>, String> {
+    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
+    stash::list_disabled_in(&home_dir, location.as_ref())
+}
 
-    std::fs::write(&claude_json_path, json_content)
-        .map_err(|e| format!("Failed to write .claude.json: {}", e))?;
+/// Every server of a location, enabled or disabled
+#[tauri::command]
+pub async fn list_mcp_server_toggles(
+    location: McpLocation,
+) -> Result<Vec<McpServerToggle>, String> {
+    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
+    stash::toggles_in(&home_dir, &location)
+}
 
-    Ok(())
+#[tauri::command]
+pub async fn probe_mcp_server(
+    server_config: Value,
+    timeout_ms: Option<u64>,
+) -> Result<McpProbeResult, String> {
+    let server_config = vault::resolve_refs(&server_config)?;
+    let timeout = timeout_ms
+        .map(std::time::Duration::from_millis)
+        .unwrap_or(probe::DEFAULT_TIMEOUT);
+
+    Ok(probe::probe_server(&server_config, timeout).await)
 }
 
 #[derive(serde::Serialize, serde::Deserialize)]
@@ -978,6 +1811,8 @@ pub async fn unlock_cc_ext() -> Result<(), String> {
     let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
     let claude_config_path = home_dir.join(".claude/config.json");
 
+    journal::record("unlock_cc_ext", &[&claude_config_path])?;
+
     // Ensure .claude directory exists
     if let Some(parent) = claude_config_path.parent() {
         std::fs::create_dir_all(parent)
@@ -1006,7 +1841,7 @@ pub async fn unlock_cc_ext() -> Result<(), String> {
             let json_content = serde_json::to_string_pretty(&json_value)
                 .map_err(|e| format!("Failed to serialize JSON: {}", e))?;
 
-            std::fs::write(&claude_config_path, json_content)
+            managed_file::write(&claude_config_path, &json_content)
                 .map_err(|e| format!("Failed to write config.json: {}", e))?;
 
             println!("Added primaryApiKey to existing config.json");
@@ -1022,7 +1857,7 @@ pub async fn unlock_cc_ext() -> Result<(), String> {
         let json_content = serde_json::to_string_pretty(&config)
             .map_err(|e| format!("Failed to serialize JSON: {}", e))?;
 
-        std::fs::write(&claude_config_path, json_content)
+        managed_file::write(&claude_config_path, &json_content)
             .map_err(|e| format!("Failed to write config.json: {}", e))?;
 
         println!("Created new config.json with primaryApiKey");
@@ -1227,13 +2062,15 @@ pub async fn write_claude_memory(content: String) -> Result<(), String> {
     let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
     let claude_md_path = home_dir.join(".claude/CLAUDE.md");
 
+    journal::record("write_claude_memory", &[&claude_md_path])?;
+
     // Ensure .claude directory exists
     if let Some(parent) = claude_md_path.parent() {
         std::fs::create_dir_all(parent)
             .map_err(|e| format!("Failed to create .claude directory: {}", e))?;
     }
 
-    std::fs::write(&claude_md_path, content)
+    managed_file::write(&claude_md_path, &content)
         .map_err(|e| format!("Failed to write CLAUDE.md file: {}", e))?;
 
     Ok(())
@@ -1270,7 +2107,9 @@ pub async fn write_project_memory(project_path: String, content: String) -> Resu
     // Primary location: ./CLAUDE.md at project root
     let claude_md_path = PathBuf::from(&project_path).join("CLAUDE.md");
 
-    std::fs::write(&claude_md_path, content)
+    journal::record("write_project_memory", &[&claude_md_path])?;
+
+    managed_file::write(&claude_md_path, &content)
         .map_err(|e| format!("Failed to write CLAUDE.md file: {}", e))?;
 
     Ok(())
@@ -1357,18 +2196,7 @@ async fn get_or_create_distinct_id() -> Result<String, String> {
         .map_err(|e| format!("Failed to create app config directory: {}", e))?;
 
     // Read existing stores.json or create new one
-    let mut stores_data = if stores_file.exists() {
-        let content = std::fs::read_to_string(&stores_file)
-            .map_err(|e| format!("Failed to read stores file: {}", e))?;
-
-        serde_json::from_str::<StoresData>(&content)
-            .map_err(|e| format!("Failed to parse stores file: {}", e))?
-    } else {
-        StoresData {
-            configs: vec![],
-            distinct_id: None,
-        }
-    };
+    let (mut stores_data, stores_snapshot) = read_stores_data(&stores_file)?;
 
     // Return existing distinct_id or create new one
     if let Some(ref id) = stores_data.distinct_id {
@@ -1379,11 +2207,7 @@ async fn get_or_create_distinct_id() -> Result<String, String> {
         stores_data.distinct_id = Some(new_id.clone());
 
         // Write back to stores.json
-        let json_content = serde_json::to_string_pretty(&stores_data)
-            .map_err(|e| format!("Failed to serialize stores data: {}", e))?;
-
-        std::fs::write(&stores_file, json_content)
-            .map_err(|e| format!("Failed to write stores file: {}", e))?;
+        write_stores_data(&stores_file, &stores_snapshot, &stores_data)?;
 
         println!("Created new distinct_id: {}", new_id);
         Ok(new_id)
@@ -1517,6 +2341,9 @@ pub struct ClaudeConfigFile {
     pub path: String,
     pub content: Value,
     pub exists: bool,
+    /// Fingerprint of the file as read, passed back on save to detect changes
+    /// made in the meantime
+    pub fingerprint: Option<String>,
 }
 
 #[tauri::command]
@@ -1526,36 +2353,54 @@ pub async fn read_claude_config_file() -> Result<ClaudeConfigFile, String> {
 
     let path_str = claude_json_path.to_string_lossy().to_string();
 
-    if claude_json_path.exists() {
-        let content = std::fs::read_to_string(&claude_json_path)
-            .map_err(|e| format!("Failed to read .claude.json: {}", e))?;
+    let snapshot = managed_file::read(&claude_json_path)
+        .map_err(|e| format!("Failed to read .claude.json: {}", e))?;
 
+    if let Some(content) = snapshot.content() {
         let json_content: Value =
-            serde_json::from_str(&content).map_err(|e| format!("Failed to parse JSON: {}", e))?;
+            serde_json::from_str(content).map_err(|e| format!("Failed to parse JSON: {}", e))?;
 
         Ok(ClaudeConfigFile {
             path: path_str,
             content: json_content,
             exists: true,
+            fingerprint: snapshot.fingerprint_hex(),
         })
     } else {
         Ok(ClaudeConfigFile {
             path: path_str,
             content: Value::Object(serde_json::Map::new()),
             exists: false,
+            fingerprint: None,
         })
     }
 }
 
 #[tauri::command]
-pub async fn write_claude_config_file(content: Value) -> Result<(), String> {
+pub async fn write_claude_config_file(
+    content: Value,
+    fingerprint: Option<String>,
+) -> Result<(), String> {
     let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
     let claude_json_path = home_dir.join(".claude.json");
 
+    // Claude Code rewrites .claude.json constantly, so only save over the copy
+    // the editor was showing
+    let snapshot = managed_file::read(&claude_json_path)
+        .map_err(|e| format!("Failed to read .claude.json: {}", e))?;
+    if snapshot.fingerprint_hex() != fingerprint {
+        return Err(
+            ".claude.json was modified by another process since it was loaded, please reload and try again"
+                .to_string(),
+        );
+    }
+
+    journal::record("write_claude_config_file", &[&claude_json_path])?;
+
     let json_content = serde_json::to_string_pretty(&content)
         .map_err(|e| format!("Failed to serialize JSON: {}", e))?;
 
-    std::fs::write(&claude_json_path, json_content)
+    managed_file::write_if_unchanged(&claude_json_path, &snapshot, &json_content)
         .map_err(|e| format!("Failed to write file: {}", e))?;
 
     Ok(())
@@ -1638,66 +2483,99 @@ pub async fn track(
 
 // Hook management functions
 
-/// Get the latest hook command based on the current operating system
+/// Quote a value for a POSIX shell
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// Quote a value as a PowerShell string literal
+fn powershell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// Get the latest hook command based on the current operating system,
+/// posting to the hook server's current endpoint with the secret read from disk
 fn get_latest_hook_command() -> serde_json::Value {
-    if cfg!(target_os = "windows") {
-        serde_json::json!({
+    let endpoint = hook_server::current_endpoint().unwrap_or(HookEndpoint::Tcp {
+        port: hook_settings::DEFAULT_PORT,
+    });
+    let secret_path = dirs::home_dir()
+        .map(|home_dir| hook_auth::secret_path(&home_dir))
+        .unwrap_or_default();
+    let secret_path = secret_path.to_string_lossy();
+
+    match endpoint {
+        HookEndpoint::Unix { socket_path } => serde_json::json!({
             "__ccfoundation__": true,
             "type": "command",
-            "command": "powershell -Command \"try { Invoke-RestMethod -Uri http://localhost:59948/claude_code/hooks -Method POST -ContentType 'application/json' -Body $input -ErrorAction Stop } catch { '' }\""
-        })
-    } else {
-        serde_json::json!({
+            "command": format!("curl -s --unix-socket {} -X POST {} -H 'Content-Type: application/json' -H \"{}: $(cat {} 2>/dev/null)\" --data-binary @- 2>/dev/null || echo", shell_quote(&socket_path.to_string_lossy()), hook_server::socket_hook_url(), SECRET_HEADER, shell_quote(&secret_path))
+        }),
+        HookEndpoint::Tcp { port } if cfg!(target_os = "windows") => serde_json::json!({
             "__ccfoundation__": true,
             "type": "command",
-            "command": "curl -s -X POST http://localhost:59948/claude_code/hooks -H 'Content-Type: application/json' --data-binary @- 2>/dev/null || echo"
-        })
+            "command": format!("powershell -Command \"try {{ (Invoke-WebRequest -UseBasicParsing -Uri {} -Method POST -ContentType 'application/json' -Headers @{{ '{}' = (Get-Content -Raw {}).Trim() }} -Body $input -ErrorAction Stop).Content }} catch {{ '' }}\"", hook_server::hook_url(port), SECRET_HEADER, powershell_quote(&secret_path))
+        }),
+        HookEndpoint::Tcp { port } => serde_json::json!({
+            "__ccfoundation__": true,
+            "type": "command",
+            "command": format!("curl -s -X POST {} -H 'Content-Type: application/json' -H \"{}: $(cat {} 2>/dev/null)\" --data-binary @- 2>/dev/null || echo", hook_server::hook_url(port), SECRET_HEADER, shell_quote(&secret_path))
+        }),
     }
 }
 
-/// Update existing ccfoundation hooks for specified events (doesn't add new ones)
-fn update_existing_hooks(
+/// Whether an entry of `hooks` holds a ccfoundation hook
+fn is_managed_hook(hook: &serde_json::Value) -> bool {
+    hook.get("__ccfoundation__").is_some()
+}
+
+fn has_managed_hooks(hooks_obj: &serde_json::Map<String, serde_json::Value>) -> bool {
+    hooks_obj
+        .values()
+        .filter_map(|event_hooks| event_hooks.as_array())
+        .flatten()
+        .filter_map(|entry| entry.get("hooks").and_then(|h| h.as_array()))
+        .flatten()
+        .any(is_managed_hook)
+}
+
+/// Remove ccfoundation hooks from every event not in `keep`, dropping entries
+/// and events left empty
+fn remove_managed_hooks(
     hooks_obj: &mut serde_json::Map<String, serde_json::Value>,
-    events: &[&str],
-) -> Result<bool, String> {
-    let latest_hook_command = get_latest_hook_command();
-    let latest_command_str = latest_hook_command
-        .get("command")
-        .and_then(|cmd| cmd.as_str())
-        .unwrap_or("");
+    keep: &[&str],
+) -> bool {
+    let mut hook_removed = false;
+    let event_names: Vec<String> = hooks_obj
+        .keys()
+        .filter(|event| !keep.contains(&event.as_str()))
+        .cloned()
+        .collect();
 
-    let mut hook_updated = false;
+    for event in event_names {
+        let Some(event_hooks) = hooks_obj.get_mut(&event).and_then(|h| h.as_array_mut()) else {
+            continue;
+        };
 
-    for event in events {
-        if let Some(event_hooks) = hooks_obj.get_mut(*event).and_then(|h| h.as_array_mut()) {
-            // Find and update existing ccfoundation hooks only
-            for entry in event_hooks.iter_mut() {
-                if let Some(hooks_array) = entry.get_mut("hooks").and_then(|h| h.as_array_mut()) {
-                    for hook in hooks_array.iter_mut() {
-                        if hook.get("__ccfoundation__").is_some() {
-                            // Compare only the command string, not the entire JSON object
-                            if let Some(existing_command) =
-                                hook.get("command").and_then(|cmd| cmd.as_str())
-                            {
-                                if existing_command != latest_command_str {
-                                    // Update only the command field, preserve other properties
-                                    hook["command"] =
-                                        serde_json::Value::String(latest_command_str.to_string());
-                                    hook_updated = true;
-                                    println!(
-                                        "🔄 Updated {} hook command: {}",
-                                        event, latest_command_str
-                                    );
-                                }
-                            }
-                        }
-                    }
-                }
+        let before = event_hooks.clone();
+        event_hooks.retain_mut(|entry| {
+            let Some(hooks_array) = entry.get_mut("hooks").and_then(|h| h.as_array_mut()) else {
+                // Keep entries that don't have a hooks array
+                return true;
+            };
+            hooks_array.retain(|hook| !is_managed_hook(hook));
+            // Keep the entry only if it still has hooks
+            !hooks_array.is_empty()
+        });
+
+        if *event_hooks != before {
+            hook_removed = true;
+            if event_hooks.is_empty() {
+                hooks_obj.remove(&event);
             }
         }
     }
 
-    Ok(hook_updated)
+    hook_removed
 }
 
 /// Update or add ccfoundation hooks for specified events
@@ -1714,10 +2592,10 @@ fn update_or_add_hooks(
             for entry in event_hooks.iter_mut() {
                 if let Some(hooks_array) = entry.get_mut("hooks").and_then(|h| h.as_array_mut()) {
                     for hook in hooks_array.iter_mut() {
-                        if hook.get("__ccfoundation__").is_some() {
-                            // Update the command to the latest version
+                        if is_managed_hook(hook) {
+                            // Update the command to the latest version, preserving other properties
                             if hook.get("command") != latest_hook_command.get("command") {
-                                *hook = latest_hook_command.clone();
+                                hook["command"] = latest_hook_command["command"].clone();
                                 hook_updated = true;
                             }
                         }
@@ -1728,9 +2606,7 @@ fn update_or_add_hooks(
             // If no ccfoundation hooks found, add one
             let ccfoundation_hook_exists = event_hooks.iter().any(|entry| {
                 if let Some(hooks_array) = entry.get("hooks").and_then(|h| h.as_array()) {
-                    hooks_array
-                        .iter()
-                        .any(|hook| hook.get("__ccfoundation__").is_some())
+                    hooks_array.iter().any(is_managed_hook)
                 } else {
                     false
                 }
@@ -1759,6 +2635,19 @@ fn update_or_add_hooks(
     Ok(hook_updated)
 }
 
+/// Install the ccfoundation hook for exactly the subscribed events
+fn sync_managed_hooks(
+    hooks_obj: &mut serde_json::Map<String, serde_json::Value>,
+    settings: &HookSettings,
+) -> Result<bool, String> {
+    let events = settings.event_names();
+    let hook_updated = update_or_add_hooks(hooks_obj, &events)?;
+    let hook_removed = remove_managed_hooks(hooks_obj, &events);
+    Ok(hook_updated || hook_removed)
+}
+
+/// Bring installed hooks in line with the latest command and the subscribed
+/// events. Does nothing when the hooks aren't installed.
 #[tauri::command]
 pub async fn update_claude_code_hook() -> Result<(), String> {
     let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
@@ -1769,45 +2658,37 @@ pub async fn update_claude_code_hook() -> Result<(), String> {
         return add_claude_code_hook().await;
     }
 
-    // Read existing settings
-    let content = std::fs::read_to_string(&settings_path)
-        .map_err(|e| format!("Failed to read settings.json: {}", e))?;
-
-    let mut settings: serde_json::Value = serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse settings.json: {}", e))?;
-
-    // Ensure hooks object exists
-    let hooks_obj = settings
-        .as_object_mut()
-        .unwrap()
-        .entry("hooks".to_string())
-        .or_insert_with(|| serde_json::Value::Object(serde_json::Map::new()))
-        .as_object_mut()
-        .unwrap();
-
-    // Update existing hooks for Notification, Stop, and PreToolUse events (only update, don't add new ones)
-    let events = ["Notification", "Stop", "PreToolUse"];
-    let hook_updated = update_existing_hooks(hooks_obj, &events)?;
-
-    if hook_updated {
-        // Write back to settings file
-        let json_content = serde_json::to_string_pretty(&settings)
-            .map_err(|e| format!("Failed to serialize settings: {}", e))?;
-
-        // Create .claude directory if it doesn't exist
-        if let Some(parent) = settings_path.parent() {
-            std::fs::create_dir_all(parent)
-                .map_err(|e| format!("Failed to create .claude directory: {}", e))?;
+    let hook_settings = hook_settings::read_settings(&home_dir)?;
+    let sync_installed = |settings: &mut Value| {
+        // Only touch installed hooks, so don't create an empty hooks object either
+        let Some(hooks_obj) = settings.get_mut("hooks").and_then(|h| h.as_object_mut()) else {
+            return Ok(false);
+        };
+        if !has_managed_hooks(hooks_obj) {
+            return Ok(false);
         }
 
-        std::fs::write(&settings_path, json_content)
-            .map_err(|e| format!("Failed to write settings.json: {}", e))?;
+        sync_managed_hooks(hooks_obj, &hook_settings)
+    };
 
-        println!("✅ Claude Code hooks updated successfully");
-    } else {
+    // This runs on every hook server start, so only journal when something changes
+    let snapshot = managed_file::read(&settings_path)?;
+    let mut preview: Value = match snapshot.content() {
+        Some(content) => serde_json::from_str(content)
+            .map_err(|e| format!("Failed to parse settings.json: {}", e))?,
+        None => Value::Object(serde_json::Map::new()),
+    };
+    if !sync_installed(&mut preview)? {
         println!("ℹ️  Claude Code hooks are already up to date - no updates needed");
+        return Ok(());
     }
 
+    journal::record("update_claude_code_hook", &[&settings_path])?;
+
+    managed_file::update_json(&settings_path, sync_installed)
+        .map_err(|e| format!("Failed to write settings.json: {}", e))?;
+
+    println!("✅ Claude Code hooks updated successfully");
     Ok(())
 }
 
@@ -1815,42 +2696,23 @@ pub async fn update_claude_code_hook() -> Result<(), String> {
 pub async fn add_claude_code_hook() -> Result<(), String> {
     let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
     let settings_path = home_dir.join(".claude/settings.json");
+    let hook_settings = hook_settings::read_settings(&home_dir)?;
 
-    // Read existing settings or create new structure
-    let mut settings = if settings_path.exists() {
-        let content = std::fs::read_to_string(&settings_path)
-            .map_err(|e| format!("Failed to read settings.json: {}", e))?;
-        serde_json::from_str(&content)
-            .map_err(|e| format!("Failed to parse settings.json: {}", e))?
-    } else {
-        serde_json::Value::Object(serde_json::Map::new())
-    };
-
-    // Ensure hooks object exists
-    let hooks_obj = settings
-        .as_object_mut()
-        .unwrap()
-        .entry("hooks".to_string())
-        .or_insert_with(|| serde_json::Value::Object(serde_json::Map::new()))
-        .as_object_mut()
-        .unwrap();
-
-    // Add hooks for Notification, Stop, and PreToolUse events
-    let events = ["Notification", "Stop", "PreToolUse"];
-    update_or_add_hooks(hooks_obj, &events)?;
-
-    // Write back to settings file
-    let json_content = serde_json::to_string_pretty(&settings)
-        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+    journal::record("add_claude_code_hook", &[&settings_path])?;
 
-    // Create .claude directory if it doesn't exist
-    if let Some(parent) = settings_path.parent() {
-        std::fs::create_dir_all(parent)
-            .map_err(|e| format!("Failed to create .claude directory: {}", e))?;
-    }
+    managed_file::update_json(&settings_path, |settings| {
+        // Ensure hooks object exists
+        let hooks_obj = settings
+            .as_object_mut()
+            .ok_or("Invalid settings.json: expected an object")?
+            .entry("hooks".to_string())
+            .or_insert_with(|| serde_json::Value::Object(serde_json::Map::new()))
+            .as_object_mut()
+            .ok_or("Invalid settings.json: hooks is not an object")?;
 
-    std::fs::write(&settings_path, json_content)
-        .map_err(|e| format!("Failed to write settings.json: {}", e))?;
+        sync_managed_hooks(hooks_obj, &hook_settings)
+    })
+    .map_err(|e| format!("Failed to write settings.json: {}", e))?;
 
     println!("✅ Claude Code hooks added successfully");
     Ok(())
@@ -1865,67 +2727,141 @@ pub async fn remove_claude_code_hook() -> Result<(), String> {
         return Ok(()); // Settings file doesn't exist, nothing to remove
     }
 
-    // Read existing settings
-    let content = std::fs::read_to_string(&settings_path)
-        .map_err(|e| format!("Failed to read settings.json: {}", e))?;
-
-    let mut settings: serde_json::Value = serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse settings.json: {}", e))?;
-
-    // Check if hooks object exists
-    if let Some(hooks_obj) = settings.get_mut("hooks").and_then(|h| h.as_object_mut()) {
-        let events = ["Notification", "Stop", "PreToolUse"];
-
-        for event in events {
-            if let Some(event_hooks) = hooks_obj.get_mut(event).and_then(|h| h.as_array_mut()) {
-                // Remove hooks that have __ccfoundation__ key from nested hooks arrays
-                let mut new_event_hooks = Vec::new();
-                for entry in event_hooks.iter() {
-                    if let Some(hooks_array) = entry.get("hooks").and_then(|h| h.as_array()) {
-                        // Filter out hooks that have __ccfoundation__ key
-                        let filtered_hooks: Vec<serde_json::Value> = hooks_array
-                            .iter()
-                            .filter(|hook| hook.get("__ccfoundation__").is_none())
-                            .cloned()
-                            .collect();
-
-                        // Keep the entry only if it still has hooks
-                        if !filtered_hooks.is_empty() {
-                            let mut new_entry = entry.clone();
-                            new_entry["hooks"] = serde_json::Value::Array(filtered_hooks);
-                            new_event_hooks.push(new_entry);
-                        }
-                    } else {
-                        // Keep entries that don't have a hooks array
-                        new_event_hooks.push(entry.clone());
-                    }
-                }
-                *event_hooks = new_event_hooks;
+    journal::record("remove_claude_code_hook", &[&settings_path])?;
+
+    managed_file::update_json(&settings_path, |settings| {
+        // Remove the hook from every event, including ones no longer subscribed
+        if let Some(hooks_obj) = settings.get_mut("hooks").and_then(|h| h.as_object_mut()) {
+            remove_managed_hooks(hooks_obj, &[]);
 
-                // If the event hooks array is empty, remove the entire event entry
-                if event_hooks.is_empty() {
-                    hooks_obj.remove(event);
+            // If hooks object is empty, remove it entirely
+            if hooks_obj.is_empty() {
+                if let Some(settings_obj) = settings.as_object_mut() {
+                    settings_obj.remove("hooks");
                 }
             }
         }
 
-        // If hooks object is empty, remove it entirely
-        if hooks_obj.is_empty() {
-            settings.as_object_mut().unwrap().remove("hooks");
-        }
-    }
+        Ok(())
+    })
+    .map_err(|e| format!("Failed to write settings.json: {}", e))?;
 
-    // Write back to settings file
-    let json_content = serde_json::to_string_pretty(&settings)
-        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+    println!("✅ Claude Code hooks removed successfully");
+    Ok(())
+}
 
-    std::fs::write(&settings_path, json_content)
-        .map_err(|e| format!("Failed to write settings.json: {}", e))?;
+#[tauri::command]
+pub async fn list_policy_rules() -> Result<Vec<PolicyRule>, String> {
+    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
+    hook_policy::read_rules(&home_dir)
+}
 
-    println!("✅ Claude Code hooks removed successfully");
+#[tauri::command]
+pub async fn create_policy_rule(rule: PolicyRule) -> Result<PolicyRule, String> {
+    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
+    hook_policy::create_rule(&home_dir, rule)
+}
+
+#[tauri::command]
+pub async fn update_policy_rule(rule: PolicyRule) -> Result<(), String> {
+    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
+    hook_policy::update_rule(&home_dir, rule)
+}
+
+#[tauri::command]
+pub async fn delete_policy_rule(rule_id: String) -> Result<(), String> {
+    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
+    hook_policy::delete_rule(&home_dir, &rule_id)
+}
+
+#[tauri::command]
+pub async fn get_notification_settings() -> Result<NotificationSettings, String> {
+    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
+    hook_notifications::read_settings(&home_dir)
+}
+
+#[tauri::command]
+pub async fn create_notification_rule(rule: NotificationRule) -> Result<NotificationRule, String> {
+    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
+    hook_notifications::create_rule(&home_dir, rule)
+}
+
+#[tauri::command]
+pub async fn update_notification_rule(rule: NotificationRule) -> Result<(), String> {
+    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
+    hook_notifications::update_rule(&home_dir, rule)
+}
+
+#[tauri::command]
+pub async fn delete_notification_rule(rule_id: String) -> Result<(), String> {
+    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
+    hook_notifications::delete_rule(&home_dir, &rule_id)
+}
+
+/// Notification rules are matched first to last; `rule_ids` is the new order
+#[tauri::command]
+pub async fn reorder_notification_rules(rule_ids: Vec<String>) -> Result<(), String> {
+    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
+    hook_notifications::reorder_rules(&home_dir, &rule_ids)
+}
+
+/// Set or clear (`None`) the quiet hours
+#[tauri::command]
+pub async fn set_notification_quiet_hours(quiet_hours: Option<QuietHours>) -> Result<(), String> {
+    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
+    hook_notifications::set_quiet_hours(&home_dir, quiet_hours)
+}
+
+/// Replace the hook secret and reinstall the hook commands that send it
+#[tauri::command]
+pub async fn rotate_hook_secret() -> Result<(), String> {
+    hook_auth::rotate()?;
+    update_claude_code_hook().await
+}
+
+#[tauri::command]
+pub async fn get_hook_server_status() -> Result<HookServerStatus, String> {
+    Ok(hook_server::status())
+}
+
+#[tauri::command]
+pub async fn get_hook_settings() -> Result<HookSettings, String> {
+    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
+    hook_settings::read_settings(&home_dir)
+}
+
+/// Save which events the app subscribes to and how hooks reach the app, then
+/// re-sync installed hooks. A new transport or port restarts the hook server,
+/// which rewrites the hooks again once it's listening.
+#[tauri::command]
+pub async fn set_hook_settings(
+    app: tauri::AppHandle,
+    settings: HookSettings,
+) -> Result<(), String> {
+    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
+    let previous = hook_settings::read_settings(&home_dir)?;
+    hook_settings::write_settings(&home_dir, &settings)?;
+
+    let settings_path = home_dir.join(".claude/settings.json");
+    if settings_path.exists() {
+        journal::record("set_hook_settings", &[&settings_path])?;
+        update_claude_code_hook().await?;
+    }
+
+    if settings.effective_transport() != previous.effective_transport()
+        || settings.port != previous.port
+    {
+        hook_server::restart_hook_server(&app);
+    }
     Ok(())
 }
 
+/// Logged hook events matching `query`, newest first
+#[tauri::command]
+pub async fn query_hook_events(query: HookLogQuery) -> Result<HookLogPage, String> {
+    hook_log::query(&query)
+}
+
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
 pub struct CommandFile {
     pub name: String,
@@ -1982,11 +2918,13 @@ pub async fn write_claude_command(command_name: String, content: String) -> Resu
     let commands_dir = home_dir.join(".claude/commands");
     let command_file_path = commands_dir.join(format!("{}.md", command_name));
 
+    journal::record("write_claude_command", &[&command_file_path])?;
+
     // Ensure .claude/commands directory exists
     std::fs::create_dir_all(&commands_dir)
         .map_err(|e| format!("Failed to create .claude/commands directory: {}", e))?;
 
-    std::fs::write(&command_file_path, content)
+    managed_file::write(&command_file_path, &content)
         .map_err(|e| format!("Failed to write command file: {}", e))?;
 
     Ok(())
@@ -1999,6 +2937,7 @@ pub async fn delete_claude_command(command_name: String) -> Result<(), String> {
     let command_file_path = commands_dir.join(format!("{}.md", command_name));
 
     if command_file_path.exists() {
+        journal::record("delete_claude_command", &[&command_file_path])?;
         std::fs::remove_file(&command_file_path)
             .map_err(|e| format!("Failed to delete command file: {}", e))?;
     }
@@ -2064,11 +3003,13 @@ pub async fn write_claude_agent(agent_name: String, content: String) -> Result<(
     let agents_dir = home_dir.join(".claude/agents");
     let agent_file_path = agents_dir.join(format!("{}.md", agent_name));
 
+    journal::record("write_claude_agent", &[&agent_file_path])?;
+
     // Ensure .claude/agents directory exists
     std::fs::create_dir_all(&agents_dir)
         .map_err(|e| format!("Failed to create .claude/agents directory: {}", e))?;
 
-    std::fs::write(&agent_file_path, content)
+    managed_file::write(&agent_file_path, &content)
         .map_err(|e| format!("Failed to write agent file: {}", e))?;
 
     Ok(())
@@ -2081,6 +3022,7 @@ pub async fn delete_claude_agent(agent_name: String) -> Result<(), String> {
     let agent_file_path = agents_dir.join(format!("{}.md", agent_name));
 
     if agent_file_path.exists() {
+        journal::record("delete_claude_agent", &[&agent_file_path])?;
         std::fs::remove_file(&agent_file_path)
             .map_err(|e| format!("Failed to delete agent file: {}", e))?;
     }
@@ -2104,6 +3046,9 @@ pub struct ProjectSettings {
     pub has_commands: bool,
     #[serde(rename = "hasMcp")]
     pub has_mcp: bool,
+    /// Servers are still left in the old PROJECT/.claude/.mcp.json
+    #[serde(rename = "hasLegacyMcp")]
+    pub has_legacy_mcp: bool,
 }
 
 /// Project registry entry - lightweight tracking in ~/.ccconfig/project-registry.json
@@ -2132,18 +3077,14 @@ fn get_project_settings_path(project_path: &str) -> PathBuf {
     get_project_claude_dir(project_path).join("settings.json")
 }
 
-fn get_project_agents_dir(project_path: &str) -> PathBuf {
+pub(crate) fn get_project_agents_dir(project_path: &str) -> PathBuf {
     get_project_claude_dir(project_path).join("agents")
 }
 
-fn get_project_commands_dir(project_path: &str) -> PathBuf {
+pub(crate) fn get_project_commands_dir(project_path: &str) -> PathBuf {
     get_project_claude_dir(project_path).join("commands")
 }
 
-fn get_project_mcp_path(project_path: &str) -> PathBuf {
-    get_project_claude_dir(project_path).join(".mcp.json")
-}
-
 fn read_project_registry() -> Result<Vec<ProjectRegistryEntry>, String> {
     let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
     let registry_path = home_dir.join(APP_CONFIG_DIR).join("project-registry.json");
@@ -2171,7 +3112,7 @@ fn read_project_registry() -> Result<Vec<ProjectRegistryEntry>, String> {
     // Auto-migrate to new format
     let json_content = serde_json::to_string_pretty(&registry)
         .map_err(|e| format!("Failed to serialize registry: {}", e))?;
-    std::fs::write(&registry_path, json_content)
+    managed_file::write(&registry_path, &json_content)
         .map_err(|e| format!("Failed to write migrated registry: {}", e))?;
 
     println!("✅ Migrated project registry to new format");
@@ -2204,7 +3145,7 @@ fn write_project_registry_entry(
     let json_content = serde_json::to_string_pretty(&registry)
         .map_err(|e| format!("Failed to serialize registry: {}", e))?;
 
-    std::fs::write(&registry_path, json_content)
+    managed_file::write(&registry_path, &json_content)
         .map_err(|e| format!("Failed to write registry: {}", e))?;
 
     Ok(())
@@ -2221,7 +3162,8 @@ pub async fn read_project_settings(project_path: String) -> Result<ProjectSettin
     let settings_path = get_project_settings_path(&project_path);
     let agents_dir = get_project_agents_dir(&project_path);
     let commands_dir = get_project_commands_dir(&project_path);
-    let mcp_path = get_project_mcp_path(&project_path);
+    let mcp_path = project_mcp::mcp_path(&project_path);
+    let legacy_mcp_path = project_mcp::legacy_mcp_path(&project_path);
 
     let exists = claude_dir.exists();
     let settings = if settings_path.exists() {
@@ -2241,14 +3183,22 @@ pub async fn read_project_settings(project_path: String) -> Result<ProjectSettin
         has_agents: agents_dir.exists() && agents_dir.is_dir(),
         has_commands: commands_dir.exists() && commands_dir.is_dir(),
         has_mcp: mcp_path.exists(),
+        has_legacy_mcp: legacy_mcp_path.exists(),
     })
 }
 
 /// Write project settings to PROJECT/.claude/settings.json
 #[tauri::command]
-pub async fn write_project_settings(project_path: String, settings: Value) -> Result<(), String> {
+pub async fn write_project_settings(
+    project_path: String,
+    settings: Value,
+) -> Result<Vec<ValidationError>, SettingsError> {
+    let (_, warnings) = validate_settings(&settings)?;
+
     let settings_path = get_project_settings_path(&project_path);
 
+    journal::record("write_project_settings", &[&settings_path])?;
+
     // Ensure .claude directory exists
     if let Some(parent) = settings_path.parent() {
         std::fs::create_dir_all(parent)
@@ -2258,10 +3208,10 @@ pub async fn write_project_settings(project_path: String, settings: Value) -> Re
     let json_content = serde_json::to_string_pretty(&settings)
         .map_err(|e| format!("Failed to serialize settings: {}", e))?;
 
-    std::fs::write(&settings_path, json_content)
+    managed_file::write(&settings_path, &json_content)
         .map_err(|e| format!("Failed to write settings: {}", e))?;
 
-    Ok(())
+    Ok(warnings)
 }
 
 /// Initialize PROJECT/.claude/ directory structure
@@ -2271,6 +3221,18 @@ pub async fn init_project_claude_dir(project_path: String) -> Result<(), String>
     let agents_dir = get_project_agents_dir(&project_path);
     let commands_dir = get_project_commands_dir(&project_path);
 
+    // A fresh .claude directory is removed as a whole on undo
+    let journal_paths = if claude_dir.exists() {
+        vec![
+            agents_dir.clone(),
+            commands_dir.clone(),
+            get_project_settings_path(&project_path),
+        ]
+    } else {
+        vec![claude_dir.clone()]
+    };
+    journal::record("init_project_claude_dir", &journal_paths)?;
+
     std::fs::create_dir_all(&claude_dir)
         .map_err(|e| format!("Failed to create .claude directory: {}", e))?;
     std::fs::create_dir_all(&agents_dir)
@@ -2291,7 +3253,7 @@ pub async fn init_project_claude_dir(project_path: String) -> Result<(), String>
         });
         let json_content = serde_json::to_string_pretty(&default_settings)
             .map_err(|e| format!("Failed to serialize default settings: {}", e))?;
-        std::fs::write(&settings_path, json_content)
+        managed_file::write(&settings_path, &json_content)
             .map_err(|e| format!("Failed to write default settings: {}", e))?;
     }
 
@@ -2353,7 +3315,8 @@ pub async fn write_project_agent(
         .map_err(|e| format!("Failed to create agents directory: {}", e))?;
 
     let file_path = agents_dir.join(format!("{}.md", agent_name));
-    std::fs::write(&file_path, content)
+    journal::record("write_project_agent", &[&file_path])?;
+    managed_file::write(&file_path, &content)
         .map_err(|e| format!("Failed to write agent file: {}", e))?;
 
     Ok(())
@@ -2366,6 +3329,7 @@ pub async fn delete_project_agent(project_path: String, agent_name: String) -> R
     let file_path = agents_dir.join(format!("{}.md", agent_name));
 
     if file_path.exists() {
+        journal::record("delete_project_agent", &[&file_path])?;
         std::fs::remove_file(&file_path)
             .map_err(|e| format!("Failed to delete agent file: {}", e))?;
     }
@@ -2428,7 +3392,8 @@ pub async fn write_project_command(
         .map_err(|e| format!("Failed to create commands directory: {}", e))?;
 
     let file_path = commands_dir.join(format!("{}.md", command_name));
-    std::fs::write(&file_path, content)
+    journal::record("write_project_command", &[&file_path])?;
+    managed_file::write(&file_path, &content)
         .map_err(|e| format!("Failed to write command file: {}", e))?;
 
     Ok(())
@@ -2444,6 +3409,7 @@ pub async fn delete_project_command(
     let file_path = commands_dir.join(format!("{}.md", command_name));
 
     if file_path.exists() {
+        journal::record("delete_project_command", &[&file_path])?;
         std::fs::remove_file(&file_path)
             .map_err(|e| format!("Failed to delete command file: {}", e))?;
     }
@@ -2451,35 +3417,48 @@ pub async fn delete_project_command(
     Ok(())
 }
 
-/// Read project MCP from PROJECT/.mcp.json
+/// Read the project's server map from PROJECT/.mcp.json
 #[tauri::command]
 pub async fn read_project_mcp(project_path: String) -> Result<Option<Value>, String> {
-    let mcp_path = get_project_mcp_path(&project_path);
+    let servers = project_mcp::read_servers(&project_mcp::mcp_path(&project_path))?;
+    Ok(servers.map(Value::Object))
+}
 
-    if !mcp_path.exists() {
-        return Ok(None);
-    }
+/// Write the project's server map to PROJECT/.mcp.json, returning warnings
+/// about the environment of the servers that changed
+#[tauri::command]
+pub async fn write_project_mcp(
+    project_path: String,
+    content: Value,
+) -> Result<Vec<ValidationError>, SettingsError> {
+    let mcp_path = project_mcp::mcp_path(&project_path);
 
-    let content = std::fs::read_to_string(&mcp_path)
-        .map_err(|e| format!("Failed to read project MCP: {}", e))?;
+    let Value::Object(servers) = content else {
+        return Err("Invalid MCP servers: expected an object".to_string().into());
+    };
+
+    let current = project_mcp::read_servers(&mcp_path).ok().flatten();
+    let (servers, warnings) =
+        validate_changed_servers(servers, current.as_ref()).map_err(invalid_server_error)?;
 
-    let json: Value = serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse project MCP: {}", e))?;
+    journal::record("write_project_mcp", &[&mcp_path])?;
 
-    Ok(Some(json))
+    project_mcp::write_servers(&mcp_path, servers)?;
+    Ok(warnings)
 }
 
-/// Write project MCP to PROJECT/.mcp.json
+/// Move servers from the old PROJECT/.claude/.mcp.json into PROJECT/.mcp.json
 #[tauri::command]
-pub async fn write_project_mcp(project_path: String, content: Value) -> Result<(), String> {
-    let mcp_path = get_project_mcp_path(&project_path);
-
-    let json_content = serde_json::to_string_pretty(&content)
-        .map_err(|e| format!("Failed to serialize MCP: {}", e))?;
-
-    std::fs::write(&mcp_path, json_content).map_err(|e| format!("Failed to write MCP: {}", e))?;
-
-    Ok(())
+pub async fn migrate_project_mcp(project_path: String) -> Result<McpMigrationReport, String> {
+    journal::record(
+        "migrate_project_mcp",
+        &[
+            project_mcp::mcp_path(&project_path),
+            project_mcp::legacy_mcp_path(&project_path),
+        ],
+    )?;
+
+    project_mcp::migrate_legacy(&project_path)
 }
 
 /// Get project registry (all tracked projects)
@@ -2526,21 +3505,13 @@ fn remove_project_from_claude_json(project_path: &str) -> Result<(), String> {
         return Ok(()); // Nothing to clean
     }
 
-    let content = std::fs::read_to_string(&claude_json_path)
-        .map_err(|e| format!("Failed to read .claude.json: {}", e))?;
-
-    let mut json: Value = serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse .claude.json: {}", e))?;
-
-    if let Some(projects) = json.get_mut("projects").and_then(|p| p.as_object_mut()) {
-        projects.remove(project_path);
-    }
-
-    let updated_content = serde_json::to_string_pretty(&json)
-        .map_err(|e| format!("Failed to serialize .claude.json: {}", e))?;
-
-    std::fs::write(&claude_json_path, updated_content)
-        .map_err(|e| format!("Failed to write .claude.json: {}", e))?;
+    managed_file::update_json(&claude_json_path, |json| {
+        if let Some(projects) = json.get_mut("projects").and_then(|p| p.as_object_mut()) {
+            projects.remove(project_path);
+        }
+        Ok(())
+    })
+    .map_err(|e| format!("Failed to write .claude.json: {}", e))?;
 
     println!("✅ Removed project from .claude.json");
     Ok(())
@@ -2566,6 +3537,24 @@ fn get_project_session_ids(project_sessions_dir: &PathBuf) -> Vec<String> {
     session_ids
 }
 
+/// Per-session files and directories that `cleanup_session_data` deletes
+fn session_data_paths(home_dir: &std::path::Path, session_ids: &[String]) -> Vec<PathBuf> {
+    let claude_dir = home_dir.join(".claude");
+
+    session_ids
+        .iter()
+        .flat_map(|session_id| {
+            [
+                claude_dir.join("todos").join(session_id),
+                claude_dir.join("file-history").join(session_id),
+                claude_dir.join("debug").join(format!("{}.txt", session_id)),
+                claude_dir.join("session-env").join(session_id),
+            ]
+        })
+        .filter(|path| path.exists())
+        .collect()
+}
+
 /// Clean up all session-related data for given session IDs
 fn cleanup_session_data(home_dir: &PathBuf, session_ids: &[String]) {
     let claude_dir = home_dir.join(".claude");
@@ -2616,34 +3605,36 @@ fn filter_history_file(home_dir: &PathBuf, project_path: &str) -> Result<(), Str
         return Ok(());
     }
 
-    let content = std::fs::read_to_string(&history_path)
-        .map_err(|e| format!("Failed to read history.jsonl: {}", e))?;
-
+    // Claude Code appends to history.jsonl while running, so re-filter on conflict
     let mut removed_count = 0;
-    let filtered_lines: Vec<String> = content
-        .lines()
-        .filter(|line| {
-            if let Ok(json) = serde_json::from_str::<Value>(line) {
-                if let Some(project) = json.get("project").and_then(|p| p.as_str()) {
-                    if project == project_path {
-                        removed_count += 1;
-                        return false;
+    managed_file::update(&history_path, |content| {
+        removed_count = 0;
+        let filtered_lines: Vec<String> = content
+            .unwrap_or_default()
+            .lines()
+            .filter(|line| {
+                if let Ok(json) = serde_json::from_str::<Value>(line) {
+                    if let Some(project) = json.get("project").and_then(|p| p.as_str()) {
+                        if project == project_path {
+                            removed_count += 1;
+                            return false;
+                        }
                     }
                 }
-            }
-            true // Keep lines that don't match or can't be parsed
-        })
-        .map(String::from)
-        .collect();
+                true // Keep lines that don't match or can't be parsed
+            })
+            .map(String::from)
+            .collect();
 
-    let filtered_content = if filtered_lines.is_empty() {
-        String::new()
-    } else {
-        filtered_lines.join("\n") + "\n"
-    };
+        let filtered_content = if filtered_lines.is_empty() {
+            String::new()
+        } else {
+            filtered_lines.join("\n") + "\n"
+        };
 
-    std::fs::write(&history_path, filtered_content)
-        .map_err(|e| format!("Failed to write history.jsonl: {}", e))?;
+        Ok(Some(filtered_content))
+    })
+    .map_err(|e| format!("Failed to write history.jsonl: {}", e))?;
 
     if removed_count > 0 {
         println!("✅ Removed {} history entries", removed_count);
@@ -2660,6 +3651,23 @@ pub async fn delete_project_config(project_path: String) -> Result<(), String> {
     let app_config_path = home_dir.join(APP_CONFIG_DIR);
     let registry_path = app_config_path.join("project-registry.json");
 
+    // Everything below deletes data for good, so journal all of it first
+    let sanitized_path = sanitize_project_path_for_dir(&project_path);
+    let project_sessions_dir = home_dir
+        .join(".claude")
+        .join("projects")
+        .join(&sanitized_path);
+    let session_ids = get_project_session_ids(&project_sessions_dir);
+
+    let mut journal_paths = vec![
+        registry_path.clone(),
+        home_dir.join(".claude.json"),
+        home_dir.join(".claude").join("history.jsonl"),
+        project_sessions_dir.clone(),
+    ];
+    journal_paths.extend(session_data_paths(&home_dir, &session_ids));
+    journal::record("delete_project_config", &journal_paths)?;
+
     // 1. Remove from registry
     let mut registry = read_project_registry()?;
     registry.retain(|entry| entry.project_path != project_path);
@@ -2668,7 +3676,7 @@ pub async fn delete_project_config(project_path: String) -> Result<(), String> {
     let json_content = serde_json::to_string_pretty(&registry)
         .map_err(|e| format!("Failed to serialize registry: {}", e))?;
 
-    std::fs::write(&registry_path, json_content)
+    managed_file::write(&registry_path, &json_content)
         .map_err(|e| format!("Failed to write registry: {}", e))?;
 
     // 2. Remove from ~/.claude.json
@@ -2677,15 +3685,7 @@ pub async fn delete_project_config(project_path: String) -> Result<(), String> {
         // Continue - don't fail the whole operation
     }
 
-    // 3. Get session IDs before deleting project sessions directory
-    let sanitized_path = sanitize_project_path_for_dir(&project_path);
-    let project_sessions_dir = home_dir
-        .join(".claude")
-        .join("projects")
-        .join(&sanitized_path);
-    let session_ids = get_project_session_ids(&project_sessions_dir);
-
-    // 4. Delete project sessions directory
+    // 3. Delete project sessions directory
     if project_sessions_dir.exists() {
         if let Err(e) = std::fs::remove_dir_all(&project_sessions_dir) {
             eprintln!("⚠️  Warning: Failed to delete project sessions: {}", e);
@@ -2694,12 +3694,12 @@ pub async fn delete_project_config(project_path: String) -> Result<(), String> {
         }
     }
 
-    // 5. Clean up session-related data
+    // 4. Clean up session-related data
     if !session_ids.is_empty() {
         cleanup_session_data(&home_dir, &session_ids);
     }
 
-    // 6. Filter history file
+    // 5. Filter history file
     if let Err(e) = filter_history_file(&home_dir, &project_path) {
         eprintln!("⚠️  Warning: Failed to filter history: {}", e);
     }
@@ -2711,6 +3711,7 @@ pub async fn delete_project_config(project_path: String) -> Result<(), String> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::hook_events::HookEventKind;
     use std::fs;
 
     // Helper function to create temporary test directories
@@ -2729,6 +3730,58 @@ mod tests {
         fs::write(path, content).expect("Failed to write JSON file");
     }
 
+    #[test]
+    fn test_sync_managed_hooks_installs_exactly_subscribed_events() {
+        let mut settings = serde_json::json!({
+            "Stop": [{ "hooks": [{ "type": "command", "command": "old", "__ccfoundation__": true }] }],
+            "PreToolUse": [{
+                "matcher": "Bash",
+                "hooks": [
+                    { "type": "command", "command": "lint" },
+                    { "type": "command", "command": "old", "__ccfoundation__": true }
+                ]
+            }]
+        });
+        let hooks_obj = settings.as_object_mut().unwrap();
+        let hook_settings = HookSettings {
+            subscribed_events: vec![HookEventKind::Stop, HookEventKind::SessionStart],
+            ..Default::default()
+        };
+
+        assert!(sync_managed_hooks(hooks_obj, &hook_settings).unwrap());
+        let mut events: Vec<&String> = hooks_obj.keys().collect();
+        events.sort();
+        assert_eq!(events, vec!["PreToolUse", "SessionStart", "Stop"]);
+        assert_eq!(
+            hooks_obj["PreToolUse"],
+            serde_json::json!([{
+                "matcher": "Bash",
+                "hooks": [{ "type": "command", "command": "lint" }]
+            }])
+        );
+        assert_eq!(
+            hooks_obj["Stop"][0]["hooks"][0]["command"],
+            get_latest_hook_command()["command"]
+        );
+        assert!(!sync_managed_hooks(hooks_obj, &hook_settings).unwrap());
+
+        assert!(remove_managed_hooks(hooks_obj, &[]));
+        assert!(!has_managed_hooks(hooks_obj));
+        assert_eq!(hooks_obj.keys().collect::<Vec<_>>(), vec!["PreToolUse"]);
+    }
+
+    #[test]
+    fn test_shell_quote_escapes_single_quotes() {
+        assert_eq!(
+            shell_quote("/home/o'brien/.ccconfig"),
+            r"'/home/o'\''brien/.ccconfig'"
+        );
+        assert_eq!(
+            powershell_quote(r"C:\Users\o'brien"),
+            r"'C:\Users\o''brien'"
+        );
+    }
+
     #[test]
     fn test_sanitize_project_path_for_dir_basic() {
         let result = sanitize_project_path_for_dir("/Users/huutri/code/ccmate");
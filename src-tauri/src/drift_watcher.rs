@@ -0,0 +1,91 @@
+//! Live drift notifications for ~/.claude/settings.json
+//!
+//! A background thread watches ~/.claude and re-runs `read_config_drift`
+//! whenever settings.json changes, emitting `DRIFT_EVENT` to the frontend
+//! each time a new drift from the active store shows up.
+
+use notify::{RecursiveMode, Watcher};
+use std::sync::mpsc;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+use crate::commands::{read_config_drift, ConfigDrift};
+
+/// Emitted with a `ConfigDrift` payload when settings.json drifts from the active store
+pub const DRIFT_EVENT: &str = "config-drift-detected";
+
+/// Editors and Claude Code write settings.json in bursts, so wait for it to settle
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Watch ~/.claude/settings.json and emit `DRIFT_EVENT` whenever new drift appears
+pub fn start_drift_watcher(app_handle: AppHandle) -> Result<(), String> {
+    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
+    let claude_dir = home_dir.join(".claude");
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)
+        .map_err(|e| format!("Failed to create settings watcher: {}", e))?;
+
+    // On a fresh install ~/.claude doesn't exist until Claude Code first runs
+    std::fs::create_dir_all(&claude_dir)
+        .map_err(|e| format!("Failed to create {}: {}", claude_dir.display(), e))?;
+
+    // Watch the directory, since atomic saves replace the file itself
+    watcher
+        .watch(&claude_dir, RecursiveMode::NonRecursive)
+        .map_err(|e| format!("Failed to watch {}: {}", claude_dir.display(), e))?;
+
+    println!("👀 Watching {} for drift", claude_dir.display());
+
+    std::thread::spawn(move || {
+        // Keep the watcher alive for as long as this thread runs
+        let _watcher = watcher;
+        let mut last_drift: Option<ConfigDrift> = None;
+
+        check_drift(&app_handle, &mut last_drift);
+
+        while let Ok(event) = rx.recv() {
+            let touches_settings = event.is_ok_and(|event| {
+                event
+                    .paths
+                    .iter()
+                    .any(|path| path.file_name().is_some_and(|name| name == "settings.json"))
+            });
+            if !touches_settings {
+                continue;
+            }
+
+            // Drain the rest of the burst before comparing
+            while rx.recv_timeout(DEBOUNCE).is_ok() {}
+
+            check_drift(&app_handle, &mut last_drift);
+        }
+    });
+
+    Ok(())
+}
+
+fn check_drift(app_handle: &AppHandle, last_drift: &mut Option<ConfigDrift>) {
+    let drift = match read_config_drift() {
+        Ok(drift) => drift.filter(|drift| !drift.changes.is_empty()),
+        Err(e) => {
+            eprintln!("Failed to check settings drift: {}", e);
+            return;
+        }
+    };
+
+    // Only notify when the drift is new, not on every write of the same state
+    if drift != *last_drift {
+        if let Some(drift) = &drift {
+            println!(
+                "⚠️  settings.json drifted from store '{}' ({} changes)",
+                drift.store_title,
+                drift.changes.len()
+            );
+            if let Err(e) = app_handle.emit(DRIFT_EVENT, drift) {
+                eprintln!("Failed to emit drift event: {}", e);
+            }
+        }
+        *last_drift = drift;
+    }
+}
@@ -0,0 +1,129 @@
+//! Shared secret between the installed hook command and the hook server
+//!
+//! The secret lives in ~/.ccconfig/hook-secret, readable only by the user.
+//! The hook command reads it when it runs and sends it in `SECRET_HEADER`,
+//! so it never ends up in settings.json or the backups and bundles made from
+//! it. The server rejects requests without it, which keeps web pages and
+//! other local processes from posting fake events.
+
+use crate::managed_file;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD as BASE64;
+use base64::Engine;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::OsRng;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+pub const SECRET_HEADER: &str = "x-ccfoundation-secret";
+
+const SECRET_FILE: &str = ".ccconfig/hook-secret";
+const SECRET_LEN: usize = 32;
+
+/// The secret as last read or written, so requests don't hit the disk
+static CURRENT_SECRET: Mutex<Option<String>> = Mutex::new(None);
+
+pub fn secret_path(home_dir: &Path) -> PathBuf {
+    home_dir.join(SECRET_FILE)
+}
+
+fn write_secret(home_dir: &Path) -> Result<String, String> {
+    let mut bytes = [0u8; SECRET_LEN];
+    OsRng.fill_bytes(&mut bytes);
+    let secret = BASE64.encode(bytes);
+
+    let path = secret_path(home_dir);
+    managed_file::write(&path, &secret)?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))
+            .map_err(|e| format!("Failed to restrict {}: {}", path.display(), e))?;
+    }
+
+    Ok(secret)
+}
+
+/// The install's secret, generated on first use
+pub fn read_or_create_in(home_dir: &Path) -> Result<String, String> {
+    let path = secret_path(home_dir);
+    match std::fs::read_to_string(&path) {
+        Ok(secret) if !secret.trim().is_empty() => Ok(secret.trim().to_string()),
+        Ok(_) => write_secret(home_dir),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => write_secret(home_dir),
+        Err(e) => Err(format!("Failed to read hook secret: {}", e)),
+    }
+}
+
+pub fn current_secret() -> Result<String, String> {
+    let mut current = CURRENT_SECRET.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(secret) = current.as_ref() {
+        return Ok(secret.clone());
+    }
+
+    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
+    let secret = read_or_create_in(&home_dir)?;
+    *current = Some(secret.clone());
+    Ok(secret)
+}
+
+/// Replace the secret; hooks still sending the old one are rejected from now on
+pub fn rotate() -> Result<(), String> {
+    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
+    let secret = write_secret(&home_dir)?;
+    *CURRENT_SECRET.lock().unwrap_or_else(|e| e.into_inner()) = Some(secret);
+    Ok(())
+}
+
+/// Compare in constant time, so response timing doesn't leak the secret
+fn secrets_match(expected: &str, provided: &str) -> bool {
+    expected.len() == provided.len()
+        && expected
+            .bytes()
+            .zip(provided.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Whether `provided` (the request's `SECRET_HEADER`) is the current secret
+pub fn verify(provided: Option<&str>) -> Result<bool, String> {
+    let expected = current_secret()?;
+    Ok(provided.is_some_and(|provided| secrets_match(&expected, provided.trim())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_read_or_create_in_keeps_secret_until_rotated() {
+        let home = tempdir().unwrap();
+
+        let secret = read_or_create_in(home.path()).unwrap();
+        assert_eq!(secret.len(), 43);
+        assert_eq!(read_or_create_in(home.path()).unwrap(), secret);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(secret_path(home.path()))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        let rotated = write_secret(home.path()).unwrap();
+        assert_ne!(rotated, secret);
+        assert_eq!(read_or_create_in(home.path()).unwrap(), rotated);
+    }
+
+    #[test]
+    fn test_secrets_match() {
+        assert!(secrets_match("abc", "abc"));
+        assert!(!secrets_match("abc", "abd"));
+        assert!(!secrets_match("abc", "ab"));
+        assert!(!secrets_match("abc", ""));
+    }
+}
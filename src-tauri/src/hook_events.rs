@@ -0,0 +1,243 @@
+//! Claude Code hook events
+//!
+//! Every hook invocation posts a JSON object with the session fields and a
+//! `hook_event_name`; the remaining fields depend on the event. `HookEvent`
+//! keeps the payload as received so nothing is lost when it's logged, and
+//! `HookEvent::payload` reads the event-specific fields into `HookPayload`.
+
+use serde_json::{Map, Value};
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct HookEvent {
+    pub session_id: String,
+    pub transcript_path: String,
+    pub cwd: String,
+    pub hook_event_name: String,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HookEventKind {
+    PreToolUse,
+    PostToolUse,
+    Notification,
+    UserPromptSubmit,
+    Stop,
+    SubagentStop,
+    PreCompact,
+    SessionStart,
+    SessionEnd,
+}
+
+impl HookEventKind {
+    pub const ALL: [HookEventKind; 9] = [
+        HookEventKind::PreToolUse,
+        HookEventKind::PostToolUse,
+        HookEventKind::Notification,
+        HookEventKind::UserPromptSubmit,
+        HookEventKind::Stop,
+        HookEventKind::SubagentStop,
+        HookEventKind::PreCompact,
+        HookEventKind::SessionStart,
+        HookEventKind::SessionEnd,
+    ];
+
+    /// The events the app has always subscribed to
+    pub const DEFAULT: [HookEventKind; 3] = [
+        HookEventKind::Notification,
+        HookEventKind::Stop,
+        HookEventKind::PreToolUse,
+    ];
+
+    /// Key of the event under `hooks` in settings.json
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HookEventKind::PreToolUse => "PreToolUse",
+            HookEventKind::PostToolUse => "PostToolUse",
+            HookEventKind::Notification => "Notification",
+            HookEventKind::UserPromptSubmit => "UserPromptSubmit",
+            HookEventKind::Stop => "Stop",
+            HookEventKind::SubagentStop => "SubagentStop",
+            HookEventKind::PreCompact => "PreCompact",
+            HookEventKind::SessionStart => "SessionStart",
+            HookEventKind::SessionEnd => "SessionEnd",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<HookEventKind> {
+        HookEventKind::ALL
+            .into_iter()
+            .find(|kind| kind.as_str() == name)
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionStartSource {
+    Startup,
+    Resume,
+    Clear,
+    Compact,
+    #[serde(other)]
+    Other,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionEndReason {
+    Clear,
+    Logout,
+    PromptInputExit,
+    #[serde(other)]
+    Other,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CompactTrigger {
+    Manual,
+    Auto,
+    #[serde(other)]
+    Other,
+}
+
+/// Event-specific fields, tagged by `hook_event_name` like the payload itself
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "hook_event_name")]
+pub enum HookPayload {
+    PreToolUse {
+        tool_name: String,
+        #[serde(default)]
+        tool_input: Value,
+    },
+    PostToolUse {
+        tool_name: String,
+        #[serde(default)]
+        tool_input: Value,
+        #[serde(default)]
+        tool_response: Value,
+    },
+    Notification {
+        message: String,
+    },
+    UserPromptSubmit {
+        prompt: String,
+    },
+    Stop {
+        /// Set when Claude Code is already continuing because of a stop hook
+        #[serde(default)]
+        stop_hook_active: bool,
+    },
+    SubagentStop {
+        #[serde(default)]
+        stop_hook_active: bool,
+    },
+    PreCompact {
+        trigger: CompactTrigger,
+        #[serde(default)]
+        custom_instructions: Option<String>,
+    },
+    SessionStart {
+        source: SessionStartSource,
+    },
+    SessionEnd {
+        reason: SessionEndReason,
+    },
+}
+
+impl HookEvent {
+    pub fn kind(&self) -> Option<HookEventKind> {
+        HookEventKind::from_name(&self.hook_event_name)
+    }
+
+    pub fn tool_name(&self) -> Option<&str> {
+        self.extra.get("tool_name").and_then(|v| v.as_str())
+    }
+
+    /// Typed view of the event-specific fields
+    pub fn payload(&self) -> Result<HookPayload, String> {
+        if self.kind().is_none() {
+            return Err(format!("Unknown hook event: {}", self.hook_event_name));
+        }
+
+        let mut fields = self.extra.clone();
+        fields.insert(
+            "hook_event_name".to_string(),
+            Value::String(self.hook_event_name.clone()),
+        );
+        serde_json::from_value(Value::Object(fields))
+            .map_err(|e| format!("Invalid {} hook event: {}", self.hook_event_name, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn event(fields: Value) -> HookEvent {
+        let mut payload = json!({
+            "session_id": "s1",
+            "transcript_path": "/tmp/s1.jsonl",
+            "cwd": "/work",
+        });
+        payload
+            .as_object_mut()
+            .unwrap()
+            .extend(fields.as_object().unwrap().clone());
+        serde_json::from_value(payload).unwrap()
+    }
+
+    #[test]
+    fn test_payload_reads_event_fields() {
+        let post = event(json!({
+            "hook_event_name": "PostToolUse",
+            "tool_name": "Write",
+            "tool_input": { "file_path": "/work/a.txt" },
+            "tool_response": { "success": true },
+        }));
+        assert_eq!(post.kind(), Some(HookEventKind::PostToolUse));
+        assert_eq!(
+            post.payload().unwrap(),
+            HookPayload::PostToolUse {
+                tool_name: "Write".into(),
+                tool_input: json!({ "file_path": "/work/a.txt" }),
+                tool_response: json!({ "success": true }),
+            }
+        );
+
+        let start = event(json!({ "hook_event_name": "SessionStart", "source": "resume" }));
+        assert_eq!(
+            start.payload().unwrap(),
+            HookPayload::SessionStart {
+                source: SessionStartSource::Resume
+            }
+        );
+
+        let prompt = event(json!({ "hook_event_name": "UserPromptSubmit", "prompt": "hi" }));
+        assert_eq!(
+            prompt.payload().unwrap(),
+            HookPayload::UserPromptSubmit {
+                prompt: "hi".into()
+            }
+        );
+    }
+
+    #[test]
+    fn test_payload_tolerates_new_values_but_not_unknown_events() {
+        let end = event(json!({ "hook_event_name": "SessionEnd", "reason": "shutdown" }));
+        assert_eq!(
+            end.payload().unwrap(),
+            HookPayload::SessionEnd {
+                reason: SessionEndReason::Other
+            }
+        );
+
+        let unknown = event(json!({ "hook_event_name": "Teleport" }));
+        assert_eq!(unknown.kind(), None);
+        assert!(unknown.payload().is_err());
+        // The raw fields are still there for the log
+        assert_eq!(unknown.hook_event_name, "Teleport");
+    }
+}
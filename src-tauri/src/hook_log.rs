@@ -0,0 +1,421 @@
+//! Persistent log of hook events
+//!
+//! Every event the hook server receives is appended as one JSON line to
+//! ~/.ccconfig/hook-events/events.jsonl. Once that file grows past
+//! `MAX_FILE_BYTES` it is renamed to events-{rotated_at}.jsonl and a fresh one
+//! is started; only the newest `MAX_ROTATED_FILES` rotated files are kept.
+
+use crate::hook_events::HookEvent;
+use crate::hook_policy::{PermissionDecision, PolicyOutcome};
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const LOG_DIR: &str = ".ccconfig/hook-events";
+const CURRENT_FILE: &str = "events.jsonl";
+const ROTATED_PREFIX: &str = "events-";
+const ROTATED_TIME_FORMAT: &str = "%Y%m%dT%H%M%S%3fZ";
+
+const MAX_FILE_BYTES: u64 = 8 * 1024 * 1024;
+const MAX_ROTATED_FILES: usize = 9;
+
+const DEFAULT_PAGE_SIZE: usize = 100;
+const MAX_PAGE_SIZE: usize = 1000;
+
+/// Hooks can fire concurrently; appends and rotation must not interleave
+static APPEND_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct HookLogEntry {
+    pub id: String,
+    /// Milliseconds since the epoch
+    #[serde(rename = "receivedAt")]
+    pub received_at: i64,
+    pub event: HookEvent,
+    /// Policy decision returned for the event, if a rule matched
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decision: Option<PolicyOutcome>,
+}
+
+/// Filters are combined with AND; unset ones match everything
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default)]
+pub struct HookLogQuery {
+    #[serde(rename = "sessionId", default)]
+    pub session_id: Option<String>,
+    /// Matches events whose cwd is this directory or inside it
+    #[serde(default)]
+    pub cwd: Option<String>,
+    #[serde(rename = "eventName", default)]
+    pub event_name: Option<String>,
+    #[serde(rename = "toolName", default)]
+    pub tool_name: Option<String>,
+    #[serde(default)]
+    pub decision: Option<PermissionDecision>,
+    /// Inclusive lower bound on `receivedAt`
+    #[serde(default)]
+    pub since: Option<i64>,
+    /// Exclusive upper bound on `receivedAt`
+    #[serde(default)]
+    pub until: Option<i64>,
+    #[serde(default)]
+    pub offset: usize,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+impl HookLogQuery {
+    fn matches(&self, entry: &HookLogEntry) -> bool {
+        let event = &entry.event;
+        self.session_id
+            .as_ref()
+            .is_none_or(|id| *id == event.session_id)
+            && self
+                .cwd
+                .as_ref()
+                .is_none_or(|cwd| Path::new(&event.cwd).starts_with(cwd))
+            && self
+                .event_name
+                .as_ref()
+                .is_none_or(|name| *name == event.hook_event_name)
+            && self
+                .tool_name
+                .as_ref()
+                .is_none_or(|tool| event.tool_name() == Some(tool.as_str()))
+            && self.decision.is_none_or(|decision| {
+                entry.decision.as_ref().map(|d| d.decision) == Some(decision)
+            })
+            && self.since.is_none_or(|since| entry.received_at >= since)
+            && self.until.is_none_or(|until| entry.received_at < until)
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct HookLogPage {
+    /// Newest first
+    pub entries: Vec<HookLogEntry>,
+    /// Number of events matching the filters, across all pages
+    pub total: usize,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+fn get_log_dir() -> Result<PathBuf, String> {
+    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
+    Ok(home_dir.join(LOG_DIR))
+}
+
+/// Sortable by time, with a random suffix in case two land in the same millisecond
+fn new_entry_id(now: chrono::DateTime<chrono::Utc>) -> String {
+    format!(
+        "{}-{}",
+        now.format(ROTATED_TIME_FORMAT),
+        &uuid::Uuid::new_v4().simple().to_string()[..6]
+    )
+}
+
+/// Rotated files, oldest first
+fn rotated_files(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map_err(|e| format!("Failed to read hook event log: {}", e))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with(ROTATED_PREFIX) && n.ends_with(".jsonl"))
+        })
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// When a rotated file was closed; everything in it was received before then
+fn rotated_at(path: &Path) -> Option<i64> {
+    let name = path.file_stem()?.to_str()?.strip_prefix(ROTATED_PREFIX)?;
+    chrono::NaiveDateTime::parse_from_str(name, ROTATED_TIME_FORMAT)
+        .ok()
+        .map(|t| t.and_utc().timestamp_millis())
+}
+
+fn rotate(dir: &Path, now: chrono::DateTime<chrono::Utc>) -> Result<(), String> {
+    let rotated = dir.join(format!(
+        "{}{}.jsonl",
+        ROTATED_PREFIX,
+        now.format(ROTATED_TIME_FORMAT)
+    ));
+    std::fs::rename(dir.join(CURRENT_FILE), &rotated)
+        .map_err(|e| format!("Failed to rotate hook event log: {}", e))?;
+
+    let files = rotated_files(dir)?;
+    let excess = files.len().saturating_sub(MAX_ROTATED_FILES);
+    for path in &files[..excess] {
+        std::fs::remove_file(path)
+            .map_err(|e| format!("Failed to remove {}: {}", path.display(), e))?;
+    }
+    Ok(())
+}
+
+pub fn append_in(
+    dir: &Path,
+    event: &HookEvent,
+    decision: Option<PolicyOutcome>,
+) -> Result<HookLogEntry, String> {
+    let now = chrono::Utc::now();
+    let entry = HookLogEntry {
+        id: new_entry_id(now),
+        received_at: now.timestamp_millis(),
+        event: event.clone(),
+        decision,
+    };
+    let mut line = serde_json::to_string(&entry)
+        .map_err(|e| format!("Failed to serialize hook event: {}", e))?;
+    line.push('\n');
+
+    let _guard = APPEND_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    std::fs::create_dir_all(dir)
+        .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+
+    // Events carry prompts, file contents and tool input, so keep them private
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700))
+            .map_err(|e| format!("Failed to restrict {}: {}", dir.display(), e))?;
+    }
+
+    let path = dir.join(CURRENT_FILE);
+    if std::fs::metadata(&path).is_ok_and(|m| m.len() >= MAX_FILE_BYTES) {
+        rotate(dir, now)?;
+    }
+
+    let mut options = std::fs::OpenOptions::new();
+    options.create(true).append(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options
+        .open(&path)
+        .and_then(|mut file| file.write_all(line.as_bytes()))
+        .map_err(|e| format!("Failed to write hook event log: {}", e))?;
+
+    Ok(entry)
+}
+
+pub fn append(event: &HookEvent, decision: Option<PolicyOutcome>) -> Result<HookLogEntry, String> {
+    append_in(&get_log_dir()?, event, decision)
+}
+
+fn read_entries(path: &Path) -> Result<Vec<HookLogEntry>, String> {
+    let file = match std::fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+    };
+
+    // A line cut short by a crash mid-append is skipped rather than failing the query
+    Ok(std::io::BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| serde_json::from_str(&line).ok())
+        .collect())
+}
+
+pub fn query_in(dir: &Path, query: &HookLogQuery) -> Result<HookLogPage, String> {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+
+    let mut files = rotated_files(dir)?;
+    // Rotated files closed before `since` can't hold anything newer
+    if let Some(since) = query.since {
+        files.retain(|path| rotated_at(path).is_none_or(|at| at >= since));
+    }
+    files.push(dir.join(CURRENT_FILE));
+
+    let mut entries = Vec::new();
+    let mut total = 0;
+    for path in files.iter().rev() {
+        for entry in read_entries(path)?.into_iter().rev() {
+            if !query.matches(&entry) {
+                continue;
+            }
+            if total >= query.offset && entries.len() < limit {
+                entries.push(entry);
+            }
+            total += 1;
+        }
+    }
+
+    Ok(HookLogPage {
+        entries,
+        total,
+        offset: query.offset,
+        limit,
+    })
+}
+
+pub fn query(query: &HookLogQuery) -> Result<HookLogPage, String> {
+    query_in(&get_log_dir()?, query)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    fn event(session: &str, cwd: &str, name: &str, tool: Option<&str>) -> HookEvent {
+        serde_json::from_value(json!({
+            "session_id": session,
+            "transcript_path": format!("/tmp/{}.jsonl", session),
+            "cwd": cwd,
+            "hook_event_name": name,
+            "tool_name": tool,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_query_in_filters_and_paginates_newest_first() {
+        let dir = tempdir().unwrap();
+        append_in(
+            dir.path(),
+            &event("a", "/work/app", "PreToolUse", Some("Bash")),
+            None,
+        )
+        .unwrap();
+        let denied = PolicyOutcome {
+            decision: PermissionDecision::Deny,
+            rule_id: "r1".into(),
+            reason: "No edits".into(),
+        };
+        append_in(
+            dir.path(),
+            &event("a", "/work/app/src", "PreToolUse", Some("Edit")),
+            Some(denied.clone()),
+        )
+        .unwrap();
+        append_in(dir.path(), &event("b", "/work/other", "Stop", None), None).unwrap();
+        let last = append_in(
+            dir.path(),
+            &event("a", "/work/app", "PreToolUse", Some("Bash")),
+            None,
+        )
+        .unwrap();
+
+        // A torn line from an interrupted write is ignored
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(dir.path().join(CURRENT_FILE))
+            .unwrap()
+            .write_all(b"{\"id\":\"trunc")
+            .unwrap();
+
+        let all = query_in(dir.path(), &HookLogQuery::default()).unwrap();
+        assert_eq!(all.total, 4);
+        assert_eq!(all.entries[0].id, last.id);
+
+        let app = query_in(
+            dir.path(),
+            &HookLogQuery {
+                cwd: Some("/work/app".into()),
+                event_name: Some("PreToolUse".into()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(app.total, 3);
+
+        let page = query_in(
+            dir.path(),
+            &HookLogQuery {
+                session_id: Some("a".into()),
+                tool_name: Some("Bash".into()),
+                offset: 1,
+                limit: Some(1),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(page.total, 2);
+        assert_eq!(page.entries.len(), 1);
+        assert_eq!(page.entries[0].event.cwd, "/work/app");
+        assert_ne!(page.entries[0].id, last.id);
+
+        let deny = query_in(
+            dir.path(),
+            &HookLogQuery {
+                decision: Some(PermissionDecision::Deny),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(deny.total, 1);
+        assert_eq!(deny.entries[0].decision, Some(denied));
+
+        let later = query_in(
+            dir.path(),
+            &HookLogQuery {
+                since: Some(last.received_at + 1),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(later.total, 0);
+    }
+
+    #[test]
+    fn test_append_in_rotates_and_prunes() {
+        let dir = tempdir().unwrap();
+        std::fs::write(
+            dir.path().join(CURRENT_FILE),
+            vec![b' '; MAX_FILE_BYTES as usize],
+        )
+        .unwrap();
+        for i in 0..MAX_ROTATED_FILES + 2 {
+            std::fs::write(
+                dir.path()
+                    .join(format!("{}20200101T0000{:02}000Z.jsonl", ROTATED_PREFIX, i)),
+                "",
+            )
+            .unwrap();
+        }
+
+        append_in(dir.path(), &event("a", "/work", "Stop", None), None).unwrap();
+
+        let rotated = rotated_files(dir.path()).unwrap();
+        assert_eq!(rotated.len(), MAX_ROTATED_FILES);
+        // The file just rotated out is the newest one kept
+        assert_eq!(
+            std::fs::metadata(rotated.last().unwrap()).unwrap().len(),
+            MAX_FILE_BYTES
+        );
+        assert_eq!(
+            query_in(dir.path(), &HookLogQuery::default())
+                .unwrap()
+                .total,
+            1
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_append_in_keeps_log_private() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempdir().unwrap();
+        let log_dir = dir.path().join("hook-events");
+
+        append_in(&log_dir, &event("a", "/work", "Stop", None), None).unwrap();
+
+        let mode = |path: &Path| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&log_dir), 0o700);
+        assert_eq!(mode(&log_dir.join(CURRENT_FILE)), 0o600);
+    }
+}
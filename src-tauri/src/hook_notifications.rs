@@ -0,0 +1,630 @@
+//! Which hook events produce desktop notifications
+//!
+//! Rules in ~/.ccconfig/hook-notifications.json are checked in order and the
+//! first enabled rule that matches an event decides its notification; events
+//! no rule matches stay silent. Each rule renders its own title and body from
+//! a template and can limit itself to one notification per time window, with
+//! the events it held back counted in the next one. During quiet hours only
+//! rules marked to bypass them notify.
+
+use crate::hook_events::{HookEvent, HookEventKind, HookPayload};
+use crate::hook_policy::{compile_regex, PermissionDecision, PolicyOutcome};
+use crate::managed_file;
+use chrono::{NaiveTime, Timelike};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const SETTINGS_FILE: &str = ".ccconfig/hook-notifications.json";
+
+const DEFAULT_TITLE: &str = "Claude Code";
+
+const TIME_FORMAT: &str = "%H:%M";
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct NotificationRule {
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Events the rule applies to; empty means every event
+    #[serde(default)]
+    pub events: Vec<HookEventKind>,
+    /// Regex the whole tool name must match, e.g. `Bash` or `Edit|Write`
+    #[serde(rename = "toolName", default)]
+    pub tool_name: Option<String>,
+    /// Only applies to sessions whose cwd is this directory or inside it
+    #[serde(rename = "projectPath", default)]
+    pub project_path: Option<String>,
+    /// Only applies to PreToolUse events that got this policy decision
+    #[serde(default)]
+    pub decision: Option<PermissionDecision>,
+    /// Title template; "Claude Code" when unset
+    #[serde(default)]
+    pub title: Option<String>,
+    /// Body template, e.g. `Using {tool_name} in {project}`
+    pub body: String,
+    /// At most one notification per this many seconds; 0 sends every one
+    #[serde(rename = "windowSeconds", default)]
+    pub window_seconds: u64,
+    /// Notify even during quiet hours
+    #[serde(rename = "bypassQuietHours", default)]
+    pub bypass_quiet_hours: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// Local time range without notifications; `end` before `start` spans midnight
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct QuietHours {
+    pub enabled: bool,
+    /// "HH:MM"
+    pub start: String,
+    /// "HH:MM"
+    pub end: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct NotificationSettings {
+    pub rules: Vec<NotificationRule>,
+    #[serde(rename = "quietHours", default)]
+    pub quiet_hours: Option<QuietHours>,
+}
+
+impl Default for NotificationSettings {
+    /// Finished tasks, Claude Code's own notifications and blocked tools, the
+    /// events worth interrupting the user for
+    fn default() -> Self {
+        let rule =
+            |id: &str, name: &str, events: Vec<HookEventKind>, body: &str| NotificationRule {
+                id: id.to_string(),
+                name: name.to_string(),
+                enabled: true,
+                events,
+                tool_name: None,
+                project_path: None,
+                decision: None,
+                title: None,
+                body: body.to_string(),
+                window_seconds: 0,
+                bypass_quiet_hours: false,
+            };
+
+        NotificationSettings {
+            rules: vec![
+                NotificationRule {
+                    decision: Some(PermissionDecision::Deny),
+                    ..rule(
+                        "blocked",
+                        "Blocked tools",
+                        vec![HookEventKind::PreToolUse],
+                        "⛔ Blocked {tool_name} tool: {reason}",
+                    )
+                },
+                rule(
+                    "notification",
+                    "Claude Code notifications",
+                    vec![HookEventKind::Notification],
+                    "✅ {message}",
+                ),
+                NotificationRule {
+                    window_seconds: 10,
+                    ..rule(
+                        "stop",
+                        "Finished tasks",
+                        vec![HookEventKind::Stop],
+                        "Task completed successfully in {project}",
+                    )
+                },
+            ],
+            quiet_hours: None,
+        }
+    }
+}
+
+/// A notification ready to show
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderedNotification {
+    pub rule_id: String,
+    pub title: String,
+    pub body: String,
+}
+
+/// When each rule last notified and how many events it held back since
+#[derive(Debug, Default)]
+pub struct NotificationThrottle {
+    windows: HashMap<String, (i64, u32)>,
+}
+
+impl NotificationThrottle {
+    /// Whether `rule` may notify at `now` (milliseconds). If so, returns how
+    /// many of its notifications were held back since the last one.
+    pub fn admit(&mut self, rule: &NotificationRule, now: i64) -> Option<u32> {
+        if rule.window_seconds == 0 {
+            return Some(0);
+        }
+
+        let window_ms = rule.window_seconds.saturating_mul(1000) as i64;
+        match self.windows.get_mut(&rule.id) {
+            Some((last_sent, suppressed)) if now - *last_sent < window_ms => {
+                *suppressed += 1;
+                None
+            }
+            Some((last_sent, suppressed)) => {
+                let held_back = *suppressed;
+                *last_sent = now;
+                *suppressed = 0;
+                Some(held_back)
+            }
+            None => {
+                self.windows.insert(rule.id.clone(), (now, 0));
+                Some(0)
+            }
+        }
+    }
+}
+
+static THROTTLE: Mutex<Option<NotificationThrottle>> = Mutex::new(None);
+
+fn parse_time(time: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(time, TIME_FORMAT)
+        .map_err(|_| format!("Invalid time '{}', expected HH:MM", time))
+}
+
+impl QuietHours {
+    pub fn contains(&self, time: NaiveTime) -> Result<bool, String> {
+        if !self.enabled {
+            return Ok(false);
+        }
+        let start = parse_time(&self.start)?;
+        let end = parse_time(&self.end)?;
+        // Compare at minute precision, like the settings are written
+        let time = NaiveTime::from_hms_opt(time.hour(), time.minute(), 0).unwrap_or(time);
+
+        Ok(if start <= end {
+            start <= time && time < end
+        } else {
+            time >= start || time < end
+        })
+    }
+}
+
+pub fn validate_rule(rule: &NotificationRule) -> Result<(), String> {
+    if rule.body.trim().is_empty() {
+        return Err("Notification body can't be empty".to_string());
+    }
+    if let Some(pattern) = &rule.tool_name {
+        compile_regex(pattern, true)?;
+    }
+    Ok(())
+}
+
+pub fn validate_quiet_hours(quiet_hours: &QuietHours) -> Result<(), String> {
+    parse_time(&quiet_hours.start)?;
+    parse_time(&quiet_hours.end)?;
+    Ok(())
+}
+
+fn rule_matches(
+    rule: &NotificationRule,
+    event: &HookEvent,
+    decision: Option<&PolicyOutcome>,
+) -> Result<bool, String> {
+    if !rule.events.is_empty() && !event.kind().is_some_and(|kind| rule.events.contains(&kind)) {
+        return Ok(false);
+    }
+
+    if let Some(project_path) = &rule.project_path {
+        if !Path::new(&event.cwd).starts_with(project_path) {
+            return Ok(false);
+        }
+    }
+
+    if let Some(pattern) = &rule.tool_name {
+        let Some(tool_name) = event.tool_name() else {
+            return Ok(false);
+        };
+        if !compile_regex(pattern, true)?.is_match(tool_name) {
+            return Ok(false);
+        }
+    }
+
+    if let Some(expected) = rule.decision {
+        if decision.map(|outcome| outcome.decision) != Some(expected) {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+/// Fill in `{placeholder}`s; unknown ones are left as written
+fn render_template(template: &str, values: &HashMap<&str, String>) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        rendered.push_str(&rest[..open]);
+        let after = &rest[open + 1..];
+        match after.find('}') {
+            Some(close) if values.contains_key(&after[..close]) => {
+                rendered.push_str(&values[&after[..close]]);
+                rest = &after[close + 1..];
+            }
+            _ => {
+                rendered.push('{');
+                rest = after;
+            }
+        }
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+fn template_values(
+    event: &HookEvent,
+    decision: Option<&PolicyOutcome>,
+) -> HashMap<&'static str, String> {
+    let message = match event.payload() {
+        Ok(HookPayload::Notification { message }) => message,
+        Ok(HookPayload::UserPromptSubmit { prompt }) => prompt,
+        _ => String::new(),
+    };
+    let project = Path::new(&event.cwd)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| event.cwd.clone());
+
+    HashMap::from([
+        ("event", event.hook_event_name.clone()),
+        (
+            "tool_name",
+            event.tool_name().unwrap_or_default().to_string(),
+        ),
+        ("cwd", event.cwd.clone()),
+        ("project", project),
+        ("session_id", event.session_id.clone()),
+        ("message", message),
+        (
+            "reason",
+            decision
+                .map(|outcome| outcome.reason.clone())
+                .unwrap_or_default(),
+        ),
+    ])
+}
+
+/// The notification `event` produces under `settings` at local time `time`
+/// and `now` (milliseconds), if any
+pub fn evaluate(
+    settings: &NotificationSettings,
+    throttle: &mut NotificationThrottle,
+    event: &HookEvent,
+    decision: Option<&PolicyOutcome>,
+    time: NaiveTime,
+    now: i64,
+) -> Option<RenderedNotification> {
+    let rule =
+        settings
+            .rules
+            .iter()
+            .filter(|rule| rule.enabled)
+            .find(|rule| match rule_matches(rule, event, decision) {
+                Ok(matched) => matched,
+                // Rules are validated when saved, so this only happens to hand-edited ones
+                Err(e) => {
+                    eprintln!("Skipping notification rule {}: {}", rule.id, e);
+                    false
+                }
+            })?;
+
+    if !rule.bypass_quiet_hours {
+        if let Some(quiet_hours) = &settings.quiet_hours {
+            match quiet_hours.contains(time) {
+                Ok(true) => return None,
+                Ok(false) => {}
+                Err(e) => eprintln!("Ignoring quiet hours: {}", e),
+            }
+        }
+    }
+
+    let held_back = throttle.admit(rule, now)?;
+
+    let values = template_values(event, decision);
+    let title = rule
+        .title
+        .as_deref()
+        .map(|title| render_template(title, &values))
+        .unwrap_or_else(|| DEFAULT_TITLE.to_string());
+    let mut body = render_template(&rule.body, &values);
+    if held_back > 0 {
+        body.push_str(&format!(" (+{} more)", held_back));
+    }
+
+    Some(RenderedNotification {
+        rule_id: rule.id.clone(),
+        title,
+        body,
+    })
+}
+
+/// The notification for `event` under the saved settings, right now
+pub fn notification_for(
+    event: &HookEvent,
+    decision: Option<&PolicyOutcome>,
+) -> Result<Option<RenderedNotification>, String> {
+    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
+    let settings = read_settings(&home_dir)?;
+
+    let mut throttle = THROTTLE.lock().unwrap_or_else(|e| e.into_inner());
+    Ok(evaluate(
+        &settings,
+        throttle.get_or_insert_with(NotificationThrottle::default),
+        event,
+        decision,
+        chrono::Local::now().time(),
+        chrono::Utc::now().timestamp_millis(),
+    ))
+}
+
+pub fn settings_path(home_dir: &Path) -> PathBuf {
+    home_dir.join(SETTINGS_FILE)
+}
+
+pub fn read_settings(home_dir: &Path) -> Result<NotificationSettings, String> {
+    let path = settings_path(home_dir);
+    if !path.exists() {
+        return Ok(NotificationSettings::default());
+    }
+
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read notification settings: {}", e))?;
+    serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse notification settings: {}", e))
+}
+
+fn write_settings(home_dir: &Path, settings: &NotificationSettings) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(settings)
+        .map_err(|e| format!("Failed to serialize notification settings: {}", e))?;
+    managed_file::write(&settings_path(home_dir), &content)
+}
+
+/// Append a rule, giving it a fresh id
+pub fn create_rule(
+    home_dir: &Path,
+    mut rule: NotificationRule,
+) -> Result<NotificationRule, String> {
+    validate_rule(&rule)?;
+    rule.id = nanoid::nanoid!(6);
+
+    let mut settings = read_settings(home_dir)?;
+    settings.rules.push(rule.clone());
+    write_settings(home_dir, &settings)?;
+    Ok(rule)
+}
+
+pub fn update_rule(home_dir: &Path, rule: NotificationRule) -> Result<(), String> {
+    validate_rule(&rule)?;
+
+    let mut settings = read_settings(home_dir)?;
+    let existing = settings
+        .rules
+        .iter_mut()
+        .find(|r| r.id == rule.id)
+        .ok_or_else(|| format!("Notification rule '{}' not found", rule.id))?;
+    *existing = rule;
+    write_settings(home_dir, &settings)
+}
+
+pub fn delete_rule(home_dir: &Path, rule_id: &str) -> Result<(), String> {
+    let mut settings = read_settings(home_dir)?;
+    let count = settings.rules.len();
+    settings.rules.retain(|r| r.id != rule_id);
+    if settings.rules.len() == count {
+        return Err(format!("Notification rule '{}' not found", rule_id));
+    }
+    write_settings(home_dir, &settings)
+}
+
+/// Rules are matched in order, so the order is part of the settings
+pub fn reorder_rules(home_dir: &Path, rule_ids: &[String]) -> Result<(), String> {
+    let mut settings = read_settings(home_dir)?;
+    if rule_ids.len() != settings.rules.len()
+        || settings
+            .rules
+            .iter()
+            .any(|rule| !rule_ids.contains(&rule.id))
+    {
+        return Err("Rule order must list every notification rule once".to_string());
+    }
+
+    settings.rules.sort_by_key(|rule| {
+        rule_ids
+            .iter()
+            .position(|id| *id == rule.id)
+            .unwrap_or(usize::MAX)
+    });
+    write_settings(home_dir, &settings)
+}
+
+pub fn set_quiet_hours(home_dir: &Path, quiet_hours: Option<QuietHours>) -> Result<(), String> {
+    if let Some(quiet_hours) = &quiet_hours {
+        validate_quiet_hours(quiet_hours)?;
+    }
+
+    let mut settings = read_settings(home_dir)?;
+    settings.quiet_hours = quiet_hours;
+    write_settings(home_dir, &settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    fn event(fields: serde_json::Value) -> HookEvent {
+        let mut payload = json!({
+            "session_id": "s1",
+            "transcript_path": "/tmp/s1.jsonl",
+            "cwd": "/work/app",
+        });
+        payload
+            .as_object_mut()
+            .unwrap()
+            .extend(fields.as_object().unwrap().clone());
+        serde_json::from_value(payload).unwrap()
+    }
+
+    fn time(time: &str) -> NaiveTime {
+        parse_time(time).unwrap()
+    }
+
+    #[test]
+    fn test_evaluate_first_matching_rule_renders_template() {
+        let mut settings = NotificationSettings::default();
+        settings.rules.push(NotificationRule {
+            id: "edits".into(),
+            name: String::new(),
+            enabled: true,
+            events: vec![HookEventKind::PreToolUse],
+            tool_name: Some("Edit|Write".into()),
+            project_path: Some("/work".into()),
+            decision: None,
+            title: Some("{project}".into()),
+            body: "{tool_name} in {cwd} {unknown}".into(),
+            window_seconds: 0,
+            bypass_quiet_hours: false,
+        });
+        let mut throttle = NotificationThrottle::default();
+        let noon = time("12:00");
+
+        let bash = event(json!({
+            "hook_event_name": "PreToolUse",
+            "tool_name": "Bash",
+            "tool_input": { "command": "ls" }
+        }));
+        // Allowed PreToolUse events stay silent by default
+        assert_eq!(
+            evaluate(&settings, &mut throttle, &bash, None, noon, 0),
+            None
+        );
+
+        let denied = PolicyOutcome {
+            decision: PermissionDecision::Deny,
+            rule_id: "r1".into(),
+            reason: "No".into(),
+        };
+        let blocked = evaluate(&settings, &mut throttle, &bash, Some(&denied), noon, 0).unwrap();
+        assert_eq!(blocked.rule_id, "blocked");
+        assert_eq!(blocked.title, "Claude Code");
+        assert_eq!(blocked.body, "⛔ Blocked Bash tool: No");
+
+        let write = event(json!({
+            "hook_event_name": "PreToolUse",
+            "tool_name": "Write",
+            "tool_input": { "file_path": "a.txt" }
+        }));
+        let edit = evaluate(&settings, &mut throttle, &write, None, noon, 0).unwrap();
+        assert_eq!(edit.title, "app");
+        assert_eq!(edit.body, "Write in /work/app {unknown}");
+
+        let message = event(json!({
+            "hook_event_name": "Notification",
+            "message": "Claude is waiting for your input"
+        }));
+        assert_eq!(
+            evaluate(&settings, &mut throttle, &message, None, noon, 0)
+                .unwrap()
+                .body,
+            "✅ Claude is waiting for your input"
+        );
+    }
+
+    #[test]
+    fn test_evaluate_respects_quiet_hours_and_window() {
+        let mut settings = NotificationSettings {
+            quiet_hours: Some(QuietHours {
+                enabled: true,
+                start: "22:00".into(),
+                end: "07:00".into(),
+            }),
+            ..Default::default()
+        };
+        let mut throttle = NotificationThrottle::default();
+        let stop = event(json!({ "hook_event_name": "Stop" }));
+
+        assert_eq!(
+            evaluate(&settings, &mut throttle, &stop, None, time("23:30"), 0),
+            None
+        );
+        assert_eq!(
+            evaluate(&settings, &mut throttle, &stop, None, time("06:59"), 0),
+            None
+        );
+
+        let day = time("07:00");
+        let first = evaluate(&settings, &mut throttle, &stop, None, day, 0).unwrap();
+        assert_eq!(first.body, "Task completed successfully in app");
+        assert_eq!(
+            evaluate(&settings, &mut throttle, &stop, None, day, 5_000),
+            None
+        );
+        assert_eq!(
+            evaluate(&settings, &mut throttle, &stop, None, day, 9_999),
+            None
+        );
+        let next = evaluate(&settings, &mut throttle, &stop, None, day, 10_000).unwrap();
+        assert_eq!(next.body, "Task completed successfully in app (+2 more)");
+
+        settings.rules[2].bypass_quiet_hours = true;
+        assert!(evaluate(&settings, &mut throttle, &stop, None, time("23:30"), 60_000).is_some());
+    }
+
+    #[test]
+    fn test_rule_crud_keeps_order_and_validates() {
+        let home = tempdir().unwrap();
+        assert_eq!(
+            read_settings(home.path()).unwrap(),
+            NotificationSettings::default()
+        );
+
+        let mut rule = NotificationSettings::default().rules[1].clone();
+        rule.tool_name = Some("(".into());
+        assert!(create_rule(home.path(), rule.clone()).is_err());
+
+        rule.tool_name = None;
+        let created = create_rule(home.path(), rule).unwrap();
+        let ids: Vec<String> = read_settings(home.path())
+            .unwrap()
+            .rules
+            .into_iter()
+            .map(|rule| rule.id)
+            .collect();
+        assert_eq!(ids, vec!["blocked", "notification", "stop", &created.id]);
+
+        let reversed: Vec<String> = ids.iter().rev().cloned().collect();
+        reorder_rules(home.path(), &reversed).unwrap();
+        assert!(reorder_rules(home.path(), &reversed[..2]).is_err());
+        delete_rule(home.path(), "blocked").unwrap();
+        assert!(delete_rule(home.path(), "blocked").is_err());
+
+        assert!(set_quiet_hours(
+            home.path(),
+            Some(QuietHours {
+                enabled: true,
+                start: "25:00".into(),
+                end: "07:00".into(),
+            })
+        )
+        .is_err());
+
+        let settings = read_settings(home.path()).unwrap();
+        let ids: Vec<&str> = settings.rules.iter().map(|rule| rule.id.as_str()).collect();
+        assert_eq!(ids, vec![created.id.as_str(), "stop", "notification"]);
+        assert_eq!(settings.quiet_hours, None);
+    }
+}
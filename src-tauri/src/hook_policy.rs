@@ -0,0 +1,399 @@
+//! PreToolUse policy rules
+//!
+//! Rules in ~/.ccconfig/hook-policy.json are matched against each PreToolUse
+//! event. Every condition a rule sets must hold; when several rules match, the
+//! strictest decision wins (deny, then ask, then allow). The hook server sends
+//! the result back to Claude Code as the hook's permission decision. When no
+//! rule matches, Claude Code's own permission settings apply.
+
+use crate::hook_events::{HookEvent, HookPayload};
+use crate::managed_file;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+const RULES_FILE: &str = ".ccconfig/hook-policy.json";
+
+/// `tool_input` fields holding the path a tool works on
+const PATH_FIELDS: [&str; 3] = ["file_path", "notebook_path", "path"];
+
+/// Ordered from least to most strict
+#[derive(
+    serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum PermissionDecision {
+    Allow,
+    Ask,
+    Deny,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct PolicyRule {
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    pub decision: PermissionDecision,
+    /// Shown to Claude (deny) or the user (allow, ask)
+    #[serde(default)]
+    pub reason: Option<String>,
+    /// Regex the whole tool name must match, e.g. `Bash` or `Edit|Write`
+    #[serde(rename = "toolName", default)]
+    pub tool_name: Option<String>,
+    /// Regex searched for in `tool_input.command`
+    #[serde(rename = "commandPattern", default)]
+    pub command_pattern: Option<String>,
+    /// Globs for the path in `tool_input`; any of them may match. Relative
+    /// globs are matched against the path relative to the session's cwd.
+    #[serde(rename = "pathGlobs", default)]
+    pub path_globs: Vec<String>,
+    /// Only applies to sessions whose cwd is this directory or inside it
+    #[serde(rename = "projectPath", default)]
+    pub project_path: Option<String>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Default)]
+struct PolicyFile {
+    rules: Vec<PolicyRule>,
+}
+
+/// The decision taken for an event and the rule that made it
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct PolicyOutcome {
+    pub decision: PermissionDecision,
+    #[serde(rename = "ruleId")]
+    pub rule_id: String,
+    pub reason: String,
+}
+
+impl PolicyOutcome {
+    /// Hook output telling Claude Code what to do with the tool call
+    pub fn to_hook_output(&self) -> Value {
+        serde_json::json!({
+            "hookSpecificOutput": {
+                "hookEventName": "PreToolUse",
+                "permissionDecision": self.decision,
+                "permissionDecisionReason": self.reason,
+            }
+        })
+    }
+}
+
+pub(crate) fn compile_regex(pattern: &str, anchored: bool) -> Result<regex::Regex, String> {
+    let source = if anchored {
+        format!("^(?:{})$", pattern)
+    } else {
+        pattern.to_string()
+    };
+    regex::Regex::new(&source).map_err(|e| format!("Invalid pattern '{}': {}", pattern, e))
+}
+
+fn compile_glob(glob: &str) -> Result<glob::Pattern, String> {
+    glob::Pattern::new(glob).map_err(|e| format!("Invalid glob '{}': {}", glob, e))
+}
+
+pub fn validate_rule(rule: &PolicyRule) -> Result<(), String> {
+    if let Some(pattern) = &rule.tool_name {
+        compile_regex(pattern, true)?;
+    }
+    if let Some(pattern) = &rule.command_pattern {
+        compile_regex(pattern, false)?;
+    }
+    for glob in &rule.path_globs {
+        compile_glob(glob)?;
+    }
+    Ok(())
+}
+
+fn path_matches(globs: &[String], path: &str, cwd: &str) -> Result<bool, String> {
+    let options = glob::MatchOptions {
+        require_literal_separator: true,
+        ..Default::default()
+    };
+    let path = Path::new(path);
+    let absolute = Path::new(cwd).join(path);
+    let relative = absolute.strip_prefix(cwd).ok();
+
+    for glob in globs {
+        let pattern = compile_glob(glob)?;
+        let matched = if Path::new(glob).is_absolute() {
+            pattern.matches_path_with(&absolute, options)
+        } else {
+            relative.is_some_and(|relative| pattern.matches_path_with(relative, options))
+        };
+        if matched {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+fn rule_matches(
+    rule: &PolicyRule,
+    tool_name: &str,
+    tool_input: &Value,
+    cwd: &str,
+) -> Result<bool, String> {
+    if let Some(project_path) = &rule.project_path {
+        if !Path::new(cwd).starts_with(project_path) {
+            return Ok(false);
+        }
+    }
+
+    if let Some(pattern) = &rule.tool_name {
+        if !compile_regex(pattern, true)?.is_match(tool_name) {
+            return Ok(false);
+        }
+    }
+
+    if let Some(pattern) = &rule.command_pattern {
+        let Some(command) = tool_input.get("command").and_then(|c| c.as_str()) else {
+            return Ok(false);
+        };
+        if !compile_regex(pattern, false)?.is_match(command) {
+            return Ok(false);
+        }
+    }
+
+    if !rule.path_globs.is_empty() {
+        let Some(path) = PATH_FIELDS
+            .iter()
+            .find_map(|field| tool_input.get(*field).and_then(|p| p.as_str()))
+        else {
+            return Ok(false);
+        };
+        if !path_matches(&rule.path_globs, path, cwd)? {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+/// Decision for a PreToolUse event, if any enabled rule matches
+pub fn evaluate(rules: &[PolicyRule], event: &HookEvent) -> Option<PolicyOutcome> {
+    let Ok(HookPayload::PreToolUse {
+        tool_name,
+        tool_input,
+    }) = event.payload()
+    else {
+        return None;
+    };
+
+    let mut outcome: Option<PolicyOutcome> = None;
+    for rule in rules.iter().filter(|rule| rule.enabled) {
+        match rule_matches(rule, &tool_name, &tool_input, &event.cwd) {
+            Ok(true) => {}
+            Ok(false) => continue,
+            // Rules are validated when saved, so this only happens to hand-edited ones
+            Err(e) => {
+                eprintln!("Skipping policy rule {}: {}", rule.id, e);
+                continue;
+            }
+        }
+
+        if outcome
+            .as_ref()
+            .is_none_or(|current| rule.decision > current.decision)
+        {
+            outcome = Some(PolicyOutcome {
+                decision: rule.decision,
+                rule_id: rule.id.clone(),
+                reason: rule.reason.clone().unwrap_or_else(|| {
+                    let name = if rule.name.is_empty() {
+                        &rule.id
+                    } else {
+                        &rule.name
+                    };
+                    format!("Matched policy rule '{}'", name)
+                }),
+            });
+        }
+    }
+
+    outcome
+}
+
+pub fn rules_path(home_dir: &Path) -> PathBuf {
+    home_dir.join(RULES_FILE)
+}
+
+pub fn read_rules(home_dir: &Path) -> Result<Vec<PolicyRule>, String> {
+    let path = rules_path(home_dir);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read policy rules: {}", e))?;
+    let file: PolicyFile = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse policy rules: {}", e))?;
+    Ok(file.rules)
+}
+
+fn write_rules(home_dir: &Path, rules: Vec<PolicyRule>) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(&PolicyFile { rules })
+        .map_err(|e| format!("Failed to serialize policy rules: {}", e))?;
+    managed_file::write(&rules_path(home_dir), &content)
+}
+
+/// Append a rule, giving it a fresh id
+pub fn create_rule(home_dir: &Path, mut rule: PolicyRule) -> Result<PolicyRule, String> {
+    validate_rule(&rule)?;
+    rule.id = nanoid::nanoid!(6);
+
+    let mut rules = read_rules(home_dir)?;
+    rules.push(rule.clone());
+    write_rules(home_dir, rules)?;
+    Ok(rule)
+}
+
+pub fn update_rule(home_dir: &Path, rule: PolicyRule) -> Result<(), String> {
+    validate_rule(&rule)?;
+
+    let mut rules = read_rules(home_dir)?;
+    let existing = rules
+        .iter_mut()
+        .find(|r| r.id == rule.id)
+        .ok_or_else(|| format!("Policy rule '{}' not found", rule.id))?;
+    *existing = rule;
+    write_rules(home_dir, rules)
+}
+
+pub fn delete_rule(home_dir: &Path, rule_id: &str) -> Result<(), String> {
+    let mut rules = read_rules(home_dir)?;
+    let count = rules.len();
+    rules.retain(|r| r.id != rule_id);
+    if rules.len() == count {
+        return Err(format!("Policy rule '{}' not found", rule_id));
+    }
+    write_rules(home_dir, rules)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    fn pre_tool_use(cwd: &str, tool_name: &str, tool_input: Value) -> HookEvent {
+        serde_json::from_value(json!({
+            "session_id": "s1",
+            "transcript_path": "/tmp/s1.jsonl",
+            "cwd": cwd,
+            "hook_event_name": "PreToolUse",
+            "tool_name": tool_name,
+            "tool_input": tool_input,
+        }))
+        .unwrap()
+    }
+
+    fn rule(id: &str, decision: PermissionDecision) -> PolicyRule {
+        PolicyRule {
+            id: id.to_string(),
+            name: String::new(),
+            enabled: true,
+            decision,
+            reason: None,
+            tool_name: None,
+            command_pattern: None,
+            path_globs: Vec::new(),
+            project_path: None,
+        }
+    }
+
+    #[test]
+    fn test_evaluate_strictest_matching_rule_wins() {
+        let rules = vec![
+            PolicyRule {
+                tool_name: Some("Bash".into()),
+                ..rule("bash", PermissionDecision::Allow)
+            },
+            PolicyRule {
+                command_pattern: Some(r"\brm\s+-rf\b".into()),
+                reason: Some("No recursive deletes".into()),
+                ..rule("rm", PermissionDecision::Deny)
+            },
+            PolicyRule {
+                enabled: false,
+                ..rule("off", PermissionDecision::Deny)
+            },
+        ];
+
+        let ls = pre_tool_use("/work/app", "Bash", json!({ "command": "ls -la" }));
+        assert_eq!(
+            evaluate(&rules, &ls).unwrap().decision,
+            PermissionDecision::Allow
+        );
+
+        let rm = pre_tool_use(
+            "/work/app",
+            "Bash",
+            json!({ "command": "cd x && rm -rf /" }),
+        );
+        let outcome = evaluate(&rules, &rm).unwrap();
+        assert_eq!(outcome.rule_id, "rm");
+        assert_eq!(
+            outcome.to_hook_output(),
+            json!({
+                "hookSpecificOutput": {
+                    "hookEventName": "PreToolUse",
+                    "permissionDecision": "deny",
+                    "permissionDecisionReason": "No recursive deletes",
+                }
+            })
+        );
+
+        let read = pre_tool_use("/work/app", "Read", json!({ "file_path": "/work/app/a" }));
+        assert_eq!(evaluate(&rules, &read), None);
+    }
+
+    #[test]
+    fn test_evaluate_matches_path_globs_within_project() {
+        let rules = vec![PolicyRule {
+            tool_name: Some("Edit|Write".into()),
+            path_globs: vec!["**/.env*".into(), "/etc/*".into()],
+            project_path: Some("/work/app".into()),
+            ..rule("secrets", PermissionDecision::Ask)
+        }];
+
+        let edit = |cwd: &str, path: &str| {
+            pre_tool_use(cwd, "Edit", json!({ "file_path": path, "old_string": "" }))
+        };
+
+        assert!(evaluate(&rules, &edit("/work/app", "/work/app/.env")).is_some());
+        assert!(evaluate(&rules, &edit("/work/app/api", "config/.env.local")).is_some());
+        assert!(evaluate(&rules, &edit("/work/app", "/etc/hosts")).is_some());
+        assert!(evaluate(&rules, &edit("/work/app", "/etc/ssh/config")).is_none());
+        assert!(evaluate(&rules, &edit("/work/app", "/work/app/src/env.rs")).is_none());
+        assert!(evaluate(&rules, &edit("/work/other", "/work/other/.env")).is_none());
+    }
+
+    #[test]
+    fn test_rule_crud_validates_patterns() {
+        let home = tempdir().unwrap();
+
+        let invalid = PolicyRule {
+            command_pattern: Some("(".into()),
+            ..rule("", PermissionDecision::Deny)
+        };
+        assert!(create_rule(home.path(), invalid).is_err());
+
+        let mut created = create_rule(home.path(), rule("", PermissionDecision::Ask)).unwrap();
+        assert!(!created.id.is_empty());
+
+        created.decision = PermissionDecision::Deny;
+        update_rule(home.path(), created.clone()).unwrap();
+        assert_eq!(read_rules(home.path()).unwrap(), vec![created.clone()]);
+
+        delete_rule(home.path(), &created.id).unwrap();
+        assert!(read_rules(home.path()).unwrap().is_empty());
+        assert!(delete_rule(home.path(), &created.id).is_err());
+    }
+}
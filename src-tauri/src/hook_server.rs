@@ -1,56 +1,404 @@
-use axum::{extract::Json, http::StatusCode, response::IntoResponse, routing::post, Router};
-use serde_json::Value;
+use crate::commands;
+use crate::hook_auth::{self, SECRET_HEADER};
+use crate::hook_events::HookEvent;
+use crate::hook_log;
+use crate::hook_notifications;
+use crate::hook_policy::{self, PolicyOutcome};
+use crate::hook_settings::{self, HookSettings, HookTransport};
+use crate::managed_file;
+use crate::sessions::live::LiveSessions;
+use axum::extract::{Json, Request};
+use axum::http::StatusCode;
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::Router;
 use std::net::SocketAddr;
-use std::sync::Arc;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tauri::{Emitter, Manager};
 use tauri_plugin_notification::NotificationExt;
-use tower_http::cors::{Any, CorsLayer};
+use tokio::net::TcpListener;
+use tokio::sync::oneshot;
 
-// Hook event data structure
-#[derive(serde::Serialize, serde::Deserialize, Debug)]
-pub struct HookEvent {
-    pub session_id: String,
-    pub transcript_path: String,
-    pub cwd: String,
-    pub hook_event_name: String,
-    #[serde(flatten)]
-    pub extra: serde_json::Map<String, Value>,
+/// Emitted with the `HookServerStatus` whenever the hook server starts or fails to
+pub const STATUS_EVENT: &str = "hook-server-status";
+
+/// Emitted with every `HookEvent` received
+pub const HOOK_EVENT: &str = "hook-event";
+
+/// Emitted with the `LiveSession` a hook event updated
+pub const LIVE_SESSION_EVENT: &str = "live-session-updated";
+
+/// Where the running instance records the address it listens on
+const DISCOVERY_FILE: &str = ".ccconfig/hook-server.json";
+
+/// Kept in its own directory, since not every platform checks permissions on
+/// the socket itself
+const SOCKET_DIR: &str = ".ccconfig/run";
+const SOCKET_FILE: &str = "hook.sock";
+
+const HOOK_PATH: &str = "/claude_code/hooks";
+
+/// Where the server listens, and so where hooks post to
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "transport", rename_all = "lowercase")]
+pub enum HookEndpoint {
+    Tcp {
+        port: u16,
+    },
+    Unix {
+        #[serde(rename = "socketPath")]
+        socket_path: PathBuf,
+    },
 }
 
-// Hook server functions
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "state", rename_all = "lowercase")]
+pub enum HookServerStatus {
+    Starting,
+    Running {
+        endpoint: HookEndpoint,
+        /// The configured port, which differs from the bound one after a fallback
+        #[serde(rename = "preferredPort")]
+        preferred_port: u16,
+    },
+    Failed {
+        error: String,
+        #[serde(rename = "preferredPort")]
+        preferred_port: u16,
+    },
+}
 
-pub async fn start_hook_server(app_handle: tauri::AppHandle) -> Result<(), String> {
-    let app = create_hook_app(app_handle);
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct HookServerDiscovery {
+    pub endpoint: HookEndpoint,
+    /// Process that owns the server, so stale files can be told apart
+    pub pid: u32,
+    #[serde(rename = "startedAt")]
+    pub started_at: u64,
+}
+
+enum HookListener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(tokio::net::UnixListener, PathBuf),
+}
 
-    let addr = SocketAddr::from(([127, 0, 0, 1], 59948));
-    println!("🚀 Starting hook server on {}", addr);
+static STATUS: Mutex<HookServerStatus> = Mutex::new(HookServerStatus::Starting);
 
-    let listener = tokio::net::TcpListener::bind(addr)
-        .await
-        .map_err(|e| format!("Failed to bind to address {}: {}", addr, e))?;
+/// Stops the running server so it starts again with the current settings
+static RESTART: Mutex<Option<oneshot::Sender<()>>> = Mutex::new(None);
+
+pub fn status() -> HookServerStatus {
+    STATUS.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+fn set_status(app_handle: &tauri::AppHandle, status: HookServerStatus) {
+    *STATUS.lock().unwrap_or_else(|e| e.into_inner()) = status.clone();
+    if let Err(e) = app_handle.emit(STATUS_EVENT, status) {
+        eprintln!("Failed to emit hook server status: {}", e);
+    }
+}
+
+pub fn hook_url(port: u16) -> String {
+    format!("http://127.0.0.1:{}{}", port, HOOK_PATH)
+}
+
+/// URL to request over the socket; the host is ignored
+pub fn socket_hook_url() -> String {
+    format!("http://localhost{}", HOOK_PATH)
+}
+
+pub fn socket_path(home_dir: &Path) -> PathBuf {
+    home_dir.join(SOCKET_DIR).join(SOCKET_FILE)
+}
+
+/// Endpoint the settings ask for, before anything is bound
+fn configured_endpoint(home_dir: &Path, settings: &HookSettings) -> HookEndpoint {
+    match settings.effective_transport() {
+        HookTransport::Tcp => HookEndpoint::Tcp {
+            port: settings.port,
+        },
+        HookTransport::Unix => HookEndpoint::Unix {
+            socket_path: socket_path(home_dir),
+        },
+    }
+}
+
+/// Endpoint hooks should post to: the running server's, or the configured one
+/// before it has started
+pub fn current_endpoint() -> Result<HookEndpoint, String> {
+    if let HookServerStatus::Running { endpoint, .. } = status() {
+        return Ok(endpoint);
+    }
+
+    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
+    let settings = hook_settings::read_settings(&home_dir)?;
+    Ok(configured_endpoint(&home_dir, &settings))
+}
+
+pub fn discovery_path(home_dir: &Path) -> PathBuf {
+    home_dir.join(DISCOVERY_FILE)
+}
+
+fn write_discovery(home_dir: &Path, endpoint: &HookEndpoint) -> Result<(), String> {
+    let discovery = HookServerDiscovery {
+        endpoint: endpoint.clone(),
+        pid: std::process::id(),
+        started_at: chrono::Utc::now().timestamp() as u64,
+    };
+    let content = serde_json::to_string_pretty(&discovery)
+        .map_err(|e| format!("Failed to serialize hook server discovery: {}", e))?;
+    managed_file::write(&discovery_path(home_dir), &content)
+}
 
-    println!("✅ Hook server listening on http://localhost:59948");
+/// Bind the preferred port, or any free one if it's taken
+async fn bind_tcp(preferred_port: u16) -> Result<TcpListener, String> {
+    let preferred = SocketAddr::from(([127, 0, 0, 1], preferred_port));
+    let preferred_error = match TcpListener::bind(preferred).await {
+        Ok(listener) => return Ok(listener),
+        Err(e) => e,
+    };
 
-    axum::serve(listener, app)
+    println!(
+        "⚠️  Could not bind {} ({}), falling back to a free port",
+        preferred, preferred_error
+    );
+    TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0)))
         .await
-        .map_err(|e| format!("Failed to start server: {}", e))?;
+        .map_err(|e| {
+            format!(
+                "Failed to bind {} ({}) or a free port ({})",
+                preferred, preferred_error, e
+            )
+        })
+}
 
-    Ok(())
+/// Listen on `path`, readable only by the user. A socket left behind by a
+/// crashed instance is replaced; one another instance still answers on isn't.
+#[cfg(unix)]
+fn bind_unix(path: &Path) -> Result<tokio::net::UnixListener, String> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = path
+        .parent()
+        .ok_or_else(|| format!("Invalid socket path: {}", path.display()))?;
+    std::fs::create_dir_all(dir)
+        .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700))
+        .map_err(|e| format!("Failed to restrict {}: {}", dir.display(), e))?;
+
+    if path.exists() {
+        if std::os::unix::net::UnixStream::connect(path).is_ok() {
+            return Err(format!(
+                "Another instance is listening on {}",
+                path.display()
+            ));
+        }
+        std::fs::remove_file(path)
+            .map_err(|e| format!("Failed to remove stale {}: {}", path.display(), e))?;
+    }
+
+    let listener = tokio::net::UnixListener::bind(path)
+        .map_err(|e| format!("Failed to bind {}: {}", path.display(), e))?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
+        .map_err(|e| format!("Failed to restrict {}: {}", path.display(), e))?;
+    Ok(listener)
 }
 
-fn create_hook_app(app_handle: tauri::AppHandle) -> Router {
-    let cors = CorsLayer::new()
-        .allow_origin(Any)
-        .allow_methods(Any)
-        .allow_headers(Any);
+async fn bind(endpoint: &HookEndpoint) -> Result<(HookListener, HookEndpoint), String> {
+    match endpoint {
+        HookEndpoint::Tcp { port } => {
+            let listener = bind_tcp(*port).await?;
+            let port = listener
+                .local_addr()
+                .map_err(|e| format!("Failed to read hook server address: {}", e))?
+                .port();
+            Ok((HookListener::Tcp(listener), HookEndpoint::Tcp { port }))
+        }
+        #[cfg(unix)]
+        HookEndpoint::Unix { socket_path } => Ok((
+            HookListener::Unix(bind_unix(socket_path)?, socket_path.clone()),
+            endpoint.clone(),
+        )),
+        #[cfg(not(unix))]
+        HookEndpoint::Unix { .. } => Err("Unix sockets aren't supported here".to_string()),
+    }
+}
+
+/// Serve HTTP over a Unix socket until `shutdown` fires, then remove the socket
+#[cfg(unix)]
+async fn serve_unix(
+    listener: tokio::net::UnixListener,
+    path: PathBuf,
+    app: Router,
+    shutdown: oneshot::Receiver<()>,
+) {
+    use hyper_util::rt::TokioIo;
+    use hyper_util::service::TowerToHyperService;
+
+    let accept_loop = tokio::spawn(async move {
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    eprintln!("Failed to accept hook connection: {}", e);
+                    continue;
+                }
+            };
+
+            let service = TowerToHyperService::new(app.clone());
+            tokio::spawn(async move {
+                if let Err(e) = hyper::server::conn::http1::Builder::new()
+                    .serve_connection(TokioIo::new(stream), service)
+                    .await
+                {
+                    eprintln!("Hook connection failed: {}", e);
+                }
+            });
+        }
+    });
+
+    let _ = shutdown.await;
+    accept_loop.abort();
+    let _ = accept_loop.await;
+    let _ = std::fs::remove_file(&path);
+}
 
+async fn serve(
+    listener: HookListener,
+    app: Router,
+    shutdown: oneshot::Receiver<()>,
+) -> Result<(), String> {
+    match listener {
+        HookListener::Tcp(listener) => axum::serve(listener, app)
+            .with_graceful_shutdown(async {
+                let _ = shutdown.await;
+            })
+            .await
+            .map_err(|e| format!("Failed to start server: {}", e)),
+        #[cfg(unix)]
+        HookListener::Unix(listener, path) => {
+            serve_unix(listener, path, app, shutdown).await;
+            Ok(())
+        }
+    }
+}
+
+/// Run the hook server, starting over with fresh settings whenever
+/// `restart_hook_server` is called. Returns only if it fails to start.
+pub async fn start_hook_server(app_handle: tauri::AppHandle) -> Result<(), String> {
+    loop {
+        let (restart_tx, restart_rx) = oneshot::channel();
+        *RESTART.lock().unwrap_or_else(|e| e.into_inner()) = Some(restart_tx);
+
+        if let Err(error) = run_hook_server(&app_handle, restart_rx).await {
+            RESTART.lock().unwrap_or_else(|e| e.into_inner()).take();
+            return Err(error);
+        }
+        println!("🔄 Restarting hook server");
+    }
+}
+
+/// Apply changed transport or port settings. Starts the server if it isn't
+/// running, e.g. after failing to bind.
+pub fn restart_hook_server(app_handle: &tauri::AppHandle) {
+    if let Some(restart_tx) = RESTART.lock().unwrap_or_else(|e| e.into_inner()).take() {
+        let _ = restart_tx.send(());
+        return;
+    }
+
+    if let HookServerStatus::Failed { .. } = status() {
+        let app_handle = app_handle.clone();
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = start_hook_server(app_handle).await {
+                eprintln!("Failed to start hook server: {}", e);
+            }
+        });
+    }
+}
+
+async fn run_hook_server(
+    app_handle: &tauri::AppHandle,
+    restart: oneshot::Receiver<()>,
+) -> Result<(), String> {
+    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
+    let settings = hook_settings::read_settings(&home_dir)?;
+    let preferred_port = settings.port;
+    // The installed hook command reads the secret from disk, so create it up front
+    hook_auth::current_secret()?;
+
+    set_status(app_handle, HookServerStatus::Starting);
+    let configured = configured_endpoint(&home_dir, &settings);
+    println!("🚀 Starting hook server on {:?}", configured);
+
+    let (listener, endpoint) = match bind(&configured).await {
+        Ok(bound) => bound,
+        Err(error) => {
+            notify(app_handle, "Hook server failed to start", &error);
+            set_status(
+                app_handle,
+                HookServerStatus::Failed {
+                    error: error.clone(),
+                    preferred_port,
+                },
+            );
+            return Err(error);
+        }
+    };
+
+    if let Err(e) = write_discovery(&home_dir, &endpoint) {
+        eprintln!("Failed to write hook server discovery file: {}", e);
+    }
+    set_status(
+        app_handle,
+        HookServerStatus::Running {
+            endpoint: endpoint.clone(),
+            preferred_port,
+        },
+    );
+    println!("✅ Hook server listening on {:?}", endpoint);
+
+    // Point installed hooks at the endpoint actually bound
+    match commands::update_claude_code_hook().await {
+        Ok(()) => println!("✅ Claude Code hooks updated/checked successfully"),
+        Err(e) => eprintln!("Failed to update Claude Code hooks: {}", e),
+    }
+
+    serve(listener, create_hook_app(app_handle.clone()), restart).await
+}
+
+fn create_hook_app(app_handle: tauri::AppHandle) -> Router {
     let shared_handle = Arc::new(app_handle);
 
+    // No CORS layer: browsers can't send the secret header cross-origin without one
     Router::new()
         .route(
-            "/claude_code/hooks",
+            HOOK_PATH,
             post(move |payload| handle_hook_event(payload, shared_handle.clone())),
         )
-        .layer(cors)
+        .layer(middleware::from_fn(require_secret))
+}
+
+/// Reject requests that don't carry the install's hook secret
+async fn require_secret(request: Request, next: Next) -> Response {
+    let provided = request
+        .headers()
+        .get(SECRET_HEADER)
+        .and_then(|value| value.to_str().ok());
+
+    match hook_auth::verify(provided) {
+        Ok(true) => next.run(request).await,
+        Ok(false) => {
+            println!("🚫 Rejected hook request without a valid secret");
+            StatusCode::UNAUTHORIZED.into_response()
+        }
+        Err(e) => {
+            eprintln!("Failed to check hook secret: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
 }
 
 async fn handle_hook_event(
@@ -64,40 +412,75 @@ async fn handle_hook_event(
             .unwrap_or_else(|_| "Failed to serialize".to_string())
     );
 
+    let decision = evaluate_policy(&payload);
+    if let Err(e) = hook_log::append(&payload, decision.clone()) {
+        eprintln!("Failed to log hook event: {}", e);
+    }
+
+    publish_event(&payload, &app_handle);
+
     // Send notification based on the hook event
-    send_hook_notification(&payload, &app_handle).await;
+    send_hook_notification(&payload, decision.as_ref(), &app_handle).await;
 
-    (StatusCode::OK, "Hook received")
+    // The hook prints this to Claude Code; an empty object leaves the call to its own permissions
+    let output = decision
+        .map(|outcome| outcome.to_hook_output())
+        .unwrap_or_else(|| serde_json::json!({}));
+    (StatusCode::OK, Json(output))
 }
 
-// Send notification based on hook event type
-async fn send_hook_notification(event: &HookEvent, app_handle: &tauri::AppHandle) {
-    let title = "Claude Code";
-    let description = match event.hook_event_name.as_str() {
-        "Stop" => "Task completed successfully".to_string(),
-        "PreToolUse" => {
-            if let Some(tool_name) = event.extra.get("tool_name").and_then(|v| v.as_str()) {
-                format!("🔨 Using {} tool", tool_name)
-            } else {
-                "A tool is going to be used".to_string()
-            }
+/// Tell the app window about the event and the session it updated
+fn publish_event(event: &HookEvent, app_handle: &tauri::AppHandle) {
+    if let Err(e) = app_handle.emit(HOOK_EVENT, event) {
+        eprintln!("Failed to emit hook event: {}", e);
+    }
+
+    let live_sessions = app_handle.state::<LiveSessions>();
+    let session = live_sessions
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .apply(event, chrono::Utc::now().timestamp_millis());
+    if let Some(session) = session {
+        if let Err(e) = app_handle.emit(LIVE_SESSION_EVENT, session) {
+            eprintln!("Failed to emit live session: {}", e);
         }
-        "Notification" => {
-            if let Some(message) = event.extra.get("message").and_then(|v| v.as_str()) {
-                format!("✅ {}", message)
-            } else {
-                "Received notification".to_string()
-            }
+    }
+}
+
+fn evaluate_policy(event: &HookEvent) -> Option<PolicyOutcome> {
+    let rules = dirs::home_dir()
+        .ok_or("Could not find home directory".to_string())
+        .and_then(|home_dir| hook_policy::read_rules(&home_dir));
+
+    match rules {
+        Ok(rules) => hook_policy::evaluate(&rules, event),
+        Err(e) => {
+            eprintln!("Failed to read policy rules: {}", e);
+            None
         }
-        _ => "Hook event received".to_string(),
-    };
+    }
+}
+
+/// Send the notification the user's rules pick for the event, if any
+async fn send_hook_notification(
+    event: &HookEvent,
+    decision: Option<&PolicyOutcome>,
+    app_handle: &tauri::AppHandle,
+) {
+    match hook_notifications::notification_for(event, decision) {
+        Ok(Some(notification)) => notify(app_handle, &notification.title, &notification.body),
+        Ok(None) => {}
+        Err(e) => eprintln!("Failed to read notification rules: {}", e),
+    }
+}
 
-    // Send notification using Tauri notification plugin
+/// Send a notification using the Tauri notification plugin
+fn notify(app_handle: &tauri::AppHandle, title: &str, description: &str) {
     match app_handle
         .notification()
         .builder()
         .title(title)
-        .body(&description)
+        .body(description)
         .show()
     {
         Ok(_) => {
@@ -108,3 +491,85 @@ async fn send_hook_notification(event: &HookEvent, app_handle: &tauri::AppHandle
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_bind_tcp_falls_back_to_free_port() {
+        tauri::async_runtime::block_on(async {
+            let taken = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let port = taken.local_addr().unwrap().port();
+
+            let (_listener, endpoint) = bind(&HookEndpoint::Tcp { port }).await.unwrap();
+            let HookEndpoint::Tcp { port: bound } = endpoint else {
+                panic!("expected a TCP endpoint");
+            };
+            assert_ne!(bound, port);
+            assert_ne!(bound, 0);
+        });
+    }
+
+    #[test]
+    fn test_write_discovery_records_endpoint() {
+        let home = tempdir().unwrap();
+        write_discovery(home.path(), &HookEndpoint::Tcp { port: 4321 }).unwrap();
+
+        let discovery: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(discovery_path(home.path())).unwrap())
+                .unwrap();
+        assert_eq!(
+            discovery["endpoint"],
+            serde_json::json!({ "transport": "tcp", "port": 4321 })
+        );
+        assert_eq!(discovery["pid"], std::process::id());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_serve_unix_answers_and_cleans_up() {
+        use std::os::unix::fs::PermissionsExt;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let home = tempdir().unwrap();
+        let path = socket_path(home.path());
+        // A socket file left behind by a crashed instance
+        drop(std::os::unix::net::UnixListener::bind({
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            &path
+        }));
+
+        tauri::async_runtime::block_on(async {
+            let listener = bind_unix(&path).unwrap();
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+            assert!(bind_unix(&path).is_err());
+
+            let app = Router::new().route(HOOK_PATH, post(|| async { "ok" }));
+            let (shutdown_tx, shutdown_rx) = oneshot::channel();
+            let server = tokio::spawn(serve_unix(listener, path.clone(), app, shutdown_rx));
+
+            let mut stream = tokio::net::UnixStream::connect(&path).await.unwrap();
+            stream
+                .write_all(
+                    format!(
+                        "POST {} HTTP/1.1\r\nHost: localhost\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                        HOOK_PATH
+                    )
+                    .as_bytes(),
+                )
+                .await
+                .unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+            assert!(response.starts_with("HTTP/1.1 200"));
+            assert!(response.ends_with("ok"));
+
+            shutdown_tx.send(()).unwrap();
+            server.await.unwrap();
+        });
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,122 @@
+//! How the app hooks into Claude Code, kept in ~/.ccconfig/hooks.json
+
+use crate::hook_events::HookEventKind;
+use crate::managed_file;
+use std::path::{Path, PathBuf};
+
+const SETTINGS_FILE: &str = ".ccconfig/hooks.json";
+
+pub const DEFAULT_PORT: u16 = 59948;
+
+/// How hooks reach the hook server
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum HookTransport {
+    /// HTTP on a localhost port
+    #[default]
+    Tcp,
+    /// HTTP over a socket in ~/.ccconfig, so no port is opened. Linux and macOS only.
+    Unix,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct HookSettings {
+    /// Events the app installs its hook for
+    #[serde(rename = "subscribedEvents", default = "default_subscribed_events")]
+    pub subscribed_events: Vec<HookEventKind>,
+    #[serde(default)]
+    pub transport: HookTransport,
+    /// Port the hook server tries first with the TCP transport. If it's taken
+    /// the server falls back to a free one.
+    #[serde(default = "default_port")]
+    pub port: u16,
+}
+
+fn default_subscribed_events() -> Vec<HookEventKind> {
+    HookEventKind::DEFAULT.to_vec()
+}
+
+fn default_port() -> u16 {
+    DEFAULT_PORT
+}
+
+impl Default for HookSettings {
+    fn default() -> Self {
+        HookSettings {
+            subscribed_events: default_subscribed_events(),
+            transport: HookTransport::default(),
+            port: DEFAULT_PORT,
+        }
+    }
+}
+
+impl HookSettings {
+    /// The transport to use on this platform
+    pub fn effective_transport(&self) -> HookTransport {
+        if cfg!(unix) {
+            self.transport
+        } else {
+            HookTransport::Tcp
+        }
+    }
+
+    /// Subscribed event names, deduplicated, in `HookEventKind::ALL` order
+    pub fn event_names(&self) -> Vec<&'static str> {
+        HookEventKind::ALL
+            .into_iter()
+            .filter(|kind| self.subscribed_events.contains(kind))
+            .map(HookEventKind::as_str)
+            .collect()
+    }
+}
+
+pub fn settings_path(home_dir: &Path) -> PathBuf {
+    home_dir.join(SETTINGS_FILE)
+}
+
+pub fn read_settings(home_dir: &Path) -> Result<HookSettings, String> {
+    let path = settings_path(home_dir);
+    if !path.exists() {
+        return Ok(HookSettings::default());
+    }
+
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read hook settings: {}", e))?;
+    serde_json::from_str(&content).map_err(|e| format!("Failed to parse hook settings: {}", e))
+}
+
+pub fn write_settings(home_dir: &Path, settings: &HookSettings) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(settings)
+        .map_err(|e| format!("Failed to serialize hook settings: {}", e))?;
+    managed_file::write(&settings_path(home_dir), &content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_read_settings_defaults_and_round_trips() {
+        let home = tempdir().unwrap();
+        let defaults = read_settings(home.path()).unwrap();
+        assert_eq!(
+            defaults.event_names(),
+            vec!["PreToolUse", "Notification", "Stop"]
+        );
+
+        let settings = HookSettings {
+            subscribed_events: vec![
+                HookEventKind::SessionEnd,
+                HookEventKind::PostToolUse,
+                HookEventKind::SessionEnd,
+            ],
+            transport: HookTransport::Unix,
+            port: 4000,
+        };
+        write_settings(home.path(), &settings).unwrap();
+        let read = read_settings(home.path()).unwrap();
+        assert_eq!(read, settings);
+        assert_eq!(read.event_names(), vec!["PostToolUse", "SessionEnd"]);
+    }
+}
@@ -0,0 +1,481 @@
+//! Undo journal for mutating commands
+//!
+//! Before a command changes user files it records the previous state of every
+//! path it touches in ~/.ccconfig/journal/{entry_id}: files are copied as-is,
+//! directories recursively, and paths that didn't exist are noted so undo can
+//! delete them again. Entries are pruned by age and by total size.
+
+use crate::managed_file;
+use crate::store_history;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+const JOURNAL_DIR: &str = ".ccconfig/journal";
+const ENTRY_FILE: &str = "entry.json";
+const DATA_DIR: &str = "data";
+
+/// Entries older than this are pruned
+const MAX_AGE_SECS: u64 = 30 * 24 * 60 * 60;
+
+/// Oldest entries are pruned until the journal fits, always keeping the newest one
+const MAX_TOTAL_BYTES: u64 = 512 * 1024 * 1024;
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum JournalPathKind {
+    File,
+    Directory,
+    /// The path didn't exist, so undo deletes whatever is there
+    Missing,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct JournalPath {
+    pub path: PathBuf,
+    pub kind: JournalPathKind,
+    pub size: u64,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct JournalEntry {
+    pub id: String,
+    /// Name of the command that made the change
+    pub command: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: u64,
+    pub paths: Vec<JournalPath>,
+    /// Bytes of previous content held by this entry
+    pub size: u64,
+    #[serde(rename = "undoneAt", default, skip_serializing_if = "Option::is_none")]
+    pub undone_at: Option<u64>,
+    /// Set on the entry recorded by an undo, naming the entry it undid
+    #[serde(rename = "undoOf", default, skip_serializing_if = "Option::is_none")]
+    pub undo_of: Option<String>,
+}
+
+fn now_secs() -> Result<u64, String> {
+    Ok(std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_err(|e| format!("Failed to get timestamp: {}", e))?
+        .as_secs())
+}
+
+fn get_journal_dir() -> Result<PathBuf, String> {
+    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
+    Ok(home_dir.join(JOURNAL_DIR))
+}
+
+fn get_entry_dir(journal_dir: &Path, entry_id: &str) -> Result<PathBuf, String> {
+    // Entry IDs come from the frontend, so guard against path traversal
+    if entry_id.is_empty()
+        || entry_id.contains("..")
+        || entry_id.contains('/')
+        || entry_id.contains('\\')
+    {
+        return Err(format!("Invalid journal entry id: {}", entry_id));
+    }
+
+    Ok(journal_dir.join(entry_id))
+}
+
+/// Sortable by time, with a random suffix in case two land in the same millisecond
+fn new_entry_id() -> String {
+    format!(
+        "{}-{}",
+        chrono::Utc::now().format("%Y%m%dT%H%M%S%3fZ"),
+        &uuid::Uuid::new_v4().simple().to_string()[..6]
+    )
+}
+
+/// Recursively copy a directory, returning the number of bytes copied
+fn copy_tree(source: &Path, dest: &Path) -> Result<u64, String> {
+    std::fs::create_dir_all(dest)
+        .map_err(|e| format!("Failed to create {}: {}", dest.display(), e))?;
+
+    let mut size = 0;
+    for entry in std::fs::read_dir(source)
+        .map_err(|e| format!("Failed to read {}: {}", source.display(), e))?
+    {
+        let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
+        let path = entry.path();
+        let target = dest.join(entry.file_name());
+
+        if path.is_dir() {
+            size += copy_tree(&path, &target)?;
+        } else {
+            size += std::fs::copy(&path, &target)
+                .map_err(|e| format!("Failed to copy {}: {}", path.display(), e))?;
+        }
+    }
+
+    Ok(size)
+}
+
+fn remove_path(path: &Path) -> Result<(), String> {
+    let result = match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => std::fs::remove_dir_all(path),
+        Ok(_) => std::fs::remove_file(path),
+        Err(_) => return Ok(()),
+    };
+    result.map_err(|e| format!("Failed to remove {}: {}", path.display(), e))
+}
+
+fn read_entry(entry_dir: &Path) -> Result<JournalEntry, String> {
+    let path = entry_dir.join(ENTRY_FILE);
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    serde_json::from_str(&content).map_err(|e| format!("Failed to parse {}: {}", path.display(), e))
+}
+
+fn write_entry(entry_dir: &Path, entry: &JournalEntry) -> Result<(), String> {
+    let json_content = serde_json::to_string_pretty(entry)
+        .map_err(|e| format!("Failed to serialize journal entry: {}", e))?;
+    managed_file::write(&entry_dir.join(ENTRY_FILE), &json_content)
+}
+
+fn record_entry_in<P: AsRef<Path>>(
+    journal_dir: &Path,
+    command: &str,
+    paths: &[P],
+    undo_of: Option<String>,
+) -> Result<JournalEntry, String> {
+    let id = new_entry_id();
+    let entry_dir = get_entry_dir(journal_dir, &id)?;
+    let data_dir = entry_dir.join(DATA_DIR);
+    std::fs::create_dir_all(&data_dir)
+        .map_err(|e| format!("Failed to create journal entry: {}", e))?;
+
+    let mut recorded: Vec<JournalPath> = Vec::new();
+    for path in paths {
+        let path = path.as_ref();
+        if recorded.iter().any(|r| r.path == path) {
+            continue;
+        }
+
+        let data_path = data_dir.join(recorded.len().to_string());
+        let (kind, size) = if path.is_dir() {
+            (JournalPathKind::Directory, copy_tree(path, &data_path)?)
+        } else if path.is_file() {
+            let size = std::fs::copy(path, &data_path)
+                .map_err(|e| format!("Failed to copy {}: {}", path.display(), e))?;
+            (JournalPathKind::File, size)
+        } else {
+            (JournalPathKind::Missing, 0)
+        };
+
+        recorded.push(JournalPath {
+            path: path.to_path_buf(),
+            kind,
+            size,
+        });
+    }
+
+    let entry = JournalEntry {
+        id,
+        command: command.to_string(),
+        created_at: now_secs()?,
+        size: recorded.iter().map(|r| r.size).sum(),
+        paths: recorded,
+        undone_at: None,
+        undo_of,
+    };
+
+    if let Err(e) = write_entry(&entry_dir, &entry) {
+        let _ = std::fs::remove_dir_all(&entry_dir);
+        return Err(e);
+    }
+
+    if let Err(e) = prune_in(journal_dir, MAX_AGE_SECS, MAX_TOTAL_BYTES) {
+        eprintln!("Warning: Failed to prune journal: {}", e);
+    }
+
+    Ok(entry)
+}
+
+fn list_in(journal_dir: &Path) -> Result<Vec<JournalEntry>, String> {
+    if !journal_dir.is_dir() {
+        return Ok(vec![]);
+    }
+
+    let mut entries = Vec::new();
+    for dir_entry in std::fs::read_dir(journal_dir)
+        .map_err(|e| format!("Failed to read journal directory: {}", e))?
+    {
+        let dir_entry = dir_entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
+        if !dir_entry.path().join(ENTRY_FILE).is_file() {
+            continue;
+        }
+        match read_entry(&dir_entry.path()) {
+            Ok(entry) => entries.push(entry),
+            Err(e) => eprintln!("Warning: Skipping unreadable journal entry: {}", e),
+        }
+    }
+
+    // IDs start with a millisecond timestamp, so they order entries within a second
+    entries.sort_by(|a, b| b.created_at.cmp(&a.created_at).then(b.id.cmp(&a.id)));
+    Ok(entries)
+}
+
+fn prune_in(journal_dir: &Path, max_age_secs: u64, max_total_bytes: u64) -> Result<(), String> {
+    let now = now_secs()?;
+    let mut total = 0;
+
+    for (index, entry) in list_in(journal_dir)?.into_iter().enumerate() {
+        total += entry.size;
+        let too_old = now.saturating_sub(entry.created_at) > max_age_secs;
+        let too_big = index > 0 && total > max_total_bytes;
+
+        if too_old || too_big {
+            std::fs::remove_dir_all(get_entry_dir(journal_dir, &entry.id)?)
+                .map_err(|e| format!("Failed to delete journal entry '{}': {}", entry.id, e))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn undo_entry_in(journal_dir: &Path, entry_id: &str) -> Result<JournalEntry, String> {
+    let entry_dir = get_entry_dir(journal_dir, entry_id)?;
+    if !entry_dir.join(ENTRY_FILE).is_file() {
+        return Err(format!("Journal entry '{}' not found", entry_id));
+    }
+
+    let mut entry = read_entry(&entry_dir)?;
+    if entry.undone_at.is_some() {
+        return Err(format!("'{}' has already been undone", entry.command));
+    }
+
+    // The undo is itself journaled, so it can be undone in turn
+    let paths: Vec<PathBuf> = entry.paths.iter().map(|p| p.path.clone()).collect();
+    record_entry_in(
+        journal_dir,
+        &format!("undo {}", entry.command),
+        &paths,
+        Some(entry.id.clone()),
+    )?;
+
+    let data_dir = entry_dir.join(DATA_DIR);
+    for (index, recorded) in entry.paths.iter().enumerate() {
+        let data_path = data_dir.join(index.to_string());
+
+        match recorded.kind {
+            JournalPathKind::Missing => remove_path(&recorded.path)?,
+            JournalPathKind::File => {
+                let bytes = std::fs::read(&data_path)
+                    .map_err(|e| format!("Failed to read journal data: {}", e))?;
+                if recorded.path.is_dir() {
+                    remove_path(&recorded.path)?;
+                }
+                managed_file::write_bytes(&recorded.path, &bytes)?;
+            }
+            JournalPathKind::Directory => {
+                remove_path(&recorded.path)?;
+                copy_tree(&data_path, &recorded.path)?;
+            }
+        }
+    }
+
+    entry.undone_at = Some(now_secs()?);
+    write_entry(&entry_dir, &entry)?;
+
+    Ok(entry)
+}
+
+fn undo_last_in(journal_dir: &Path) -> Result<JournalEntry, String> {
+    let last = list_in(journal_dir)?
+        .into_iter()
+        .find(|entry| entry.undone_at.is_none() && entry.undo_of.is_none())
+        .ok_or("Nothing to undo")?;
+
+    undo_entry_in(journal_dir, &last.id)
+}
+
+fn replace_values_in(
+    journal_dir: &Path,
+    path: &Path,
+    replacements: &HashMap<String, String>,
+) -> Result<(), String> {
+    for mut entry in list_in(journal_dir)? {
+        let entry_dir = get_entry_dir(journal_dir, &entry.id)?;
+        let mut changed = false;
+
+        for (index, recorded) in entry.paths.iter_mut().enumerate() {
+            if recorded.path != path || recorded.kind != JournalPathKind::File {
+                continue;
+            }
+
+            let data_path = entry_dir.join(DATA_DIR).join(index.to_string());
+            let content = std::fs::read_to_string(&data_path)
+                .map_err(|e| format!("Failed to read journal data: {}", e))?;
+            // Copies that aren't JSON can't hold the values being replaced as strings
+            let Ok(mut json) = serde_json::from_str::<serde_json::Value>(&content) else {
+                continue;
+            };
+            store_history::replace_strings(&mut json, replacements);
+
+            let content = serde_json::to_string_pretty(&json)
+                .map_err(|e| format!("Failed to serialize journal data: {}", e))?;
+            managed_file::write(&data_path, &content)?;
+            recorded.size = content.len() as u64;
+            changed = true;
+        }
+
+        if changed {
+            entry.size = entry.paths.iter().map(|p| p.size).sum();
+            write_entry(&entry_dir, &entry)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Save the current state of `paths` before `command` changes them
+pub fn record<P: AsRef<Path>>(command: &str, paths: &[P]) -> Result<JournalEntry, String> {
+    record_entry_in(&get_journal_dir()?, command, paths, None)
+}
+
+/// All journal entries, newest first
+pub fn list() -> Result<Vec<JournalEntry>, String> {
+    list_in(&get_journal_dir()?)
+}
+
+/// Put back the state recorded by an entry
+pub fn undo_entry(entry_id: &str) -> Result<JournalEntry, String> {
+    undo_entry_in(&get_journal_dir()?, entry_id)
+}
+
+/// Rewrite string values in every saved copy of the JSON file at `path`, e.g.
+/// to swap plaintext secrets for vault references
+pub fn replace_values(path: &Path, replacements: &HashMap<String, String>) -> Result<(), String> {
+    replace_values_in(&get_journal_dir()?, path, replacements)
+}
+
+/// Undo the most recent change that hasn't been undone yet
+pub fn undo_last() -> Result<JournalEntry, String> {
+    undo_last_in(&get_journal_dir()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_undo_entry_restores_files_dirs_and_missing_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal_dir = dir.path().join("journal");
+        let file = dir.path().join("settings.json");
+        let sessions = dir.path().join("sessions");
+        let created = dir.path().join("new.md");
+
+        std::fs::write(&file, "before").unwrap();
+        std::fs::create_dir_all(sessions.join("nested")).unwrap();
+        std::fs::write(sessions.join("nested/a.jsonl"), "log").unwrap();
+
+        let entry = record_entry_in(
+            &journal_dir,
+            "delete_project_config",
+            &[file.clone(), sessions.clone(), created.clone()],
+            None,
+        )
+        .unwrap();
+        assert_eq!(entry.size, "before".len() as u64 + "log".len() as u64);
+
+        std::fs::write(&file, "after").unwrap();
+        std::fs::remove_dir_all(&sessions).unwrap();
+        std::fs::write(&created, "new").unwrap();
+
+        let undone = undo_last_in(&journal_dir).unwrap();
+
+        assert_eq!(undone.id, entry.id);
+        assert!(undone.undone_at.is_some());
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "before");
+        assert_eq!(
+            std::fs::read_to_string(sessions.join("nested/a.jsonl")).unwrap(),
+            "log"
+        );
+        assert!(!created.exists());
+        assert!(undo_entry_in(&journal_dir, &entry.id).is_err());
+    }
+
+    #[test]
+    fn test_undo_is_itself_undoable() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal_dir = dir.path().join("journal");
+        let file = dir.path().join("agent.md");
+
+        std::fs::write(&file, "v1").unwrap();
+        record_entry_in(&journal_dir, "write_claude_agent", &[&file], None).unwrap();
+        std::fs::write(&file, "v2").unwrap();
+
+        undo_last_in(&journal_dir).unwrap();
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "v1");
+
+        let undo_record = list_in(&journal_dir)
+            .unwrap()
+            .into_iter()
+            .find(|entry| entry.undo_of.is_some())
+            .unwrap();
+        undo_entry_in(&journal_dir, &undo_record.id).unwrap();
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "v2");
+
+        // Undo records and undone entries are skipped by undo_last
+        assert!(undo_last_in(&journal_dir).is_err());
+    }
+
+    #[test]
+    fn test_replace_values_scrubs_every_copy() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal_dir = dir.path().join("journal");
+        let stores = dir.path().join("stores.json");
+        let other = dir.path().join("notes.md");
+
+        std::fs::write(
+            &stores,
+            r#"{"configs":[{"settings":{"env":{"ANTHROPIC_AUTH_TOKEN":"sk-plain"}}}]}"#,
+        )
+        .unwrap();
+        std::fs::write(&other, "sk-plain").unwrap();
+        record_entry_in(&journal_dir, "update_config", &[&stores, &other], None).unwrap();
+        record_entry_in(&journal_dir, "set_using_config", &[&stores], None).unwrap();
+
+        let replacements =
+            HashMap::from([("sk-plain".to_string(), "{{secret:token}}".to_string())]);
+        replace_values_in(&journal_dir, &stores, &replacements).unwrap();
+
+        for entry in list_in(&journal_dir).unwrap() {
+            let data = std::fs::read_to_string(journal_dir.join(&entry.id).join("data/0")).unwrap();
+            assert!(!data.contains("sk-plain"));
+            assert!(data.contains("{{secret:token}}"));
+            assert_eq!(entry.paths[0].size, data.len() as u64);
+        }
+
+        // Copies of other files are left alone
+        let update = list_in(&journal_dir)
+            .unwrap()
+            .into_iter()
+            .find(|entry| entry.command == "update_config")
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(journal_dir.join(&update.id).join("data/1")).unwrap(),
+            "sk-plain"
+        );
+    }
+
+    #[test]
+    fn test_prune_drops_oldest_beyond_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal_dir = dir.path().join("journal");
+        let file = dir.path().join("big.json");
+        std::fs::write(&file, "0123456789").unwrap();
+
+        for _ in 0..3 {
+            record_entry_in(&journal_dir, "write_config_file", &[&file], None).unwrap();
+        }
+        prune_in(&journal_dir, MAX_AGE_SECS, 25).unwrap();
+
+        assert_eq!(list_in(&journal_dir).unwrap().len(), 2);
+
+        prune_in(&journal_dir, MAX_AGE_SECS, 0).unwrap();
+        assert_eq!(list_in(&journal_dir).unwrap().len(), 1);
+    }
+}
@@ -1,8 +1,27 @@
+mod backups;
+mod bundle;
+mod claude_settings;
 mod commands;
+mod drift_watcher;
+mod hook_auth;
+mod hook_events;
+mod hook_log;
+mod hook_notifications;
+mod hook_policy;
 mod hook_server;
+mod hook_settings;
+mod journal;
+mod managed_file;
+mod mcp;
 mod models;
 mod sessions;
+mod settings_diff;
+mod settings_drift;
+mod settings_merge;
+mod store_history;
+mod store_inheritance;
 mod tray;
+mod vault;
 
 use commands::*;
 use hook_server::start_hook_server;
@@ -19,9 +38,11 @@ pub fn run() {
 
     // Initialize sessions running processes state
     let session_processes = sessions::resume::init_running_processes();
+    let live_sessions = sessions::live::init_live_sessions();
 
     builder
         .manage(session_processes)
+        .manage(live_sessions)
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_store::Builder::default().build())
@@ -156,16 +177,8 @@ pub fn run() {
                 }
             });
 
-            // Always update hooks to ensure they have the latest command settings
-            tauri::async_runtime::spawn(async move {
-                println!("Updating Claude Code hooks to latest version...");
-                match commands::update_claude_code_hook().await {
-                    Ok(()) => println!("✅ Claude Code hooks updated/checked successfully"),
-                    Err(e) => eprintln!("Failed to update Claude Code hooks: {}", e),
-                }
-            });
-
-            // Start hook server in background
+            // Start hook server in background. Once bound it updates the installed
+            // hooks to the latest command and the port it got.
             println!("Starting hook server...");
             let app_handle_for_server = app.handle().clone();
             tauri::async_runtime::spawn(async move {
@@ -174,6 +187,11 @@ pub fn run() {
                     Err(e) => eprintln!("Failed to start hook server: {}", e),
                 }
             });
+
+            // Watch settings.json for changes made outside the app
+            if let Err(e) = drift_watcher::start_drift_watcher(app.handle().clone()) {
+                eprintln!("Failed to start drift watcher: {}", e);
+            }
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
@@ -183,6 +201,11 @@ pub fn run() {
             check_app_config_exists,
             create_app_config_dir,
             backup_claude_configs,
+            list_backups,
+            diff_backup,
+            restore_backup,
+            get_backup_policy,
+            set_backup_policy,
             get_stores,
             get_store,
             create_config,
@@ -191,11 +214,45 @@ pub fn run() {
             set_using_config,
             reset_to_original_config,
             get_current_store,
+            list_config_revisions,
+            diff_config_revisions,
+            rollback_config,
+            get_resolved_config,
+            set_config_parent,
+            export_config_bundle,
+            read_config_bundle,
+            import_config_bundle,
+            list_journal,
+            undo_last,
+            undo_entry,
+            get_vault_status,
+            create_vault,
+            unlock_vault,
+            lock_vault,
+            list_secrets,
+            set_secret,
+            rotate_secret,
+            delete_secret,
+            migrate_plaintext_secrets,
+            check_config_drift,
+            capture_config_drift,
+            reapply_config_store,
+            fork_config_drift,
             open_config_path,
             get_global_mcp_servers,
             update_global_mcp_server,
             delete_global_mcp_server,
             check_mcp_server_exists,
+            probe_mcp_server,
+            resolve_mcp_servers,
+            transfer_mcp_server,
+            transfer_mcp_servers,
+            preview_mcp_import,
+            import_mcp_servers,
+            disable_mcp_server,
+            enable_mcp_server,
+            list_disabled_mcp_servers,
+            list_mcp_server_toggles,
             read_claude_projects,
             read_claude_config_file,
             write_claude_config_file,
@@ -212,6 +269,21 @@ pub fn run() {
             add_claude_code_hook,
             update_claude_code_hook,
             remove_claude_code_hook,
+            rotate_hook_secret,
+            get_hook_server_status,
+            get_hook_settings,
+            set_hook_settings,
+            list_policy_rules,
+            create_policy_rule,
+            update_policy_rule,
+            delete_policy_rule,
+            get_notification_settings,
+            create_notification_rule,
+            update_notification_rule,
+            delete_notification_rule,
+            reorder_notification_rules,
+            set_notification_quiet_hours,
+            query_hook_events,
             read_claude_commands,
             write_claude_command,
             delete_claude_command,
@@ -248,6 +320,7 @@ pub fn run() {
             delete_project_command,
             read_project_mcp,
             write_project_mcp,
+            migrate_project_mcp,
             get_project_registry,
             update_project_registry,
             delete_project_config,
@@ -260,6 +333,7 @@ pub fn run() {
             sessions::session_resume,
             sessions::session_cancel,
             sessions::session_delete,
+            sessions::session_live_list,
             sessions::session_migrate_models,
             // Model commands
             models::get_models,
@@ -0,0 +1,366 @@
+//! Crash-safe, locked writes for the files the app manages
+//!
+//! Every write goes to a temp file in the same directory, is fsynced, and then
+//! atomically renamed over the target. Writers take an advisory lock on a
+//! sidecar file in ~/.ccconfig/locks and re-check the file's fingerprint just
+//! before renaming, so a concurrent change (e.g. Claude Code rewriting
+//! ~/.claude.json) is retried or reported instead of clobbered.
+
+use fs4::fs_std::FileExt;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const LOCKS_DIR: &str = ".ccconfig/locks";
+
+/// How often `update` re-reads and re-applies a change after a conflict
+const MAX_ATTEMPTS: usize = 5;
+
+/// File contents as read, plus the fingerprint used to detect later changes
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    content: Option<String>,
+    fingerprint: Option<[u8; 32]>,
+}
+
+impl Snapshot {
+    /// File contents, or `None` if the file did not exist
+    pub fn content(&self) -> Option<&str> {
+        self.content.as_deref()
+    }
+
+    /// Hex fingerprint of the contents, for callers that hand the snapshot to
+    /// the frontend and check it again on save
+    pub fn fingerprint_hex(&self) -> Option<String> {
+        self.fingerprint
+            .map(|f| f.iter().map(|b| format!("{:02x}", b)).collect())
+    }
+}
+
+enum WriteError {
+    Conflict,
+    Io(String),
+}
+
+fn conflict_message(path: &Path) -> String {
+    format!(
+        "{} was modified by another process while saving, please try again",
+        path.display()
+    )
+}
+
+fn fingerprint(bytes: &[u8]) -> [u8; 32] {
+    Sha256::digest(bytes).into()
+}
+
+/// Write through symlinks (e.g. dotfile managers) instead of replacing them
+fn resolve_target(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Exclusive advisory lock, released on drop
+struct FileLock(File);
+
+impl FileLock {
+    fn acquire(path: &Path) -> Result<Self, String> {
+        let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
+        let locks_dir = home_dir.join(LOCKS_DIR);
+        std::fs::create_dir_all(&locks_dir)
+            .map_err(|e| format!("Failed to create locks directory: {}", e))?;
+
+        // Keep lock files out of project directories by keying them on the path hash
+        let digest = fingerprint(path.to_string_lossy().as_bytes());
+        let name: String = digest[..8].iter().map(|b| format!("{:02x}", b)).collect();
+
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(locks_dir.join(format!("{}.lock", name)))
+            .map_err(|e| format!("Failed to open lock file: {}", e))?;
+
+        file.lock_exclusive()
+            .map_err(|e| format!("Failed to lock {}: {}", path.display(), e))?;
+
+        Ok(FileLock(file))
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = FileExt::unlock(&self.0);
+    }
+}
+
+fn read_bytes(path: &Path) -> Result<Option<Vec<u8>>, String> {
+    match std::fs::read(path) {
+        Ok(bytes) => Ok(Some(bytes)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+    }
+}
+
+/// Write `content` to a temp file next to `path`, fsync it and rename it into place
+fn write_atomic(path: &Path, content: &[u8]) -> Result<(), String> {
+    let parent = path
+        .parent()
+        .ok_or_else(|| format!("Invalid file path: {}", path.display()))?;
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| format!("Invalid file path: {}", path.display()))?
+        .to_string_lossy();
+
+    std::fs::create_dir_all(parent)
+        .map_err(|e| format!("Failed to create directory {}: {}", parent.display(), e))?;
+
+    let tmp_path = parent.join(format!(
+        ".{}.{}.tmp",
+        file_name,
+        uuid::Uuid::new_v4().simple()
+    ));
+
+    let result = (|| -> std::io::Result<()> {
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(content)?;
+
+        // Keep the original permissions (e.g. 0600 on ~/.claude.json)
+        if let Ok(metadata) = std::fs::metadata(path) {
+            std::fs::set_permissions(&tmp_path, metadata.permissions())?;
+        }
+
+        file.sync_all()?;
+        std::fs::rename(&tmp_path, path)?;
+
+        // Persist the rename itself
+        #[cfg(unix)]
+        File::open(parent)?.sync_all()?;
+
+        Ok(())
+    })();
+
+    result.map_err(|e| {
+        let _ = std::fs::remove_file(&tmp_path);
+        format!("Failed to write {}: {}", path.display(), e)
+    })
+}
+
+fn try_write(path: &Path, expected: Option<&Snapshot>, content: &[u8]) -> Result<(), WriteError> {
+    let _lock = FileLock::acquire(path).map_err(WriteError::Io)?;
+
+    if let Some(snapshot) = expected {
+        let current = read_bytes(path).map_err(WriteError::Io)?;
+        if current.as_deref().map(fingerprint) != snapshot.fingerprint {
+            return Err(WriteError::Conflict);
+        }
+    }
+
+    write_atomic(path, content).map_err(WriteError::Io)
+}
+
+/// Read a file and remember its fingerprint for `write_if_unchanged`
+pub fn read(path: &Path) -> Result<Snapshot, String> {
+    let path = resolve_target(path);
+    let bytes = read_bytes(&path)?;
+
+    Ok(Snapshot {
+        fingerprint: bytes.as_deref().map(fingerprint),
+        content: bytes
+            .map(|b| {
+                String::from_utf8(b)
+                    .map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+            })
+            .transpose()?,
+    })
+}
+
+/// Atomically replace a file, regardless of what it currently contains
+pub fn write(path: &Path, content: &str) -> Result<(), String> {
+    write_bytes(path, content.as_bytes())
+}
+
+/// `write` for content that isn't necessarily UTF-8, e.g. restored hook scripts
+pub fn write_bytes(path: &Path, content: &[u8]) -> Result<(), String> {
+    let path = resolve_target(path);
+    try_write(&path, None, content).map_err(|e| match e {
+        WriteError::Conflict => conflict_message(&path),
+        WriteError::Io(e) => e,
+    })
+}
+
+/// Atomically replace a file, failing if it changed since `snapshot` was read
+pub fn write_if_unchanged(path: &Path, snapshot: &Snapshot, content: &str) -> Result<(), String> {
+    let path = resolve_target(path);
+    try_write(&path, Some(snapshot), content.as_bytes()).map_err(|e| match e {
+        WriteError::Conflict => conflict_message(&path),
+        WriteError::Io(e) => e,
+    })
+}
+
+/// Read-modify-write a file, re-running `f` on fresh contents if the file
+/// changes underneath. `f` returns `None` when nothing needs to be written.
+pub fn update<F>(path: &Path, mut f: F) -> Result<(), String>
+where
+    F: FnMut(Option<&str>) -> Result<Option<String>, String>,
+{
+    for _ in 0..MAX_ATTEMPTS {
+        let snapshot = read(path)?;
+        let Some(new_content) = f(snapshot.content())? else {
+            return Ok(());
+        };
+
+        match try_write(
+            &resolve_target(path),
+            Some(&snapshot),
+            new_content.as_bytes(),
+        ) {
+            Ok(()) => return Ok(()),
+            Err(WriteError::Conflict) => continue,
+            Err(WriteError::Io(e)) => return Err(e),
+        }
+    }
+
+    Err(conflict_message(path))
+}
+
+/// Read-modify-write a JSON file. A missing file starts as `{}`, and the file
+/// is only written when `f` actually changed the value.
+pub fn update_json<R, F>(path: &Path, mut f: F) -> Result<R, String>
+where
+    F: FnMut(&mut Value) -> Result<R, String>,
+{
+    let mut result = None;
+
+    update(path, |content| {
+        let mut value = match content {
+            Some(content) if !content.trim().is_empty() => serde_json::from_str(content)
+                .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?,
+            _ => Value::Object(serde_json::Map::new()),
+        };
+        let original = value.clone();
+
+        result = Some(f(&mut value)?);
+
+        if value == original {
+            return Ok(None);
+        }
+
+        serde_json::to_string_pretty(&value)
+            .map(Some)
+            .map_err(|e| format!("Failed to serialize {}: {}", path.display(), e))
+    })?;
+
+    result.ok_or_else(|| format!("Failed to update {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_write_creates_parent_and_leaves_no_temp_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested/settings.json");
+
+        write(&path, "{}").unwrap();
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "{}");
+        let entries: Vec<_> = std::fs::read_dir(path.parent().unwrap())
+            .unwrap()
+            .flatten()
+            .collect();
+        assert_eq!(entries.len(), 1);
+    }
+
+    #[test]
+    fn test_write_if_unchanged_detects_conflict() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("stores.json");
+        std::fs::write(&path, "a").unwrap();
+
+        let snapshot = read(&path).unwrap();
+        std::fs::write(&path, "b").unwrap();
+
+        assert!(write_if_unchanged(&path, &snapshot, "c").is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "b");
+
+        let snapshot = read(&path).unwrap();
+        write_if_unchanged(&path, &snapshot, "c").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "c");
+    }
+
+    #[test]
+    fn test_write_if_unchanged_missing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("project-registry.json");
+
+        let snapshot = read(&path).unwrap();
+        assert!(snapshot.content().is_none());
+
+        write_if_unchanged(&path, &snapshot, "[]").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "[]");
+    }
+
+    #[test]
+    fn test_update_retries_after_concurrent_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".claude.json");
+        std::fs::write(&path, r#"{"numStartups": 1}"#).unwrap();
+
+        let mut attempts = 0;
+        update_json(&path, |value| {
+            attempts += 1;
+            if attempts == 1 {
+                // Simulate Claude Code rewriting the file mid-update
+                std::fs::write(&path, r#"{"numStartups": 2}"#).unwrap();
+            }
+            value["mcpServers"] = json!({ "fs": { "command": "npx" } });
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(attempts, 2);
+        let written: Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(written["numStartups"], json!(2));
+        assert_eq!(written["mcpServers"]["fs"]["command"], json!("npx"));
+    }
+
+    #[test]
+    fn test_update_json_skips_unchanged_write() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        std::fs::write(&path, r#"{"model":"opus"}"#).unwrap();
+
+        update_json(&path, |_| Ok(())).unwrap();
+
+        // Untouched, so the compact formatting survives
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            r#"{"model":"opus"}"#
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_follows_symlinks() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("dotfiles-settings.json");
+        let link = dir.path().join("settings.json");
+        std::fs::write(&target, "{}").unwrap();
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        write(&link, r#"{"model":"opus"}"#).unwrap();
+
+        assert!(std::fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(
+            std::fs::read_to_string(&target).unwrap(),
+            r#"{"model":"opus"}"#
+        );
+    }
+}
@@ -0,0 +1,530 @@
+//! Import MCP servers from other clients' config files
+//!
+//! Each client keeps its servers under its own key and field names. Entries
+//! are translated into Claude Code's `mcpServers` shape and checked, then
+//! shown as a preview before anything is written.
+
+use super::server::validate_server;
+use crate::bundle::{unique_name, ConflictStrategy, ImportAction};
+use crate::claude_settings::ValidationError;
+use serde_json::{json, Map, Value};
+use std::path::Path;
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum McpImportFormat {
+    /// claude_desktop_config.json
+    ClaudeDesktop,
+    /// ~/.cursor/mcp.json or PROJECT/.cursor/mcp.json
+    Cursor,
+    /// .vscode/mcp.json, or `mcp.servers` in settings.json
+    VsCode,
+    /// ~/.codeium/windsurf/mcp_config.json
+    Windsurf,
+    /// `context_servers` in Zed's settings.json
+    Zed,
+    /// ~/.codex/config.toml
+    Codex,
+    /// ~/.gemini/settings.json
+    Gemini,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct McpImportCandidate {
+    /// Name in the source file
+    pub name: String,
+    /// Name the server would be saved under
+    #[serde(rename = "targetName")]
+    pub target_name: String,
+    pub config: Value,
+    pub action: ImportAction,
+    /// Things lost or left to fix by hand in translation
+    pub warnings: Vec<String>,
+    /// Problems that stop the server from being saved
+    pub errors: Vec<ValidationError>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct McpImportSkipped {
+    pub name: String,
+    pub reason: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct McpImportPreview {
+    pub format: McpImportFormat,
+    pub candidates: Vec<McpImportCandidate>,
+    pub skipped: Vec<McpImportSkipped>,
+}
+
+/// A translated entry, or why it can't be translated
+type Translation = Result<(Value, Vec<String>), String>;
+
+/// Strip `//` and `/* */` comments and trailing commas, as VS Code and Zed allow
+fn strip_jsonc(content: &str) -> String {
+    let mut without_comments = String::with_capacity(content.len());
+    let mut chars = content.chars().peekable();
+    let mut in_string = false;
+
+    while let Some(c) = chars.next() {
+        if in_string {
+            without_comments.push(c);
+            if c == '\\' {
+                without_comments.extend(chars.next());
+            } else if c == '"' {
+                in_string = false;
+            }
+        } else if c == '/' && chars.peek() == Some(&'/') {
+            while chars.next_if(|&c| c != '\n').is_some() {}
+        } else if c == '/' && chars.peek() == Some(&'*') {
+            chars.next();
+            while let Some(c) = chars.next() {
+                if c == '*' && chars.next_if_eq(&'/').is_some() {
+                    break;
+                }
+            }
+        } else {
+            in_string = c == '"';
+            without_comments.push(c);
+        }
+    }
+
+    let chars: Vec<char> = without_comments.chars().collect();
+    let mut out = String::with_capacity(chars.len());
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for (i, &c) in chars.iter().enumerate() {
+        if in_string {
+            in_string = escaped || c != '"';
+            escaped = !escaped && c == '\\';
+        } else if c == '"' {
+            in_string = true;
+        } else if c == ',' {
+            let next = chars[i + 1..].iter().find(|c| !c.is_whitespace());
+            if matches!(next, Some('}') | Some(']')) {
+                continue;
+            }
+        }
+        out.push(c);
+    }
+
+    out
+}
+
+fn parse_source(content: &str, format: McpImportFormat) -> Result<Value, String> {
+    match format {
+        McpImportFormat::Codex => {
+            toml::from_str(content).map_err(|e| format!("Failed to parse TOML: {}", e))
+        }
+        McpImportFormat::VsCode | McpImportFormat::Zed => {
+            serde_json::from_str(&strip_jsonc(content))
+                .map_err(|e| format!("Failed to parse JSON: {}", e))
+        }
+        _ => serde_json::from_str(content).map_err(|e| format!("Failed to parse JSON: {}", e)),
+    }
+}
+
+/// The object holding server entries for `format`
+fn server_entries(doc: &Value, format: McpImportFormat) -> Option<&Map<String, Value>> {
+    let entries = match format {
+        McpImportFormat::VsCode => doc
+            .get("servers")
+            .or_else(|| doc.get("mcp").and_then(|m| m.get("servers")))
+            .or_else(|| doc.get("mcp.servers")),
+        McpImportFormat::Zed => doc.get("context_servers"),
+        McpImportFormat::Codex => doc.get("mcp_servers"),
+        _ => doc.get("mcpServers"),
+    };
+    entries.and_then(|e| e.as_object())
+}
+
+/// SSE endpoints conventionally end in `/sse`
+fn url_transport(url: &str) -> &'static str {
+    if url.trim_end_matches('/').ends_with("/sse") {
+        "sse"
+    } else {
+        "http"
+    }
+}
+
+/// Builds the Claude Code definition, tracking which source fields were used
+struct Entry<'a> {
+    source: &'a Map<String, Value>,
+    used: Vec<&'static str>,
+    config: Map<String, Value>,
+    warnings: Vec<String>,
+}
+
+impl<'a> Entry<'a> {
+    fn new(source: &'a Map<String, Value>) -> Self {
+        Entry {
+            source,
+            used: vec![],
+            config: Map::new(),
+            warnings: vec![],
+        }
+    }
+
+    fn take(&mut self, key: &'static str) -> Option<&'a Value> {
+        let value = self.source.get(key)?;
+        self.used.push(key);
+        Some(value)
+    }
+
+    fn copy(&mut self, from: &'static str, to: &str) {
+        if let Some(value) = self.take(from) {
+            self.config.insert(to.to_string(), value.clone());
+        }
+    }
+
+    fn stdio(&mut self) {
+        self.config.insert("type".to_string(), json!("stdio"));
+        self.copy("command", "command");
+        self.copy("args", "args");
+        self.copy("env", "env");
+    }
+
+    fn remote(&mut self, url_key: &'static str, transport: Option<&str>) -> bool {
+        let Some(url) = self.take(url_key).and_then(|u| u.as_str()) else {
+            return false;
+        };
+        let transport = transport.unwrap_or_else(|| url_transport(url));
+        self.config.insert("type".to_string(), json!(transport));
+        self.config.insert("url".to_string(), json!(url));
+        self.copy("headers", "headers");
+        true
+    }
+
+    fn finish(mut self) -> Translation {
+        self.used.extend(["disabled", "enabled"]);
+        if self.source.get("disabled") == Some(&json!(true))
+            || self.source.get("enabled") == Some(&json!(false))
+        {
+            self.warnings
+                .push("Disabled in the source client, but will be enabled here".to_string());
+        }
+
+        let dropped: Vec<&str> = self
+            .source
+            .keys()
+            .map(|k| k.as_str())
+            .filter(|k| !self.used.iter().any(|used| used == k))
+            .collect();
+        if !dropped.is_empty() {
+            self.warnings.push(format!(
+                "Dropped fields Claude Code doesn't support: {}",
+                dropped.join(", ")
+            ));
+        }
+
+        let config = Value::Object(self.config);
+        let text = config.to_string();
+        if text.contains("${input:") {
+            self.warnings
+                .push("Uses VS Code input variables, replace them with real values".to_string());
+        }
+        if text.contains("${workspaceFolder") {
+            self.warnings
+                .push("Uses ${workspaceFolder}, which Claude Code doesn't expand".to_string());
+        }
+
+        Ok((config, self.warnings))
+    }
+}
+
+fn translate_entry(format: McpImportFormat, value: &Value) -> Translation {
+    let source = value.as_object().ok_or("Entry is not an object")?;
+    let mut entry = Entry::new(source);
+
+    match format {
+        McpImportFormat::ClaudeDesktop | McpImportFormat::Cursor => {
+            if !entry.remote("url", None) {
+                entry.stdio();
+            }
+        }
+        McpImportFormat::Windsurf => {
+            if !entry.remote("serverUrl", None) && !entry.remote("url", None) {
+                entry.stdio();
+            }
+        }
+        McpImportFormat::VsCode => {
+            let transport = entry.take("type").and_then(|t| t.as_str());
+            match transport {
+                Some("http") | Some("sse") => {
+                    entry.remote("url", transport);
+                }
+                _ => {
+                    if !entry.remote("url", None) {
+                        entry.stdio();
+                    }
+                }
+            }
+            if entry.take("envFile").is_some() {
+                entry
+                    .warnings
+                    .push("envFile isn't supported, copy its variables into env".to_string());
+            }
+        }
+        McpImportFormat::Gemini => {
+            if !entry.remote("httpUrl", Some("http")) && !entry.remote("url", Some("sse")) {
+                entry.stdio();
+            }
+        }
+        McpImportFormat::Zed => {
+            if source.get("source").and_then(|s| s.as_str()) == Some("extension") {
+                return Err("Provided by a Zed extension".to_string());
+            }
+            entry.take("source");
+
+            // Older Zed settings nest the command as { path, args, env }
+            match source.get("command") {
+                Some(Value::Object(command)) => {
+                    entry.take("command");
+                    entry.config.insert("type".to_string(), json!("stdio"));
+                    for (from, to) in [("path", "command"), ("args", "args"), ("env", "env")] {
+                        if let Some(value) = command.get(from) {
+                            entry.config.insert(to.to_string(), value.clone());
+                        }
+                    }
+                }
+                _ => {
+                    if !entry.remote("url", None) {
+                        entry.stdio();
+                    }
+                }
+            }
+        }
+        McpImportFormat::Codex => {
+            if entry.remote("url", Some("http")) {
+                let mut headers = entry
+                    .take("http_headers")
+                    .and_then(|h| h.as_object())
+                    .cloned()
+                    .unwrap_or_default();
+                if let Some(env_headers) =
+                    entry.take("env_http_headers").and_then(|h| h.as_object())
+                {
+                    for (name, var) in env_headers {
+                        if let Some(var) = var.as_str() {
+                            headers.insert(name.clone(), json!(format!("${{{}}}", var)));
+                        }
+                    }
+                }
+                if let Some(var) = entry.take("bearer_token_env_var").and_then(|v| v.as_str()) {
+                    headers.insert(
+                        "Authorization".to_string(),
+                        json!(format!("Bearer ${{{}}}", var)),
+                    );
+                }
+                if !headers.is_empty() {
+                    entry
+                        .config
+                        .insert("headers".to_string(), Value::Object(headers));
+                }
+            } else {
+                entry.stdio();
+            }
+        }
+    }
+
+    entry.finish()
+}
+
+/// Translate `content` and plan where each server would go given `existing`
+pub fn preview(
+    content: &str,
+    format: McpImportFormat,
+    existing: &Map<String, Value>,
+    strategy: ConflictStrategy,
+) -> Result<McpImportPreview, String> {
+    let doc = parse_source(content, format)?;
+    let entries = server_entries(&doc, format)
+        .ok_or("No MCP servers found in this file for the selected format")?;
+
+    let mut taken: Map<String, Value> = existing.clone();
+    let mut candidates = Vec::new();
+    let mut skipped = Vec::new();
+
+    for (name, value) in entries {
+        let (config, mut warnings) = match translate_entry(format, value) {
+            Ok(translated) => translated,
+            Err(reason) => {
+                skipped.push(McpImportSkipped {
+                    name: name.clone(),
+                    reason,
+                });
+                continue;
+            }
+        };
+
+        let (config, errors) = match validate_server(&config, &format!("$.{}", name)) {
+            Ok((config, environment)) => {
+                warnings.extend(
+                    environment
+                        .into_iter()
+                        .map(|warning| format!("{}: {}", warning.path, warning.message)),
+                );
+                (config, vec![])
+            }
+            Err(errors) => (config, errors),
+        };
+
+        let action = match (taken.contains_key(name), strategy) {
+            (false, _) => ImportAction::Create,
+            (true, ConflictStrategy::Skip) => ImportAction::Skip,
+            (true, ConflictStrategy::Overwrite) => ImportAction::Overwrite,
+            (true, ConflictStrategy::Rename) => ImportAction::Rename,
+        };
+        let target_name = match action {
+            ImportAction::Rename => unique_name(name, |n| taken.contains_key(n)),
+            _ => name.clone(),
+        };
+        taken.insert(target_name.clone(), Value::Null);
+
+        candidates.push(McpImportCandidate {
+            name: name.clone(),
+            target_name,
+            config,
+            action,
+            warnings,
+            errors,
+        });
+    }
+
+    Ok(McpImportPreview {
+        format,
+        candidates,
+        skipped,
+    })
+}
+
+pub fn preview_file(
+    path: &Path,
+    format: McpImportFormat,
+    existing: &Map<String, Value>,
+    strategy: ConflictStrategy,
+) -> Result<McpImportPreview, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    preview(&content, format, existing, strategy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate<'a>(preview: &'a McpImportPreview, name: &str) -> &'a McpImportCandidate {
+        preview.candidates.iter().find(|c| c.name == name).unwrap()
+    }
+
+    #[test]
+    fn test_preview_vscode_jsonc() {
+        let content = r#"{
+            // Team servers
+            "inputs": [{ "id": "token", "type": "promptString" }],
+            "servers": {
+                "local": { "type": "stdio", "command": "sh", "args": ["-c", "x"], "envFile": ".env" },
+                "remote": { "type": "sse", "url": "https://example.com/events", }, /* trailing */
+            }
+        }"#;
+
+        let preview = preview(
+            content,
+            McpImportFormat::VsCode,
+            &Map::new(),
+            ConflictStrategy::Skip,
+        )
+        .unwrap();
+
+        let local = candidate(&preview, "local");
+        assert_eq!(
+            local.config,
+            json!({ "type": "stdio", "command": "sh", "args": ["-c", "x"] })
+        );
+        assert_eq!(local.warnings.len(), 1);
+        assert!(local.errors.is_empty());
+
+        let remote = candidate(&preview, "remote");
+        assert_eq!(remote.config["type"], "sse");
+        assert_eq!(remote.action, ImportAction::Create);
+    }
+
+    #[test]
+    fn test_preview_codex_toml_renames_collisions() {
+        let content = r#"
+[mcp_servers.docs]
+url = "https://docs.example.com/mcp"
+bearer_token_env_var = "DOCS_TOKEN"
+startup_timeout_sec = 20
+
+[mcp_servers.shell]
+command = "sh"
+args = ["-c", "serve"]
+"#;
+        let existing: Map<String, Value> = [("shell".to_string(), json!({}))].into_iter().collect();
+
+        let preview = preview(
+            content,
+            McpImportFormat::Codex,
+            &existing,
+            ConflictStrategy::Rename,
+        )
+        .unwrap();
+
+        let docs = candidate(&preview, "docs");
+        assert_eq!(docs.config["type"], "http");
+        assert_eq!(
+            docs.config["headers"]["Authorization"],
+            "Bearer ${DOCS_TOKEN}"
+        );
+        // DOCS_TOKEN isn't set in the test environment, which only warns
+        assert_eq!(
+            docs.warnings,
+            vec![
+                "Dropped fields Claude Code doesn't support: startup_timeout_sec",
+                "$.docs.headers.Authorization: Environment variable DOCS_TOKEN is not set in this app's environment",
+            ]
+        );
+        assert!(docs.errors.is_empty());
+
+        let shell = candidate(&preview, "shell");
+        assert_eq!(shell.action, ImportAction::Rename);
+        assert_eq!(shell.target_name, "shell-2");
+    }
+
+    #[test]
+    fn test_preview_zed_and_windsurf() {
+        let zed = r#"{
+            "context_servers": {
+                "legacy": { "command": { "path": "sh", "args": ["x"] }, "settings": {} },
+                "ext": { "source": "extension", "settings": {} }
+            }
+        }"#;
+        let preview = preview(
+            zed,
+            McpImportFormat::Zed,
+            &Map::new(),
+            ConflictStrategy::Skip,
+        )
+        .unwrap();
+        assert_eq!(
+            candidate(&preview, "legacy").config,
+            json!({ "type": "stdio", "command": "sh", "args": ["x"] })
+        );
+        assert_eq!(preview.skipped[0].name, "ext");
+
+        let windsurf = r#"{ "mcpServers": { "events": { "serverUrl": "https://x.dev/sse" } } }"#;
+        let preview = super::preview(
+            windsurf,
+            McpImportFormat::Windsurf,
+            &Map::new(),
+            ConflictStrategy::Skip,
+        )
+        .unwrap();
+        assert_eq!(
+            candidate(&preview, "events").config,
+            json!({ "type": "sse", "url": "https://x.dev/sse" })
+        );
+    }
+}
@@ -0,0 +1,9 @@
+//! Model Context Protocol helpers
+
+pub mod import;
+pub mod probe;
+pub mod project;
+pub mod resolver;
+pub mod server;
+pub mod stash;
+pub mod transfer;
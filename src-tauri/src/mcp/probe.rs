@@ -0,0 +1,931 @@
+//! MCP server health check
+//!
+//! Connects to a server the way Claude Code would: it spawns a stdio server,
+//! or talks to a streamable HTTP or legacy SSE endpoint. It then performs the
+//! `initialize` handshake and lists the server's tools, prompts and
+//! resources, all under a single timeout.
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, ACCEPT};
+use reqwest::Url;
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(15);
+
+const PROTOCOL_VERSION: &str = "2025-06-18";
+
+/// Lines of stderr kept for failure reports
+const STDERR_TAIL_LINES: usize = 20;
+
+/// Stops a server that keeps handing out cursors
+const MAX_LIST_PAGES: usize = 50;
+
+const METHOD_NOT_FOUND: i64 = -32601;
+
+const SESSION_HEADER: &str = "mcp-session-id";
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProbeTransport {
+    Stdio,
+    Http,
+    Sse,
+}
+
+/// How far the probe got before failing
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum ProbeStage {
+    Config,
+    Connect,
+    Initialize,
+    List,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct McpServerReport {
+    pub transport: ProbeTransport,
+    #[serde(rename = "protocolVersion")]
+    pub protocol_version: String,
+    #[serde(rename = "serverInfo")]
+    pub server_info: Value,
+    pub capabilities: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instructions: Option<String>,
+    pub tools: Vec<Value>,
+    pub prompts: Vec<Value>,
+    pub resources: Vec<Value>,
+    #[serde(rename = "durationMs")]
+    pub duration_ms: u64,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct McpProbeFailure {
+    pub transport: Option<ProbeTransport>,
+    pub stage: ProbeStage,
+    #[serde(rename = "timedOut")]
+    pub timed_out: bool,
+    pub message: String,
+    /// Last lines a stdio server wrote to stderr
+    #[serde(rename = "stderrTail")]
+    pub stderr_tail: Vec<String>,
+    #[serde(rename = "exitCode", skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    #[serde(rename = "durationMs")]
+    pub duration_ms: u64,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum McpProbeResult {
+    Ok(McpServerReport),
+    Failed(McpProbeFailure),
+}
+
+struct ProbeError {
+    stage: ProbeStage,
+    message: String,
+    exit_code: Option<i32>,
+}
+
+impl ProbeError {
+    fn new(stage: ProbeStage, message: impl Into<String>) -> Self {
+        ProbeError {
+            stage,
+            message: message.into(),
+            exit_code: None,
+        }
+    }
+}
+
+type StderrTail = Arc<Mutex<VecDeque<String>>>;
+
+/// Incremental parser for `text/event-stream` bodies
+#[derive(Default)]
+struct SseParser {
+    buffer: Vec<u8>,
+    event: Option<String>,
+    data: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct SseEvent {
+    event: String,
+    data: String,
+}
+
+impl SseParser {
+    /// Feed raw bytes, returning the events they complete
+    fn push(&mut self, bytes: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(bytes);
+
+        let mut events = Vec::new();
+        while let Some(pos) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line_bytes: Vec<u8> = self.buffer.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line_bytes);
+            let line = line.trim_end_matches(['\n', '\r']);
+
+            if line.is_empty() {
+                if !self.data.is_empty() {
+                    events.push(SseEvent {
+                        event: self.event.take().unwrap_or_else(|| "message".to_string()),
+                        data: self.data.join("\n"),
+                    });
+                    self.data.clear();
+                }
+                self.event = None;
+            } else if let Some(value) = line.strip_prefix("data:") {
+                self.data
+                    .push(value.strip_prefix(' ').unwrap_or(value).to_string());
+            } else if let Some(value) = line.strip_prefix("event:") {
+                self.event = Some(value.trim().to_string());
+            }
+            // Comments (`:`), `id:` and `retry:` don't matter for a probe
+        }
+
+        events
+    }
+}
+
+struct StdioConnection {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: Lines<BufReader<ChildStdout>>,
+}
+
+struct HttpConnection {
+    client: reqwest::Client,
+    url: Url,
+    headers: HeaderMap,
+    session_id: Option<String>,
+}
+
+struct SseConnection {
+    client: reqwest::Client,
+    post_url: Url,
+    headers: HeaderMap,
+    stream: reqwest::Response,
+    parser: SseParser,
+    pending: VecDeque<SseEvent>,
+}
+
+enum Connection {
+    Stdio(StdioConnection),
+    Http(HttpConnection),
+    Sse(SseConnection),
+}
+
+fn is_response_to(message: &Value, id: u64) -> bool {
+    message.get("id").and_then(|v| v.as_u64()) == Some(id)
+        && (message.get("result").is_some() || message.get("error").is_some())
+}
+
+/// Pick the response to `id` out of a JSON body, which may be a batch
+fn find_response(body: Value, id: u64) -> Option<Value> {
+    match body {
+        Value::Array(messages) => messages.into_iter().find(|m| is_response_to(m, id)),
+        message if is_response_to(&message, id) => Some(message),
+        _ => None,
+    }
+}
+
+async fn read_sse_response(
+    response: &mut reqwest::Response,
+    parser: &mut SseParser,
+    pending: &mut VecDeque<SseEvent>,
+    id: u64,
+) -> Result<Value, String> {
+    loop {
+        while let Some(event) = pending.pop_front() {
+            if event.event != "message" {
+                continue;
+            }
+            if let Ok(message) = serde_json::from_str::<Value>(&event.data) {
+                if let Some(response) = find_response(message, id) {
+                    return Ok(response);
+                }
+            }
+        }
+
+        let chunk = response
+            .chunk()
+            .await
+            .map_err(|e| format!("Failed to read event stream: {}", e))?
+            .ok_or("Event stream closed before the server responded")?;
+        pending.extend(parser.push(&chunk));
+    }
+}
+
+impl StdioConnection {
+    async fn send(&mut self, message: &Value) -> Result<(), String> {
+        let mut line = message.to_string();
+        line.push('\n');
+        self.stdin
+            .write_all(line.as_bytes())
+            .await
+            .map_err(|e| format!("Failed to write to server stdin: {}", e))?;
+        self.stdin
+            .flush()
+            .await
+            .map_err(|e| format!("Failed to write to server stdin: {}", e))
+    }
+
+    async fn receive(&mut self, id: u64) -> Result<Value, String> {
+        loop {
+            let line = self
+                .stdout
+                .next_line()
+                .await
+                .map_err(|e| format!("Failed to read server stdout: {}", e))?
+                .ok_or("Server closed stdout")?;
+
+            // Servers sometimes log to stdout; anything that isn't JSON-RPC is skipped
+            if let Ok(message) = serde_json::from_str::<Value>(&line) {
+                if is_response_to(&message, id) {
+                    return Ok(message);
+                }
+            }
+        }
+    }
+
+    /// Exit code of a server that stopped, waiting briefly for it to finish
+    async fn exit_code(&mut self) -> Option<i32> {
+        match tokio::time::timeout(Duration::from_millis(500), self.child.wait()).await {
+            Ok(Ok(status)) => status.code(),
+            _ => None,
+        }
+    }
+}
+
+impl HttpConnection {
+    async fn post(&mut self, message: &Value, id: Option<u64>) -> Result<Option<Value>, String> {
+        let mut request = self
+            .client
+            .post(self.url.clone())
+            .headers(self.headers.clone())
+            .header(ACCEPT, "application/json, text/event-stream")
+            .json(message);
+        if let Some(session_id) = &self.session_id {
+            request = request.header(SESSION_HEADER, session_id);
+        }
+
+        let mut response = request
+            .send()
+            .await
+            .map_err(|e| format!("Request to {} failed: {}", self.url, e))?;
+
+        if let Some(session_id) = response
+            .headers()
+            .get(SESSION_HEADER)
+            .and_then(|v| v.to_str().ok())
+        {
+            self.session_id = Some(session_id.to_string());
+        }
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(format!("HTTP {}: {}", status, body.trim()));
+        }
+
+        let Some(id) = id else {
+            return Ok(None);
+        };
+
+        let is_event_stream = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.starts_with("text/event-stream"));
+
+        if is_event_stream {
+            let mut parser = SseParser::default();
+            let mut pending = VecDeque::new();
+            return read_sse_response(&mut response, &mut parser, &mut pending, id)
+                .await
+                .map(Some);
+        }
+
+        let body: Value = response
+            .json()
+            .await
+            .map_err(|e| format!("Invalid JSON response: {}", e))?;
+        find_response(body, id)
+            .map(Some)
+            .ok_or_else(|| "Response did not answer the request".to_string())
+    }
+}
+
+impl SseConnection {
+    async fn connect(
+        client: reqwest::Client,
+        url: Url,
+        headers: HeaderMap,
+    ) -> Result<Self, String> {
+        let stream = client
+            .get(url.clone())
+            .headers(headers.clone())
+            .header(ACCEPT, "text/event-stream")
+            .send()
+            .await
+            .map_err(|e| format!("Failed to connect to {}: {}", url, e))?;
+
+        if !stream.status().is_success() {
+            return Err(format!("HTTP {} from {}", stream.status(), url));
+        }
+
+        let mut connection = SseConnection {
+            client,
+            post_url: url.clone(),
+            headers,
+            stream,
+            parser: SseParser::default(),
+            pending: VecDeque::new(),
+        };
+
+        // The server first tells us where to POST messages
+        loop {
+            while let Some(event) = connection.pending.pop_front() {
+                if event.event == "endpoint" {
+                    connection.post_url = url
+                        .join(event.data.trim())
+                        .map_err(|e| format!("Invalid endpoint '{}': {}", event.data, e))?;
+                    return Ok(connection);
+                }
+            }
+
+            let chunk = connection
+                .stream
+                .chunk()
+                .await
+                .map_err(|e| format!("Failed to read event stream: {}", e))?
+                .ok_or("Event stream closed before sending an endpoint")?;
+            let events = connection.parser.push(&chunk);
+            connection.pending.extend(events);
+        }
+    }
+
+    async fn send(&mut self, message: &Value) -> Result<(), String> {
+        let response = self
+            .client
+            .post(self.post_url.clone())
+            .headers(self.headers.clone())
+            .json(message)
+            .send()
+            .await
+            .map_err(|e| format!("Request to {} failed: {}", self.post_url, e))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(format!("HTTP {}: {}", status, body.trim()));
+        }
+        Ok(())
+    }
+}
+
+impl Connection {
+    async fn request(&mut self, id: u64, method: &str, params: Value) -> Result<Value, String> {
+        let message = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+
+        match self {
+            Connection::Stdio(conn) => {
+                conn.send(&message).await?;
+                conn.receive(id).await
+            }
+            Connection::Http(conn) => conn
+                .post(&message, Some(id))
+                .await?
+                .ok_or_else(|| "Server sent no response".to_string()),
+            Connection::Sse(conn) => {
+                conn.send(&message).await?;
+                read_sse_response(&mut conn.stream, &mut conn.parser, &mut conn.pending, id).await
+            }
+        }
+    }
+
+    async fn notify(&mut self, method: &str) -> Result<(), String> {
+        let message = json!({ "jsonrpc": "2.0", "method": method });
+
+        match self {
+            Connection::Stdio(conn) => conn.send(&message).await,
+            Connection::Http(conn) => conn.post(&message, None).await.map(|_| ()),
+            Connection::Sse(conn) => conn.send(&message).await,
+        }
+    }
+
+    /// Turn a transport failure into a probe error, noting why a stdio server died
+    async fn fail(&mut self, stage: ProbeStage, message: String) -> ProbeError {
+        let exit_code = match self {
+            Connection::Stdio(conn) => conn.exit_code().await,
+            _ => None,
+        };
+        ProbeError {
+            stage,
+            message,
+            exit_code,
+        }
+    }
+}
+
+/// The transport a server config uses, following Claude Code's defaults
+pub fn detect_transport(config: &Value) -> Result<ProbeTransport, String> {
+    match config.get("type").and_then(|t| t.as_str()) {
+        Some("stdio") => Ok(ProbeTransport::Stdio),
+        Some("http") | Some("streamable-http") => Ok(ProbeTransport::Http),
+        Some("sse") => Ok(ProbeTransport::Sse),
+        Some(other) => Err(format!("Unsupported MCP transport '{}'", other)),
+        None if config.get("command").is_some() => Ok(ProbeTransport::Stdio),
+        None if config.get("url").is_some() => Ok(ProbeTransport::Http),
+        None => Err("Server config has neither a command nor a url".to_string()),
+    }
+}
+
+fn string_map(config: &Value, key: &str) -> Vec<(String, String)> {
+    config
+        .get(key)
+        .and_then(|v| v.as_object())
+        .map(|obj| {
+            obj.iter()
+                .filter_map(|(k, v)| v.as_str().map(|v| (k.clone(), v.to_string())))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn header_map(config: &Value) -> Result<HeaderMap, String> {
+    let mut headers = HeaderMap::new();
+    for (name, value) in string_map(config, "headers") {
+        let header_name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|e| format!("Invalid header name '{}': {}", name, e))?;
+        let header_value = HeaderValue::from_str(&value)
+            .map_err(|e| format!("Invalid value for header '{}': {}", name, e))?;
+        headers.insert(header_name, header_value);
+    }
+    Ok(headers)
+}
+
+fn server_url(config: &Value) -> Result<Url, ProbeError> {
+    let url = config
+        .get("url")
+        .and_then(|u| u.as_str())
+        .ok_or_else(|| ProbeError::new(ProbeStage::Config, "Server config has no url"))?;
+    Url::parse(url)
+        .map_err(|e| ProbeError::new(ProbeStage::Config, format!("Invalid url '{}': {}", url, e)))
+}
+
+fn spawn_stdio(config: &Value, stderr_tail: &StderrTail) -> Result<StdioConnection, ProbeError> {
+    let command = config
+        .get("command")
+        .and_then(|c| c.as_str())
+        .ok_or_else(|| ProbeError::new(ProbeStage::Config, "Server config has no command"))?;
+    let args: Vec<String> = config
+        .get("args")
+        .and_then(|a| a.as_array())
+        .map(|args| {
+            args.iter()
+                .filter_map(|a| a.as_str().map(String::from))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut child = Command::new(command)
+        .args(&args)
+        .envs(string_map(config, "env"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| {
+            ProbeError::new(
+                ProbeStage::Connect,
+                format!("Failed to start '{}': {}", command, e),
+            )
+        })?;
+
+    let stdin = child.stdin.take();
+    let stdout = child.stdout.take();
+    let (Some(stdin), Some(stdout)) = (stdin, stdout) else {
+        return Err(ProbeError::new(
+            ProbeStage::Connect,
+            "Failed to open server stdio",
+        ));
+    };
+
+    if let Some(stderr) = child.stderr.take() {
+        let stderr_tail = stderr_tail.clone();
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stderr).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                if let Ok(mut tail) = stderr_tail.lock() {
+                    if tail.len() == STDERR_TAIL_LINES {
+                        tail.pop_front();
+                    }
+                    tail.push_back(line);
+                }
+            }
+        });
+    }
+
+    Ok(StdioConnection {
+        child,
+        stdin,
+        stdout: BufReader::new(stdout).lines(),
+    })
+}
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+fn rpc_result(response: Value) -> Result<Value, RpcError> {
+    match response.get("error") {
+        Some(error) => Err(RpcError {
+            code: error.get("code").and_then(|c| c.as_i64()).unwrap_or(0),
+            message: error
+                .get("message")
+                .and_then(|m| m.as_str())
+                .unwrap_or("Unknown error")
+                .to_string(),
+        }),
+        None => Ok(response.get("result").cloned().unwrap_or(Value::Null)),
+    }
+}
+
+/// Call a paginated `*/list` method and collect every item under `key`
+async fn list_all(
+    connection: &mut Connection,
+    next_id: &mut u64,
+    method: &str,
+    key: &str,
+) -> Result<Vec<Value>, ProbeError> {
+    let mut items = Vec::new();
+    let mut cursor: Option<String> = None;
+
+    for _ in 0..MAX_LIST_PAGES {
+        *next_id += 1;
+        let params = match &cursor {
+            Some(cursor) => json!({ "cursor": cursor }),
+            None => json!({}),
+        };
+
+        let response = match connection.request(*next_id, method, params).await {
+            Ok(response) => response,
+            Err(e) => {
+                return Err(connection
+                    .fail(ProbeStage::List, format!("{} failed: {}", method, e))
+                    .await)
+            }
+        };
+
+        let result = match rpc_result(response) {
+            Ok(result) => result,
+            // Advertised but not implemented; report it as empty rather than broken
+            Err(e) if e.code == METHOD_NOT_FOUND => return Ok(items),
+            Err(e) => {
+                return Err(ProbeError::new(
+                    ProbeStage::List,
+                    format!("{} failed: {}", method, e.message),
+                ))
+            }
+        };
+
+        if let Some(page) = result.get(key).and_then(|v| v.as_array()) {
+            items.extend(page.iter().cloned());
+        }
+
+        cursor = result
+            .get("nextCursor")
+            .and_then(|c| c.as_str())
+            .map(String::from);
+        if cursor.is_none() {
+            break;
+        }
+    }
+
+    Ok(items)
+}
+
+async fn run_probe(
+    config: &Value,
+    transport: ProbeTransport,
+    stderr_tail: &StderrTail,
+    stage: &mut ProbeStage,
+    started: Instant,
+) -> Result<McpServerReport, ProbeError> {
+    *stage = ProbeStage::Connect;
+    let mut connection = match transport {
+        ProbeTransport::Stdio => Connection::Stdio(spawn_stdio(config, stderr_tail)?),
+        ProbeTransport::Http => Connection::Http(HttpConnection {
+            client: reqwest::Client::new(),
+            url: server_url(config)?,
+            headers: header_map(config).map_err(|e| ProbeError::new(ProbeStage::Config, e))?,
+            session_id: None,
+        }),
+        ProbeTransport::Sse => Connection::Sse(
+            SseConnection::connect(
+                reqwest::Client::new(),
+                server_url(config)?,
+                header_map(config).map_err(|e| ProbeError::new(ProbeStage::Config, e))?,
+            )
+            .await
+            .map_err(|e| ProbeError::new(ProbeStage::Connect, e))?,
+        ),
+    };
+
+    *stage = ProbeStage::Initialize;
+    let mut next_id = 1;
+    let params = json!({
+        "protocolVersion": PROTOCOL_VERSION,
+        "capabilities": {},
+        "clientInfo": { "name": "ccfoundation", "version": env!("CARGO_PKG_VERSION") }
+    });
+    let response = match connection.request(next_id, "initialize", params).await {
+        Ok(response) => response,
+        Err(e) => return Err(connection.fail(ProbeStage::Initialize, e).await),
+    };
+    let init = rpc_result(response).map_err(|e| {
+        ProbeError::new(
+            ProbeStage::Initialize,
+            format!("initialize failed: {}", e.message),
+        )
+    })?;
+
+    if let Err(e) = connection.notify("notifications/initialized").await {
+        return Err(connection.fail(ProbeStage::Initialize, e).await);
+    }
+
+    *stage = ProbeStage::List;
+    let capabilities = init.get("capabilities").cloned().unwrap_or(json!({}));
+    let advertises = |name: &str| capabilities.get(name).is_some();
+
+    let tools = if advertises("tools") {
+        list_all(&mut connection, &mut next_id, "tools/list", "tools").await?
+    } else {
+        vec![]
+    };
+    let prompts = if advertises("prompts") {
+        list_all(&mut connection, &mut next_id, "prompts/list", "prompts").await?
+    } else {
+        vec![]
+    };
+    let resources = if advertises("resources") {
+        list_all(&mut connection, &mut next_id, "resources/list", "resources").await?
+    } else {
+        vec![]
+    };
+
+    Ok(McpServerReport {
+        transport,
+        protocol_version: init
+            .get("protocolVersion")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_string(),
+        server_info: init.get("serverInfo").cloned().unwrap_or(json!({})),
+        instructions: init
+            .get("instructions")
+            .and_then(|v| v.as_str())
+            .map(String::from),
+        capabilities,
+        tools,
+        prompts,
+        resources,
+        duration_ms: started.elapsed().as_millis() as u64,
+    })
+}
+
+/// Start or connect to an MCP server, handshake and list what it offers.
+/// A stdio server is killed once the probe finishes or times out.
+pub async fn probe_server(config: &Value, timeout: Duration) -> McpProbeResult {
+    let started = Instant::now();
+    let stderr_tail: StderrTail = Arc::new(Mutex::new(VecDeque::new()));
+
+    let transport = match detect_transport(config) {
+        Ok(transport) => transport,
+        Err(message) => {
+            return McpProbeResult::Failed(McpProbeFailure {
+                transport: None,
+                stage: ProbeStage::Config,
+                timed_out: false,
+                message,
+                stderr_tail: vec![],
+                exit_code: None,
+                duration_ms: 0,
+            })
+        }
+    };
+
+    let mut stage = ProbeStage::Config;
+    let outcome = tokio::time::timeout(
+        timeout,
+        run_probe(config, transport, &stderr_tail, &mut stage, started),
+    )
+    .await;
+
+    let (error, timed_out) = match outcome {
+        Ok(Ok(report)) => return McpProbeResult::Ok(report),
+        Ok(Err(error)) => (error, false),
+        Err(_) => (
+            ProbeError::new(
+                stage,
+                format!("No response within {} seconds", timeout.as_secs_f32()),
+            ),
+            true,
+        ),
+    };
+
+    // Let the stderr reader catch up with a server that just exited
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let stderr_tail = stderr_tail
+        .lock()
+        .map(|tail| tail.iter().cloned().collect())
+        .unwrap_or_default();
+
+    McpProbeResult::Failed(McpProbeFailure {
+        transport: Some(transport),
+        stage: error.stage,
+        timed_out,
+        message: error.message,
+        stderr_tail,
+        exit_code: error.exit_code,
+        duration_ms: started.elapsed().as_millis() as u64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn probe(config: Value, timeout: Duration) -> McpProbeResult {
+        tauri::async_runtime::block_on(probe_server(&config, timeout))
+    }
+
+    #[test]
+    fn test_sse_parser_handles_split_chunks() {
+        let mut parser = SseParser::default();
+
+        assert!(parser.push(b"event: endpoint\r\nda").is_empty());
+        let events = parser.push(b"ta: /messages?session=1\r\n\r\ndata: {\"a\":1}\n\n");
+
+        assert_eq!(
+            events,
+            vec![
+                SseEvent {
+                    event: "endpoint".into(),
+                    data: "/messages?session=1".into()
+                },
+                SseEvent {
+                    event: "message".into(),
+                    data: "{\"a\":1}".into()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_detect_transport() {
+        assert_eq!(
+            detect_transport(&json!({ "command": "npx" })),
+            Ok(ProbeTransport::Stdio)
+        );
+        assert_eq!(
+            detect_transport(&json!({ "url": "http://x" })),
+            Ok(ProbeTransport::Http)
+        );
+        assert_eq!(
+            detect_transport(&json!({ "type": "sse", "url": "http://x" })),
+            Ok(ProbeTransport::Sse)
+        );
+        assert!(detect_transport(&json!({})).is_err());
+    }
+
+    /// A tiny stdio MCP server: answers by method, echoing the request id
+    #[cfg(unix)]
+    const FAKE_STDIO_SERVER: &str = r#"
+echo "fake server starting" >&2
+while IFS= read -r line; do
+  id=$(printf '%s' "$line" | sed -n 's/.*"id":\([0-9]*\).*/\1/p')
+  case "$line" in
+    *'"method":"initialize"'*)
+      printf '{"jsonrpc":"2.0","id":%s,"result":{"protocolVersion":"2025-06-18","capabilities":{"tools":{},"prompts":{}},"serverInfo":{"name":"fake","version":"1.0.0"}}}\n' "$id" ;;
+    *'"method":"tools/list"'*'"cursor"'*)
+      printf '{"jsonrpc":"2.0","id":%s,"result":{"tools":[{"name":"second","inputSchema":{"type":"object"}}]}}\n' "$id" ;;
+    *'"method":"tools/list"'*)
+      echo "not json-rpc"
+      printf '{"jsonrpc":"2.0","id":%s,"result":{"tools":[{"name":"first","inputSchema":{"type":"object"}}],"nextCursor":"2"}}\n' "$id" ;;
+    *'"method":"prompts/list"'*)
+      printf '{"jsonrpc":"2.0","id":%s,"error":{"code":-32601,"message":"Method not found"}}\n' "$id" ;;
+  esac
+done
+"#;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_probe_stdio_server_lists_tools() {
+        let result = probe(
+            json!({ "command": "sh", "args": ["-c", FAKE_STDIO_SERVER] }),
+            Duration::from_secs(10),
+        );
+
+        let McpProbeResult::Ok(report) = result else {
+            panic!("probe failed: {:?}", result);
+        };
+        assert_eq!(report.transport, ProbeTransport::Stdio);
+        assert_eq!(report.server_info["name"], "fake");
+        let names: Vec<&str> = report
+            .tools
+            .iter()
+            .filter_map(|t| t["name"].as_str())
+            .collect();
+        assert_eq!(names, vec!["first", "second"]);
+        assert!(report.prompts.is_empty());
+        assert!(report.resources.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_probe_stdio_server_that_crashes() {
+        let result = probe(
+            json!({ "command": "sh", "args": ["-c", "echo 'missing API key' >&2; exit 3"] }),
+            Duration::from_secs(10),
+        );
+
+        let McpProbeResult::Failed(failure) = result else {
+            panic!("probe should fail");
+        };
+        assert_eq!(failure.stage, ProbeStage::Initialize);
+        assert!(!failure.timed_out);
+        assert_eq!(failure.exit_code, Some(3));
+        assert_eq!(failure.stderr_tail, vec!["missing API key"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_probe_times_out() {
+        let result = probe(
+            json!({ "command": "sh", "args": ["-c", "sleep 5"] }),
+            Duration::from_millis(300),
+        );
+
+        let McpProbeResult::Failed(failure) = result else {
+            panic!("probe should time out");
+        };
+        assert!(failure.timed_out);
+        assert_eq!(failure.stage, ProbeStage::Initialize);
+    }
+
+    #[test]
+    fn test_probe_http_server() {
+        use axum::{routing::post, Json, Router};
+
+        async fn handle(Json(message): Json<Value>) -> axum::response::Response {
+            use axum::response::IntoResponse;
+
+            let id = message.get("id").cloned();
+            let result = match message["method"].as_str() {
+                Some("initialize") => json!({
+                    "protocolVersion": "2025-06-18",
+                    "capabilities": { "resources": {} },
+                    "serverInfo": { "name": "fake-http", "version": "0.1.0" }
+                }),
+                Some("resources/list") => {
+                    json!({ "resources": [{ "uri": "file:///a", "name": "a" }] })
+                }
+                _ => return axum::http::StatusCode::ACCEPTED.into_response(),
+            };
+            (
+                [(SESSION_HEADER, "session-1")],
+                Json(json!({ "jsonrpc": "2.0", "id": id, "result": result })),
+            )
+                .into_response()
+        }
+
+        let result = tauri::async_runtime::block_on(async {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                let app = Router::new().route("/mcp", post(handle));
+                axum::serve(listener, app).await.unwrap();
+            });
+
+            let config = json!({ "type": "http", "url": format!("http://{}/mcp", addr) });
+            probe_server(&config, Duration::from_secs(10)).await
+        });
+
+        let McpProbeResult::Ok(report) = result else {
+            panic!("probe failed: {:?}", result);
+        };
+        assert_eq!(report.transport, ProbeTransport::Http);
+        assert_eq!(report.server_info["name"], "fake-http");
+        assert_eq!(report.resources.len(), 1);
+        assert!(report.tools.is_empty());
+    }
+}
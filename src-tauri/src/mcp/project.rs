@@ -0,0 +1,223 @@
+//! Project-scoped MCP servers in PROJECT/.mcp.json
+//!
+//! Earlier versions wrote a bare server map to PROJECT/.claude/.mcp.json,
+//! which Claude Code never reads. `migrate_legacy` folds those files
+//! into the real location.
+
+use crate::managed_file;
+use serde_json::{Map, Value};
+use std::path::{Path, PathBuf};
+
+pub fn mcp_path(project_path: &str) -> PathBuf {
+    PathBuf::from(project_path).join(".mcp.json")
+}
+
+/// Where the app used to keep project servers
+pub fn legacy_mcp_path(project_path: &str) -> PathBuf {
+    PathBuf::from(project_path)
+        .join(".claude")
+        .join(".mcp.json")
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct McpMigrationConflict {
+    pub name: String,
+    /// Definition kept in PROJECT/.mcp.json
+    pub current: Value,
+    /// Definition left behind in the legacy file
+    pub legacy: Value,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct McpMigrationReport {
+    /// Servers copied into PROJECT/.mcp.json
+    pub migrated: Vec<String>,
+    /// Servers already present with an identical definition
+    pub unchanged: Vec<String>,
+    /// Servers defined differently in both files; the legacy file keeps these
+    pub conflicts: Vec<McpMigrationConflict>,
+    #[serde(rename = "legacyRemoved")]
+    pub legacy_removed: bool,
+}
+
+/// Server map of a PROJECT/.mcp.json document
+pub fn servers_of(json: Value) -> Map<String, Value> {
+    match json {
+        Value::Object(mut obj) => match obj.remove("mcpServers") {
+            Some(Value::Object(servers)) => servers,
+            _ => Map::new(),
+        },
+        _ => Map::new(),
+    }
+}
+
+/// Server map of a legacy PROJECT/.claude/.mcp.json, which holds the bare map
+fn legacy_servers_of(json: Value) -> Map<String, Value> {
+    match json {
+        Value::Object(obj) if obj.contains_key("mcpServers") => servers_of(Value::Object(obj)),
+        Value::Object(obj) => obj,
+        _ => Map::new(),
+    }
+}
+
+fn read_json(path: &Path) -> Result<Option<Value>, String> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let content =
+        std::fs::read_to_string(path).map_err(|e| format!("Failed to read project MCP: {}", e))?;
+    let json: Value = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse project MCP: {}", e))?;
+
+    Ok(Some(json))
+}
+
+pub fn read_servers(path: &Path) -> Result<Option<Map<String, Value>>, String> {
+    Ok(read_json(path)?.map(servers_of))
+}
+
+pub fn read_legacy_servers(path: &Path) -> Result<Option<Map<String, Value>>, String> {
+    Ok(read_json(path)?.map(legacy_servers_of))
+}
+
+/// Replace the servers in PROJECT/.mcp.json, keeping any other top-level keys
+pub fn write_servers(path: &Path, servers: Map<String, Value>) -> Result<(), String> {
+    managed_file::update_json(path, |json| {
+        let obj = json
+            .as_object_mut()
+            .ok_or("Invalid .mcp.json: root is not an object")?;
+        obj.insert("mcpServers".to_string(), Value::Object(servers.clone()));
+        Ok(())
+    })
+    .map_err(|e| format!("Failed to write MCP: {}", e))
+}
+
+/// Merge the legacy file into PROJECT/.mcp.json. Conflicting servers stay
+/// behind in the legacy file; it is removed once nothing is left in it.
+pub fn migrate_legacy(project_path: &str) -> Result<McpMigrationReport, String> {
+    let legacy_path = legacy_mcp_path(project_path);
+    let Some(legacy) = read_legacy_servers(&legacy_path)? else {
+        return Ok(McpMigrationReport::default());
+    };
+
+    let path = mcp_path(project_path);
+    let mut servers = read_servers(&path)?.unwrap_or_default();
+    let mut report = McpMigrationReport::default();
+    let mut remaining = Map::new();
+
+    for (name, config) in legacy {
+        match servers.get(&name) {
+            None => {
+                servers.insert(name.clone(), config);
+                report.migrated.push(name);
+            }
+            Some(current) if *current == config => report.unchanged.push(name),
+            Some(current) => {
+                report.conflicts.push(McpMigrationConflict {
+                    name: name.clone(),
+                    current: current.clone(),
+                    legacy: config.clone(),
+                });
+                remaining.insert(name, config);
+            }
+        }
+    }
+
+    if !report.migrated.is_empty() {
+        write_servers(&path, servers)?;
+    }
+
+    if remaining.is_empty() {
+        std::fs::remove_file(&legacy_path)
+            .map_err(|e| format!("Failed to remove legacy MCP file: {}", e))?;
+        report.legacy_removed = true;
+    } else {
+        let content = serde_json::to_string_pretty(&remaining)
+            .map_err(|e| format!("Failed to serialize MCP: {}", e))?;
+        managed_file::write(&legacy_path, &content)?;
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_write_servers_keeps_other_keys() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(".mcp.json");
+        std::fs::write(&path, r#"{"$schema":"x","mcpServers":{"old":{}}}"#).unwrap();
+
+        let mut servers = Map::new();
+        servers.insert("new".to_string(), json!({ "command": "new" }));
+        write_servers(&path, servers).unwrap();
+
+        let json: Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(json["$schema"], "x");
+        assert_eq!(json["mcpServers"], json!({ "new": { "command": "new" } }));
+    }
+
+    #[test]
+    fn test_read_servers_ignores_other_root_keys() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(".mcp.json");
+        std::fs::write(&path, r#"{"$schema":"x"}"#).unwrap();
+
+        assert!(read_servers(&path).unwrap().unwrap().is_empty());
+        assert_eq!(read_legacy_servers(&path).unwrap().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_migrate_legacy_reports_conflicts() {
+        let dir = tempdir().unwrap();
+        let project = dir.path().to_string_lossy().to_string();
+
+        std::fs::create_dir_all(dir.path().join(".claude")).unwrap();
+        std::fs::write(
+            legacy_mcp_path(&project),
+            json!({
+                "fresh": { "command": "fresh" },
+                "same": { "command": "same" },
+                "clash": { "command": "legacy" }
+            })
+            .to_string(),
+        )
+        .unwrap();
+        std::fs::write(
+            mcp_path(&project),
+            json!({ "mcpServers": {
+                "same": { "command": "same" },
+                "clash": { "command": "current" }
+            }})
+            .to_string(),
+        )
+        .unwrap();
+
+        let report = migrate_legacy(&project).unwrap();
+        assert_eq!(report.migrated, vec!["fresh"]);
+        assert_eq!(report.unchanged, vec!["same"]);
+        assert_eq!(report.conflicts.len(), 1);
+        assert_eq!(report.conflicts[0].legacy, json!({ "command": "legacy" }));
+        assert!(!report.legacy_removed);
+
+        let servers = read_servers(&mcp_path(&project)).unwrap().unwrap();
+        assert_eq!(servers.len(), 3);
+        assert_eq!(servers["clash"], json!({ "command": "current" }));
+
+        let legacy = read_legacy_servers(&legacy_mcp_path(&project))
+            .unwrap()
+            .unwrap();
+        assert_eq!(legacy.keys().collect::<Vec<_>>(), vec!["clash"]);
+
+        // Resolving the conflict by hand and migrating again clears the old file
+        std::fs::write(legacy_mcp_path(&project), "{}").unwrap();
+        let report = migrate_legacy(&project).unwrap();
+        assert!(report.legacy_removed);
+        assert!(!legacy_mcp_path(&project).exists());
+    }
+}
@@ -0,0 +1,576 @@
+//! Which MCP servers Claude Code would load for a project
+//!
+//! Servers come from four places. In order of precedence they are:
+//! managed-mcp.json, the project's entry in ~/.claude.json (local scope),
+//! PROJECT/.mcp.json (project scope) and the top-level `mcpServers` in
+//! ~/.claude.json (user scope). When two scopes define the same name, the
+//! higher scope wins. Policy in managed-settings.json can then block user and
+//! project servers by name, command or url.
+//!
+//! PROJECT/.mcp.json servers also need the user's approval, recorded in
+//! `enabledMcpjsonServers`, `disabledMcpjsonServers` and
+//! `enableAllProjectMcpServers`. Unapproved ones and servers disabled in this
+//! app don't take part in picking the winner of a name.
+
+use super::project;
+use super::stash;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+#[derive(
+    serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum McpScope {
+    Managed,
+    Local,
+    Project,
+    User,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct ResolvedMcpServer {
+    pub name: String,
+    pub scope: McpScope,
+    pub config: Value,
+    /// File the definition was read from
+    pub source: String,
+    /// True when this definition wins its name and isn't blocked
+    pub active: bool,
+    /// Scope of the definition that wins over this one
+    #[serde(rename = "shadowedBy", skip_serializing_if = "Option::is_none")]
+    pub shadowed_by: Option<McpScope>,
+    /// Why managed policy stops Claude Code from loading the server
+    #[serde(rename = "blockedReason", skip_serializing_if = "Option::is_none")]
+    pub blocked_reason: Option<String>,
+    /// Why Claude Code won't load this definition, set whenever `active` is false
+    #[serde(rename = "excludedReason", skip_serializing_if = "Option::is_none")]
+    pub excluded_reason: Option<String>,
+}
+
+/// Directory holding managed-settings.json and managed-mcp.json
+pub fn managed_dir() -> PathBuf {
+    if cfg!(target_os = "macos") {
+        PathBuf::from("/Library/Application Support/ClaudeCode")
+    } else if cfg!(target_os = "windows") {
+        PathBuf::from("C:\\ProgramData\\ClaudeCode")
+    } else {
+        PathBuf::from("/etc/claude-code")
+    }
+}
+
+fn read_json(path: &Path) -> Result<Option<Value>, String> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let json = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+
+    Ok(Some(json))
+}
+
+/// The servers under `mcpServers` in a config file, before resolution
+fn definitions(scope: McpScope, path: &Path, json: Option<&Value>) -> Vec<ResolvedMcpServer> {
+    let Some(servers) = json
+        .and_then(|j| j.get("mcpServers"))
+        .and_then(|s| s.as_object())
+    else {
+        return vec![];
+    };
+
+    servers
+        .iter()
+        .map(|(name, config)| ResolvedMcpServer {
+            name: name.clone(),
+            scope,
+            config: config.clone(),
+            source: path.to_string_lossy().to_string(),
+            active: false,
+            shadowed_by: None,
+            blocked_reason: None,
+            excluded_reason: None,
+        })
+        .collect()
+}
+
+/// The .mcp.json approval settings, combined from every file Claude Code reads
+/// them from
+#[derive(Debug, Default)]
+struct McpjsonApprovals {
+    enable_all: bool,
+    enabled: Vec<String>,
+    disabled: Vec<String>,
+}
+
+impl McpjsonApprovals {
+    fn add(&mut self, settings: Option<&Value>) {
+        let Some(settings) = settings else {
+            return;
+        };
+        let names = |key: &str| -> Vec<String> {
+            settings
+                .get(key)
+                .and_then(|v| v.as_array())
+                .map(|names| {
+                    names
+                        .iter()
+                        .filter_map(|n| n.as_str().map(String::from))
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+
+        self.enable_all |= settings
+            .get("enableAllProjectMcpServers")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        self.enabled.extend(names("enabledMcpjsonServers"));
+        self.disabled.extend(names("disabledMcpjsonServers"));
+    }
+
+    /// Why a .mcp.json server won't start, or None once it is approved
+    fn exclusion(&self, name: &str) -> Option<String> {
+        let name = name.to_string();
+        if self.disabled.contains(&name) {
+            Some("Rejected in disabledMcpjsonServers".to_string())
+        } else if self.enable_all || self.enabled.contains(&name) {
+            None
+        } else {
+            Some(
+                "Not approved yet in enabledMcpjsonServers or enableAllProjectMcpServers"
+                    .to_string(),
+            )
+        }
+    }
+}
+
+/// Servers parked by `stash::disable_in` that belong to this resolution
+fn disabled_definitions(
+    home: &Path,
+    project_path: Option<&str>,
+) -> Result<Vec<ResolvedMcpServer>, String> {
+    let source = stash::stash_path(home).to_string_lossy().to_string();
+
+    Ok(stash::list_disabled_in(home, None)?
+        .into_iter()
+        .filter(|server| match server.location.scope {
+            McpScope::User => true,
+            _ => project_path.is_some() && server.location.project_path.as_deref() == project_path,
+        })
+        .map(|server| ResolvedMcpServer {
+            name: server.name,
+            scope: server.location.scope,
+            config: server.config,
+            source: source.clone(),
+            active: false,
+            shadowed_by: None,
+            blocked_reason: None,
+            excluded_reason: Some("Disabled in this app".to_string()),
+        })
+        .collect())
+}
+
+/// `*` matches any run of characters, everything else matches literally
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == text;
+    }
+
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if !text.starts_with(first) || text.len() < first.len() + last.len() {
+        return false;
+    }
+
+    let mut rest = &text[first.len()..text.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(pos) => rest = &rest[pos + part.len()..],
+            None => return false,
+        }
+    }
+
+    text.ends_with(last)
+}
+
+/// Command line of a stdio server, as compared by `serverCommand` entries
+fn command_line(config: &Value) -> Option<Vec<String>> {
+    let command = config.get("command")?.as_str()?;
+    let mut line = vec![command.to_string()];
+    if let Some(args) = config.get("args").and_then(|a| a.as_array()) {
+        line.extend(args.iter().filter_map(|a| a.as_str().map(String::from)));
+    }
+    Some(line)
+}
+
+/// Whether an `allowedMcpServers`/`deniedMcpServers` entry covers a server
+fn policy_entry_matches(entry: &Value, name: &str, config: &Value) -> bool {
+    if let Some(server_name) = entry.get("serverName").and_then(|n| n.as_str()) {
+        return server_name == name;
+    }
+
+    if let Some(command) = entry.get("serverCommand").and_then(|c| c.as_array()) {
+        let command: Vec<String> = command
+            .iter()
+            .filter_map(|a| a.as_str().map(String::from))
+            .collect();
+        return command_line(config).as_ref() == Some(&command);
+    }
+
+    if let Some(pattern) = entry.get("serverUrl").and_then(|u| u.as_str()) {
+        return config
+            .get("url")
+            .and_then(|u| u.as_str())
+            .is_some_and(|url| wildcard_match(pattern, url));
+    }
+
+    false
+}
+
+fn blocked_reason(
+    managed_settings: Option<&Value>,
+    exclusive_managed: bool,
+    scope: McpScope,
+    name: &str,
+    config: &Value,
+) -> Option<String> {
+    // Administrators' own servers aren't subject to their allow and deny lists
+    if scope == McpScope::Managed {
+        return None;
+    }
+    if exclusive_managed {
+        return Some("managed-mcp.json takes exclusive control of MCP servers".to_string());
+    }
+
+    let settings = managed_settings?;
+
+    // The denylist wins over the allowlist
+    let denied = settings
+        .get("deniedMcpServers")
+        .and_then(|d| d.as_array())
+        .is_some_and(|entries| {
+            entries
+                .iter()
+                .any(|e| policy_entry_matches(e, name, config))
+        });
+    if denied {
+        return Some("Listed in deniedMcpServers".to_string());
+    }
+
+    // A missing allowlist allows everything; an empty one allows nothing
+    if let Some(allowed) = settings.get("allowedMcpServers").and_then(|a| a.as_array()) {
+        if !allowed
+            .iter()
+            .any(|e| policy_entry_matches(e, name, config))
+        {
+            return Some("Not listed in allowedMcpServers".to_string());
+        }
+    }
+
+    None
+}
+
+/// Every MCP server definition visible to `project_path`, sorted by name and
+/// then precedence, with the winner of each name marked active
+pub fn resolve_in(
+    home: &Path,
+    managed_dir: &Path,
+    project_path: Option<&str>,
+) -> Result<Vec<ResolvedMcpServer>, String> {
+    let claude_json_path = home.join(".claude.json");
+    let managed_mcp_path = managed_dir.join("managed-mcp.json");
+
+    let claude_json = read_json(&claude_json_path)?;
+    let managed_mcp = read_json(&managed_mcp_path)?;
+    let managed_settings = read_json(&managed_dir.join("managed-settings.json"))?;
+
+    let mut resolved = definitions(McpScope::Managed, &managed_mcp_path, managed_mcp.as_ref());
+
+    let mut approvals = McpjsonApprovals::default();
+    approvals.add(managed_settings.as_ref());
+    approvals.add(read_json(&home.join(".claude/settings.json"))?.as_ref());
+
+    if let Some(project_path) = project_path {
+        let local = claude_json
+            .as_ref()
+            .and_then(|j| j.get("projects"))
+            .and_then(|p| p.get(project_path));
+        resolved.extend(definitions(McpScope::Local, &claude_json_path, local));
+
+        let project_mcp_path = project::mcp_path(project_path);
+        let project_mcp = read_json(&project_mcp_path)?;
+        let mut project_servers =
+            definitions(McpScope::Project, &project_mcp_path, project_mcp.as_ref());
+
+        approvals.add(local);
+        let claude_dir = Path::new(project_path).join(".claude");
+        approvals.add(read_json(&claude_dir.join("settings.json"))?.as_ref());
+        approvals.add(read_json(&claude_dir.join("settings.local.json"))?.as_ref());
+        for server in &mut project_servers {
+            server.excluded_reason = approvals.exclusion(&server.name);
+        }
+        resolved.extend(project_servers);
+    }
+
+    resolved.extend(definitions(
+        McpScope::User,
+        &claude_json_path,
+        claude_json.as_ref(),
+    ));
+    resolved.extend(disabled_definitions(home, project_path)?);
+
+    let exclusive_managed = managed_mcp.is_some();
+    resolved.sort_by(|a, b| a.name.cmp(&b.name).then(a.scope.cmp(&b.scope)));
+
+    let mut winner: Option<(String, McpScope)> = None;
+    for server in &mut resolved {
+        server.blocked_reason = blocked_reason(
+            managed_settings.as_ref(),
+            exclusive_managed,
+            server.scope,
+            &server.name,
+            &server.config,
+        );
+
+        // Disabled and unapproved definitions never load, so they can't win a name
+        if server.excluded_reason.is_some() {
+            continue;
+        }
+
+        match &winner {
+            Some((name, scope)) if *name == server.name => {
+                server.shadowed_by = Some(*scope);
+                server.excluded_reason = Some(format!(
+                    "Overridden by the {} scope",
+                    format!("{:?}", scope).to_lowercase()
+                ));
+            }
+            _ => {
+                winner = Some((server.name.clone(), server.scope));
+                server.active = server.blocked_reason.is_none();
+                server.excluded_reason = server.blocked_reason.clone();
+            }
+        }
+    }
+
+    Ok(resolved)
+}
+
+/// Resolve against the real home and managed policy directories
+pub fn resolve(project_path: Option<&str>) -> Result<Vec<ResolvedMcpServer>, String> {
+    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
+    resolve_in(&home_dir, &managed_dir(), project_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    fn write_json(path: &Path, value: Value) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, serde_json::to_string(&value).unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_resolve_in_precedence() {
+        let dir = tempdir().unwrap();
+        let home = dir.path().join("home");
+        let managed = dir.path().join("managed");
+        let project = dir.path().join("project");
+        let project_path = project.to_string_lossy().to_string();
+
+        write_json(
+            &home.join(".claude.json"),
+            json!({
+                "mcpServers": {
+                    "github": { "command": "user-github" },
+                    "notes": { "command": "notes" }
+                },
+                "projects": {
+                    project_path.clone(): {
+                        "mcpServers": { "github": { "command": "local-github" } }
+                    }
+                }
+            }),
+        );
+        write_json(
+            &project.join(".mcp.json"),
+            json!({ "mcpServers": { "github": { "command": "project-github" } } }),
+        );
+        write_json(
+            &project.join(".claude/settings.local.json"),
+            json!({ "enableAllProjectMcpServers": true }),
+        );
+
+        let resolved = resolve_in(&home, &managed, Some(&project_path)).unwrap();
+
+        let github: Vec<_> = resolved.iter().filter(|s| s.name == "github").collect();
+        assert_eq!(github.len(), 3);
+        assert_eq!(github[0].scope, McpScope::Local);
+        assert!(github[0].active);
+        assert_eq!(github[1].scope, McpScope::Project);
+        assert_eq!(github[1].shadowed_by, Some(McpScope::Local));
+        assert_eq!(
+            github[1].excluded_reason.as_deref(),
+            Some("Overridden by the local scope")
+        );
+        assert!(!github[2].active);
+
+        let notes = resolved.iter().find(|s| s.name == "notes").unwrap();
+        assert_eq!(notes.scope, McpScope::User);
+        assert!(notes.active);
+
+        // Without a project only user scope is visible
+        let user_only = resolve_in(&home, &managed, None).unwrap();
+        assert_eq!(user_only.len(), 2);
+        assert!(user_only.iter().all(|s| s.active));
+    }
+
+    #[test]
+    fn test_resolve_in_policy() {
+        let dir = tempdir().unwrap();
+        let home = dir.path().join("home");
+        let managed = dir.path().join("managed");
+
+        write_json(
+            &home.join(".claude.json"),
+            json!({
+                "mcpServers": {
+                    "docs": { "type": "http", "url": "https://docs.example.com/mcp" },
+                    "fs": { "command": "npx", "args": ["-y", "fs-server"] },
+                    "other": { "command": "other" }
+                }
+            }),
+        );
+        write_json(
+            &managed.join("managed-settings.json"),
+            json!({
+                "allowedMcpServers": [
+                    { "serverUrl": "https://*.example.com/*" },
+                    { "serverCommand": ["npx", "-y", "fs-server"] },
+                    { "serverName": "other" }
+                ],
+                "deniedMcpServers": [{ "serverName": "other" }]
+            }),
+        );
+
+        let resolved = resolve_in(&home, &managed, None).unwrap();
+        let by_name = |name: &str| resolved.iter().find(|s| s.name == name).unwrap();
+
+        assert!(by_name("docs").active);
+        assert!(by_name("fs").active);
+        assert_eq!(
+            by_name("other").blocked_reason.as_deref(),
+            Some("Listed in deniedMcpServers")
+        );
+
+        // managed-mcp.json takes over entirely
+        write_json(
+            &managed.join("managed-mcp.json"),
+            json!({ "mcpServers": { "corp": { "command": "corp" } } }),
+        );
+        let resolved = resolve_in(&home, &managed, None).unwrap();
+        let active: Vec<_> = resolved.iter().filter(|s| s.active).collect();
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].name, "corp");
+        assert_eq!(active[0].scope, McpScope::Managed);
+    }
+
+    #[test]
+    fn test_resolve_in_applies_approvals_and_disabled_servers() {
+        let dir = tempdir().unwrap();
+        let home = dir.path().join("home");
+        let managed = dir.path().join("managed");
+        let project = dir.path().join("project");
+        let project_path = project.to_string_lossy().to_string();
+
+        write_json(
+            &home.join(".claude.json"),
+            json!({ "mcpServers": { "db": { "command": "user-db" } } }),
+        );
+        write_json(
+            &home.join(".claude/settings.json"),
+            json!({ "disabledMcpjsonServers": ["shell"] }),
+        );
+        write_json(
+            &project.join(".mcp.json"),
+            json!({
+                "mcpServers": {
+                    "db": { "command": "project-db" },
+                    "docs": { "command": "docs" },
+                    "shell": { "command": "shell" }
+                }
+            }),
+        );
+        write_json(
+            &project.join(".claude/settings.json"),
+            json!({ "enabledMcpjsonServers": ["docs", "shell"] }),
+        );
+        write_json(
+            &stash::stash_path(&home),
+            json!({
+                "servers": [{
+                    "name": "notes",
+                    "location": { "scope": "user" },
+                    "config": { "command": "notes" },
+                    "disabledAt": 1
+                }]
+            }),
+        );
+
+        let resolved = resolve_in(&home, &managed, Some(&project_path)).unwrap();
+        let find = |name: &str, scope: McpScope| {
+            resolved
+                .iter()
+                .find(|s| s.name == name && s.scope == scope)
+                .unwrap()
+        };
+
+        assert!(find("docs", McpScope::Project).active);
+        assert_eq!(
+            find("shell", McpScope::Project).excluded_reason.as_deref(),
+            Some("Rejected in disabledMcpjsonServers")
+        );
+
+        // The unapproved project server doesn't hide the user one
+        let project_db = find("db", McpScope::Project);
+        assert!(!project_db.active);
+        assert!(project_db
+            .excluded_reason
+            .as_deref()
+            .unwrap()
+            .starts_with("Not approved"));
+        let user_db = find("db", McpScope::User);
+        assert!(user_db.active);
+        assert_eq!(user_db.shadowed_by, None);
+
+        let notes = find("notes", McpScope::User);
+        assert!(!notes.active);
+        assert_eq!(
+            notes.excluded_reason.as_deref(),
+            Some("Disabled in this app")
+        );
+        assert!(resolved
+            .iter()
+            .all(|s| s.active == s.excluded_reason.is_none()));
+    }
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match(
+            "https://*.example.com/*",
+            "https://a.example.com/mcp"
+        ));
+        assert!(!wildcard_match(
+            "https://*.example.com/*",
+            "https://example.org/mcp"
+        ));
+        assert!(wildcard_match("exact", "exact"));
+        assert!(!wildcard_match("a*a", "a"));
+    }
+}
@@ -0,0 +1,519 @@
+//! Typed MCP server definitions and the checks run before saving one
+//!
+//! Definitions are kept as JSON everywhere else, so parsing goes through
+//! `McpServerConfig::from_value` and back through `to_value`. Fields this
+//! app doesn't model are carried along untouched.
+//!
+//! Only a malformed definition stops a save. Whether the command is on PATH
+//! and the `${VAR}` references are set is judged from this app's environment,
+//! which can differ from the shell Claude Code runs in (an app opened from
+//! Finder doesn't get the shell's PATH), so those checks come back as
+//! warnings.
+
+use crate::claude_settings::{SettingsError, Validation, ValidationError};
+use reqwest::header::{HeaderName, HeaderValue};
+use reqwest::Url;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum McpTransport {
+    Stdio {
+        command: String,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        args: Vec<String>,
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        env: BTreeMap<String, String>,
+    },
+    Sse {
+        url: String,
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        headers: BTreeMap<String, String>,
+    },
+    Http {
+        url: String,
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        headers: BTreeMap<String, String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct McpServerConfig {
+    pub transport: McpTransport,
+    /// Fields outside the transport, e.g. `timeout` or client-specific keys
+    pub extra: Map<String, Value>,
+}
+
+const TRANSPORT_KEYS: [&str; 6] = ["type", "command", "args", "env", "url", "headers"];
+
+fn error_at(path: &str, message: impl Into<String>) -> ValidationError {
+    ValidationError {
+        path: path.to_string(),
+        message: message.into(),
+    }
+}
+
+fn string_field(
+    obj: &Map<String, Value>,
+    key: &str,
+    path: &str,
+    errors: &mut Vec<ValidationError>,
+) -> Option<String> {
+    let field_path = format!("{}.{}", path, key);
+    match obj.get(key) {
+        Some(Value::String(s)) if !s.trim().is_empty() => Some(s.clone()),
+        Some(Value::String(_)) | None => {
+            errors.push(error_at(&field_path, format!("\"{}\" is required", key)));
+            None
+        }
+        Some(_) => {
+            errors.push(error_at(&field_path, "Expected a string"));
+            None
+        }
+    }
+}
+
+fn string_list(
+    obj: &Map<String, Value>,
+    key: &str,
+    path: &str,
+    errors: &mut Vec<ValidationError>,
+) -> Vec<String> {
+    let field_path = format!("{}.{}", path, key);
+    let Some(value) = obj.get(key) else {
+        return vec![];
+    };
+    let Some(items) = value.as_array() else {
+        errors.push(error_at(&field_path, "Expected an array of strings"));
+        return vec![];
+    };
+
+    items
+        .iter()
+        .enumerate()
+        .filter_map(|(i, item)| match item.as_str() {
+            Some(s) => Some(s.to_string()),
+            None => {
+                errors.push(error_at(
+                    &format!("{}[{}]", field_path, i),
+                    "Expected a string",
+                ));
+                None
+            }
+        })
+        .collect()
+}
+
+fn string_map(
+    obj: &Map<String, Value>,
+    key: &str,
+    path: &str,
+    errors: &mut Vec<ValidationError>,
+) -> BTreeMap<String, String> {
+    let field_path = format!("{}.{}", path, key);
+    let Some(value) = obj.get(key) else {
+        return BTreeMap::new();
+    };
+    let Some(entries) = value.as_object() else {
+        errors.push(error_at(&field_path, "Expected an object of strings"));
+        return BTreeMap::new();
+    };
+
+    entries
+        .iter()
+        .filter_map(|(name, value)| match value.as_str() {
+            Some(s) => Some((name.clone(), s.to_string())),
+            None => {
+                errors.push(error_at(
+                    &format!("{}.{}", field_path, name),
+                    "Expected a string",
+                ));
+                None
+            }
+        })
+        .collect()
+}
+
+fn check_url(url: &str, path: &str, errors: &mut Vec<ValidationError>) {
+    // Leave `${VAR}` urls to the environment check
+    if !env_refs(url).is_empty() {
+        return;
+    }
+
+    match Url::parse(url) {
+        Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => {}
+        Ok(parsed) => errors.push(error_at(
+            path,
+            format!("Unsupported URL scheme \"{}\"", parsed.scheme()),
+        )),
+        Err(e) => errors.push(error_at(path, format!("Invalid URL: {}", e))),
+    }
+}
+
+fn check_headers(
+    headers: &BTreeMap<String, String>,
+    path: &str,
+    errors: &mut Vec<ValidationError>,
+) {
+    for (name, value) in headers {
+        let header_path = format!("{}.headers.{}", path, name);
+        if HeaderName::from_bytes(name.as_bytes()).is_err() {
+            errors.push(error_at(&header_path, "Invalid header name"));
+        } else if env_refs(value).is_empty() && HeaderValue::from_str(value).is_err() {
+            errors.push(error_at(&header_path, "Invalid header value"));
+        }
+    }
+}
+
+impl McpServerConfig {
+    /// Parse a server definition, reporting every structural problem.
+    /// A definition without `type` is stdio if it has a command, like Claude Code assumes.
+    pub fn from_value(value: &Value, path: &str) -> Result<Self, Vec<ValidationError>> {
+        let Some(obj) = value.as_object() else {
+            return Err(vec![error_at(path, "Server must be a JSON object")]);
+        };
+
+        let mut errors = Vec::new();
+        let server_type = match obj.get("type") {
+            None if obj.contains_key("url") => {
+                errors.push(error_at(
+                    &format!("{}.type", path),
+                    "\"type\" is required for URL servers (\"http\" or \"sse\")",
+                ));
+                return Err(errors);
+            }
+            None => "stdio",
+            Some(Value::String(t)) => t.as_str(),
+            Some(_) => {
+                return Err(vec![error_at(
+                    &format!("{}.type", path),
+                    "Expected a string",
+                )])
+            }
+        };
+
+        let transport = match server_type {
+            "stdio" => {
+                let command = string_field(obj, "command", path, &mut errors);
+                let args = string_list(obj, "args", path, &mut errors);
+                let env = string_map(obj, "env", path, &mut errors);
+                command.map(|command| McpTransport::Stdio { command, args, env })
+            }
+            "sse" | "http" => {
+                let url = string_field(obj, "url", path, &mut errors);
+                let headers = string_map(obj, "headers", path, &mut errors);
+                if let Some(url) = &url {
+                    check_url(url, &format!("{}.url", path), &mut errors);
+                }
+                check_headers(&headers, path, &mut errors);
+                url.map(|url| match server_type {
+                    "sse" => McpTransport::Sse { url, headers },
+                    _ => McpTransport::Http { url, headers },
+                })
+            }
+            other => {
+                errors.push(error_at(
+                    &format!("{}.type", path),
+                    format!(
+                        "Unknown transport \"{}\", expected \"stdio\", \"sse\" or \"http\"",
+                        other
+                    ),
+                ));
+                None
+            }
+        };
+
+        match transport {
+            Some(transport) if errors.is_empty() => Ok(McpServerConfig {
+                transport,
+                extra: obj
+                    .iter()
+                    .filter(|(key, _)| !TRANSPORT_KEYS.contains(&key.as_str()))
+                    .map(|(key, value)| (key.clone(), value.clone()))
+                    .collect(),
+            }),
+            _ => Err(errors),
+        }
+    }
+
+    pub fn to_value(&self) -> Value {
+        let mut value = serde_json::to_value(&self.transport).unwrap_or_default();
+        if let Some(obj) = value.as_object_mut() {
+            for (key, extra) in &self.extra {
+                obj.entry(key.clone()).or_insert_with(|| extra.clone());
+            }
+        }
+        value
+    }
+
+    /// Problems Claude Code may hit starting the server, as far as this app's
+    /// environment tells
+    pub fn check_environment(&self, path: &str) -> Vec<ValidationError> {
+        let mut warnings = Vec::new();
+
+        let mut check_refs = |field_path: String, text: &str| {
+            for name in env_refs(text) {
+                if std::env::var_os(&name).is_none() {
+                    warnings.push(error_at(
+                        &field_path,
+                        format!(
+                            "Environment variable {} is not set in this app's environment",
+                            name
+                        ),
+                    ));
+                }
+            }
+        };
+
+        match &self.transport {
+            McpTransport::Stdio { command, args, env } => {
+                check_refs(format!("{}.command", path), command);
+                for (i, arg) in args.iter().enumerate() {
+                    check_refs(format!("{}.args[{}]", path, i), arg);
+                }
+                for (name, value) in env {
+                    check_refs(format!("{}.env.{}", path, name), value);
+                }
+            }
+            McpTransport::Sse { url, headers } | McpTransport::Http { url, headers } => {
+                check_refs(format!("{}.url", path), url);
+                for (name, value) in headers {
+                    check_refs(format!("{}.headers.{}", path, name), value);
+                }
+            }
+        }
+
+        if let McpTransport::Stdio { command, .. } = &self.transport {
+            if env_refs(command).is_empty() && !command_exists(command) {
+                warnings.push(error_at(
+                    &format!("{}.command", path),
+                    format!("\"{}\" was not found on this app's PATH", command),
+                ));
+            }
+        }
+
+        warnings
+    }
+}
+
+/// Names of `${VAR}` references without a `${VAR:-default}` fallback
+fn env_refs(text: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find("${") {
+        let after = &rest[start + 2..];
+        let Some(end) = after.find('}') else {
+            break;
+        };
+
+        let inner = &after[..end];
+        if !inner.contains(":-") && !inner.is_empty() {
+            names.push(inner.to_string());
+        }
+        rest = &after[end + 1..];
+    }
+
+    names
+}
+
+fn is_executable(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        path.metadata()
+            .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+            .unwrap_or(false)
+    }
+
+    #[cfg(not(unix))]
+    {
+        path.is_file()
+    }
+}
+
+/// Whether `command` is a path to an executable or resolves on PATH
+fn command_exists(command: &str) -> bool {
+    let path = Path::new(command);
+    if path.components().count() > 1 {
+        return is_executable(path);
+    }
+
+    let Some(search_path) = std::env::var_os("PATH") else {
+        return false;
+    };
+
+    let extensions: Vec<String> = if cfg!(windows) {
+        std::env::var("PATHEXT")
+            .unwrap_or_else(|_| ".EXE;.CMD;.BAT;.COM".to_string())
+            .split(';')
+            .map(|ext| ext.to_string())
+            .chain(std::iter::once(String::new()))
+            .collect()
+    } else {
+        vec![String::new()]
+    };
+
+    std::env::split_paths(&search_path).any(|dir| {
+        extensions
+            .iter()
+            .any(|ext| is_executable(&dir.join(format!("{}{}", command, ext))))
+    })
+}
+
+/// Parse and check a definition about to be saved, returning its normalized
+/// form and any environment warnings
+pub fn validate_server(value: &Value, path: &str) -> Validation<Value> {
+    let config = McpServerConfig::from_value(value, path)?;
+    let warnings = config.check_environment(path);
+    Ok((config.to_value(), warnings))
+}
+
+/// Validate the servers in `servers` that aren't already saved as-is in
+/// `current`, so one broken existing server doesn't block unrelated edits.
+/// Returns the servers and the warnings for the changed ones.
+pub fn validate_changed_servers(
+    servers: Map<String, Value>,
+    current: Option<&Map<String, Value>>,
+) -> Validation<Map<String, Value>> {
+    let mut errors = Vec::new();
+    let mut warnings = Vec::new();
+    let mut validated = Map::new();
+
+    for (name, config) in servers {
+        if current.and_then(|c| c.get(&name)) == Some(&config) {
+            validated.insert(name, config);
+            continue;
+        }
+
+        match validate_server(&config, &format!("$.{}", name)) {
+            Ok((config, server_warnings)) => {
+                validated.insert(name, config);
+                warnings.extend(server_warnings);
+            }
+            Err(server_errors) => errors.extend(server_errors),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok((validated, warnings))
+    } else {
+        Err(errors)
+    }
+}
+
+pub fn invalid_server_error(errors: Vec<ValidationError>) -> SettingsError {
+    SettingsError::Invalid {
+        message: format!("MCP server is invalid ({} problems)", errors.len()),
+        errors,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn paths(errors: &[ValidationError]) -> Vec<&str> {
+        errors.iter().map(|e| e.path.as_str()).collect()
+    }
+
+    #[test]
+    fn test_from_value_round_trips_unknown_fields() {
+        let value = json!({
+            "command": "npx",
+            "args": ["-y", "server"],
+            "env": { "TOKEN": "x" },
+            "timeout": 30
+        });
+
+        let config = McpServerConfig::from_value(&value, "$").unwrap();
+        assert!(matches!(config.transport, McpTransport::Stdio { .. }));
+
+        let mut expected = value.clone();
+        expected["type"] = json!("stdio");
+        assert_eq!(config.to_value(), expected);
+    }
+
+    #[test]
+    fn test_from_value_reports_fields() {
+        let errors =
+            McpServerConfig::from_value(&json!({ "type": "stdio", "args": [1] }), "$").unwrap_err();
+        assert_eq!(paths(&errors), vec!["$.command", "$.args[0]"]);
+
+        let errors = McpServerConfig::from_value(
+            &json!({ "type": "http", "url": "not a url", "headers": { "bad header": "v" } }),
+            "$.docs",
+        )
+        .unwrap_err();
+        assert_eq!(
+            paths(&errors),
+            vec!["$.docs.url", "$.docs.headers.bad header"]
+        );
+
+        let errors = McpServerConfig::from_value(&json!({ "type": "websocket" }), "$").unwrap_err();
+        assert_eq!(paths(&errors), vec!["$.type"]);
+    }
+
+    #[test]
+    fn test_check_environment() {
+        let config = McpServerConfig::from_value(
+            &json!({
+                "command": "definitely-not-a-real-binary-3f9a",
+                "env": {
+                    "TOKEN": "${CCFOUNDATION_TEST_UNSET_VAR}",
+                    "OPTIONAL": "${CCFOUNDATION_TEST_UNSET_VAR:-fallback}"
+                }
+            }),
+            "$",
+        )
+        .unwrap();
+
+        let warnings = config.check_environment("$");
+        assert_eq!(paths(&warnings), vec!["$.env.TOKEN", "$.command"]);
+
+        let config = McpServerConfig::from_value(&json!({ "command": "sh" }), "$").unwrap();
+        assert!(config.check_environment("$").is_empty());
+    }
+
+    #[test]
+    fn test_validate_changed_servers_skips_unchanged() {
+        let broken = json!({ "command": ["not", "a", "string"] });
+        let current: Map<String, Value> =
+            [("old".to_string(), broken.clone())].into_iter().collect();
+
+        let servers: Map<String, Value> = [
+            ("old".to_string(), broken.clone()),
+            ("new".to_string(), json!({ "command": "sh" })),
+        ]
+        .into_iter()
+        .collect();
+        let (validated, warnings) = validate_changed_servers(servers, Some(&current)).unwrap();
+        assert_eq!(validated["old"], broken);
+        assert_eq!(validated["new"]["type"], "stdio");
+        assert!(warnings.is_empty());
+
+        let servers: Map<String, Value> = [("old".to_string(), broken)].into_iter().collect();
+        assert!(validate_changed_servers(servers, None).is_err());
+    }
+
+    #[test]
+    fn test_validate_server_warns_about_environment() {
+        // npx may only be on the PATH of the user's shell, so it mustn't block saving
+        let (config, warnings) = validate_server(
+            &json!({
+                "command": "definitely-not-a-real-binary-3f9a",
+                "env": { "TOKEN": "${CCFOUNDATION_TEST_UNSET_VAR}" }
+            }),
+            "$.tool",
+        )
+        .unwrap();
+        assert_eq!(config["type"], "stdio");
+        assert_eq!(paths(&warnings), vec!["$.tool.env.TOKEN", "$.tool.command"]);
+
+        let errors = validate_server(&json!({ "type": "http" }), "$.tool").unwrap_err();
+        assert_eq!(paths(&errors), vec!["$.tool.url"]);
+    }
+}
@@ -0,0 +1,230 @@
+//! Disabled MCP servers
+//!
+//! Claude Code has no per-server off switch, so disabling a server removes
+//! it from its `mcpServers` and parks the definition in
+//! ~/.ccconfig/disabled-mcp-servers.json. Claude Code never touches that file,
+//! so the definition survives its rewrites of ~/.claude.json until the
+//! server is enabled again.
+
+use super::resolver::McpScope;
+use super::transfer::{read_location, update_location, McpLocation};
+use crate::managed_file;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+pub(crate) const STASH_FILE: &str = ".ccconfig/disabled-mcp-servers.json";
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct DisabledMcpServer {
+    pub name: String,
+    pub location: McpLocation,
+    /// Definition exactly as it was when disabled
+    pub config: Value,
+    #[serde(rename = "disabledAt")]
+    pub disabled_at: u64,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Default)]
+struct Stash {
+    servers: Vec<DisabledMcpServer>,
+}
+
+/// A server in a location and whether Claude Code currently loads it
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct McpServerToggle {
+    pub name: String,
+    pub enabled: bool,
+}
+
+pub fn stash_path(home: &Path) -> PathBuf {
+    home.join(STASH_FILE)
+}
+
+fn same_location(a: &McpLocation, b: &McpLocation) -> bool {
+    a.scope == b.scope && (a.scope == McpScope::User || a.project_path == b.project_path)
+}
+
+fn read_stash(home: &Path) -> Result<Stash, String> {
+    let path = stash_path(home);
+    if !path.exists() {
+        return Ok(Stash::default());
+    }
+
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read disabled MCP servers: {}", e))?;
+    serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse disabled MCP servers: {}", e))
+}
+
+fn write_stash(home: &Path, stash: &Stash) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(stash)
+        .map_err(|e| format!("Failed to serialize disabled MCP servers: {}", e))?;
+    managed_file::write(&stash_path(home), &content)
+}
+
+pub fn list_disabled_in(
+    home: &Path,
+    location: Option<&McpLocation>,
+) -> Result<Vec<DisabledMcpServer>, String> {
+    Ok(read_stash(home)?
+        .servers
+        .into_iter()
+        .filter(|s| location.is_none_or(|l| same_location(&s.location, l)))
+        .collect())
+}
+
+/// Remove a server from its location, keeping its definition in the stash
+pub fn disable_in(home: &Path, location: &McpLocation, name: &str) -> Result<(), String> {
+    let config = read_location(home, location)?
+        .get(name)
+        .cloned()
+        .ok_or_else(|| format!("MCP server '{}' not found", name))?;
+
+    let mut stash = read_stash(home)?;
+    stash
+        .servers
+        .retain(|s| !(s.name == name && same_location(&s.location, location)));
+    stash.servers.push(DisabledMcpServer {
+        name: name.to_string(),
+        location: location.clone(),
+        config,
+        disabled_at: chrono::Utc::now().timestamp() as u64,
+    });
+
+    // Stash first, so a failed removal never loses the definition
+    write_stash(home, &stash)?;
+    update_location(home, location, |servers| {
+        servers.remove(name);
+    })
+}
+
+/// Put a stashed server back exactly as it was
+pub fn enable_in(home: &Path, location: &McpLocation, name: &str) -> Result<(), String> {
+    let mut stash = read_stash(home)?;
+    let index = stash
+        .servers
+        .iter()
+        .position(|s| s.name == name && same_location(&s.location, location))
+        .ok_or_else(|| format!("MCP server '{}' is not disabled", name))?;
+
+    if read_location(home, location)?.contains_key(name) {
+        return Err(format!(
+            "An MCP server named '{}' was added since it was disabled",
+            name
+        ));
+    }
+
+    let server = stash.servers.remove(index);
+    update_location(home, location, |servers| {
+        servers.insert(server.name.clone(), server.config.clone());
+    })?;
+    write_stash(home, &stash)
+}
+
+/// Enabled and disabled servers of a location, sorted by name
+pub fn toggles_in(home: &Path, location: &McpLocation) -> Result<Vec<McpServerToggle>, String> {
+    let mut toggles: Vec<McpServerToggle> = read_location(home, location)?
+        .keys()
+        .map(|name| McpServerToggle {
+            name: name.clone(),
+            enabled: true,
+        })
+        .collect();
+
+    for server in list_disabled_in(home, Some(location))? {
+        if !toggles.iter().any(|t| t.name == server.name) {
+            toggles.push(McpServerToggle {
+                name: server.name,
+                enabled: false,
+            });
+        }
+    }
+
+    toggles.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(toggles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_disable_and_enable_restore_definition() {
+        let home = tempdir().unwrap();
+        let user = McpLocation {
+            scope: McpScope::User,
+            project_path: None,
+        };
+        let github = json!({ "command": "npx", "args": ["-y", "github"], "custom": 1 });
+
+        std::fs::write(
+            home.path().join(".claude.json"),
+            json!({ "mcpServers": { "github": github.clone(), "notes": { "command": "n" } } })
+                .to_string(),
+        )
+        .unwrap();
+
+        disable_in(home.path(), &user, "github").unwrap();
+        assert!(!read_location(home.path(), &user)
+            .unwrap()
+            .contains_key("github"));
+
+        // Claude Code rewriting .claude.json doesn't affect the stash
+        std::fs::write(
+            home.path().join(".claude.json"),
+            json!({ "numStartups": 9, "mcpServers": { "notes": { "command": "n" } } }).to_string(),
+        )
+        .unwrap();
+
+        let toggles = toggles_in(home.path(), &user).unwrap();
+        assert_eq!(
+            toggles,
+            vec![
+                McpServerToggle {
+                    name: "github".into(),
+                    enabled: false
+                },
+                McpServerToggle {
+                    name: "notes".into(),
+                    enabled: true
+                },
+            ]
+        );
+
+        enable_in(home.path(), &user, "github").unwrap();
+        assert_eq!(read_location(home.path(), &user).unwrap()["github"], github);
+        assert!(list_disabled_in(home.path(), None).unwrap().is_empty());
+        assert!(enable_in(home.path(), &user, "github").is_err());
+    }
+
+    #[test]
+    fn test_enable_in_refuses_to_overwrite() {
+        let home = tempdir().unwrap();
+        let project_dir = tempdir().unwrap();
+        let project = McpLocation {
+            scope: McpScope::Project,
+            project_path: Some(project_dir.path().to_string_lossy().to_string()),
+        };
+
+        std::fs::write(
+            project_dir.path().join(".mcp.json"),
+            json!({ "mcpServers": { "db": { "command": "old" } } }).to_string(),
+        )
+        .unwrap();
+
+        disable_in(home.path(), &project, "db").unwrap();
+        std::fs::write(
+            project_dir.path().join(".mcp.json"),
+            json!({ "mcpServers": { "db": { "command": "new" } } }).to_string(),
+        )
+        .unwrap();
+
+        assert!(enable_in(home.path(), &project, "db").is_err());
+        assert_eq!(
+            list_disabled_in(home.path(), Some(&project)).unwrap().len(),
+            1
+        );
+    }
+}
@@ -0,0 +1,367 @@
+//! Copy or move MCP servers between the user, local and project scopes
+
+use super::project;
+use super::resolver::McpScope;
+use super::server::{invalid_server_error, validate_server};
+use crate::bundle::{unique_name, ConflictStrategy, ImportAction};
+use crate::claude_settings::{SettingsError, ValidationError};
+use crate::managed_file;
+use serde_json::{Map, Value};
+use std::path::{Path, PathBuf};
+
+/// A writable place MCP servers live in
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct McpLocation {
+    pub scope: McpScope,
+    /// Required for the local and project scopes
+    #[serde(rename = "projectPath", default)]
+    pub project_path: Option<String>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum McpTransferMode {
+    Copy,
+    Move,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct McpTransferItem {
+    pub name: String,
+    /// Name to use at the destination, defaults to `name`
+    #[serde(rename = "targetName", default)]
+    pub target_name: Option<String>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct McpTransferResult {
+    pub name: String,
+    /// Name the server ended up with at the destination
+    #[serde(rename = "targetName")]
+    pub target_name: String,
+    pub action: ImportAction,
+    /// Environment problems Claude Code may hit starting the server
+    pub warnings: Vec<ValidationError>,
+}
+
+fn project_path(location: &McpLocation) -> Result<&str, String> {
+    location
+        .project_path
+        .as_deref()
+        .filter(|p| !p.is_empty())
+        .ok_or_else(|| {
+            format!(
+                "A project path is required for the {:?} scope",
+                location.scope
+            )
+        })
+}
+
+/// File holding the servers of `location`
+pub fn location_path(home: &Path, location: &McpLocation) -> Result<PathBuf, String> {
+    match location.scope {
+        McpScope::User => Ok(home.join(".claude.json")),
+        McpScope::Local => project_path(location).map(|_| home.join(".claude.json")),
+        McpScope::Project => Ok(project::mcp_path(project_path(location)?)),
+        McpScope::Managed => Err("Managed MCP servers can't be changed".to_string()),
+    }
+}
+
+/// The `mcpServers` object of `location` inside ~/.claude.json, created on demand
+fn claude_json_servers<'a>(
+    json: &'a mut Value,
+    location: &McpLocation,
+) -> Result<&'a mut Map<String, Value>, String> {
+    let mut obj = json
+        .as_object_mut()
+        .ok_or("Invalid .claude.json: expected an object")?;
+
+    if location.scope == McpScope::Local {
+        obj = obj
+            .entry("projects".to_string())
+            .or_insert_with(|| Value::Object(Map::new()))
+            .as_object_mut()
+            .ok_or("Invalid .claude.json: projects is not an object")?
+            .entry(project_path(location)?.to_string())
+            .or_insert_with(|| Value::Object(Map::new()))
+            .as_object_mut()
+            .ok_or("Invalid .claude.json: project entry is not an object")?;
+    }
+
+    obj.entry("mcpServers".to_string())
+        .or_insert_with(|| Value::Object(Map::new()))
+        .as_object_mut()
+        .ok_or_else(|| "Invalid .claude.json: mcpServers is not an object".to_string())
+}
+
+pub(crate) fn read_location(
+    home: &Path,
+    location: &McpLocation,
+) -> Result<Map<String, Value>, String> {
+    let path = location_path(home, location)?;
+    if location.scope == McpScope::Project {
+        return Ok(project::read_servers(&path)?.unwrap_or_default());
+    }
+
+    if !path.exists() {
+        return Ok(Map::new());
+    }
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read .claude.json: {}", e))?;
+    let mut json: Value = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse .claude.json: {}", e))?;
+
+    Ok(claude_json_servers(&mut json, location)?.clone())
+}
+
+/// Apply `f` to the latest servers of `location` and save them
+pub(crate) fn update_location<F>(
+    home: &Path,
+    location: &McpLocation,
+    mut f: F,
+) -> Result<(), String>
+where
+    F: FnMut(&mut Map<String, Value>),
+{
+    let path = location_path(home, location)?;
+    if location.scope == McpScope::Project {
+        let mut servers = project::read_servers(&path)?.unwrap_or_default();
+        f(&mut servers);
+        return project::write_servers(&path, servers);
+    }
+
+    // Claude Code rewrites .claude.json constantly, so apply the change to the latest copy
+    managed_file::update_json(&path, |json| {
+        f(claude_json_servers(json, location)?);
+        Ok(())
+    })
+    .map_err(|e| format!("Failed to write .claude.json: {}", e))
+}
+
+/// Copy or move `items` from one location to another. Every definition is
+/// validated before anything is written; a move only removes what it copied.
+pub fn transfer_in(
+    home: &Path,
+    items: &[McpTransferItem],
+    from: &McpLocation,
+    to: &McpLocation,
+    mode: McpTransferMode,
+    strategy: ConflictStrategy,
+) -> Result<Vec<McpTransferResult>, SettingsError> {
+    if from == to {
+        return Err("Source and destination are the same".into());
+    }
+
+    let source = read_location(home, from)?;
+    let mut target = read_location(home, to)?;
+
+    let mut results = Vec::new();
+    let mut additions = Map::new();
+    let mut errors = Vec::new();
+
+    for item in items {
+        let config = source
+            .get(&item.name)
+            .ok_or_else(|| format!("MCP server '{}' not found", item.name))?;
+        let desired = item
+            .target_name
+            .clone()
+            .unwrap_or_else(|| item.name.clone());
+
+        let action = match (target.contains_key(&desired), strategy) {
+            (false, _) => ImportAction::Create,
+            (true, ConflictStrategy::Skip) => ImportAction::Skip,
+            (true, ConflictStrategy::Overwrite) => ImportAction::Overwrite,
+            (true, ConflictStrategy::Rename) => ImportAction::Rename,
+        };
+        let target_name = match action {
+            ImportAction::Rename => unique_name(&desired, |name| target.contains_key(name)),
+            _ => desired,
+        };
+
+        let mut warnings = Vec::new();
+        if action != ImportAction::Skip {
+            match validate_server(config, &format!("$.{}", item.name)) {
+                Ok((config, server_warnings)) => {
+                    target.insert(target_name.clone(), config.clone());
+                    additions.insert(target_name.clone(), config);
+                    warnings = server_warnings;
+                }
+                Err(server_errors) => errors.extend(server_errors),
+            }
+        }
+
+        results.push(McpTransferResult {
+            name: item.name.clone(),
+            target_name,
+            action,
+            warnings,
+        });
+    }
+
+    if !errors.is_empty() {
+        return Err(invalid_server_error(errors));
+    }
+
+    if !additions.is_empty() {
+        update_location(home, to, |servers| {
+            for (name, config) in &additions {
+                servers.insert(name.clone(), config.clone());
+            }
+        })?;
+    }
+
+    if mode == McpTransferMode::Move {
+        let moved: Vec<&str> = results
+            .iter()
+            .filter(|r| r.action != ImportAction::Skip)
+            .map(|r| r.name.as_str())
+            .collect();
+        if !moved.is_empty() {
+            update_location(home, from, |servers| {
+                for name in &moved {
+                    servers.remove(*name);
+                }
+            })?;
+        }
+    }
+
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    fn item(name: &str) -> McpTransferItem {
+        McpTransferItem {
+            name: name.to_string(),
+            target_name: None,
+        }
+    }
+
+    #[test]
+    fn test_transfer_in_moves_user_server_to_project() {
+        let home = tempdir().unwrap();
+        let project_dir = tempdir().unwrap();
+        let project_path = project_dir.path().to_string_lossy().to_string();
+
+        std::fs::write(
+            home.path().join(".claude.json"),
+            json!({
+                "numStartups": 3,
+                "mcpServers": {
+                    "github": { "command": "sh" },
+                    "notes": { "command": "sh", "args": ["notes"] }
+                }
+            })
+            .to_string(),
+        )
+        .unwrap();
+        std::fs::write(
+            project::mcp_path(&project_path),
+            json!({ "mcpServers": { "github": { "type": "stdio", "command": "other" } } })
+                .to_string(),
+        )
+        .unwrap();
+
+        let user = McpLocation {
+            scope: McpScope::User,
+            project_path: None,
+        };
+        let team = McpLocation {
+            scope: McpScope::Project,
+            project_path: Some(project_path.clone()),
+        };
+
+        let results = transfer_in(
+            home.path(),
+            &[item("github"), item("notes")],
+            &user,
+            &team,
+            McpTransferMode::Move,
+            ConflictStrategy::Rename,
+        )
+        .unwrap();
+
+        assert_eq!(results[0].target_name, "github-2");
+        assert_eq!(results[0].action, ImportAction::Rename);
+        assert_eq!(results[1].action, ImportAction::Create);
+
+        let team_servers = read_location(home.path(), &team).unwrap();
+        assert_eq!(team_servers.len(), 3);
+        assert_eq!(team_servers["github"]["command"], "other");
+        assert_eq!(team_servers["github-2"]["command"], "sh");
+
+        assert!(read_location(home.path(), &user).unwrap().is_empty());
+        let claude_json: Value = serde_json::from_str(
+            &std::fs::read_to_string(home.path().join(".claude.json")).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(claude_json["numStartups"], 3);
+    }
+
+    #[test]
+    fn test_transfer_in_copies_into_local_scope_and_skips_conflicts() {
+        let home = tempdir().unwrap();
+        let local = McpLocation {
+            scope: McpScope::Local,
+            project_path: Some("/work/app".to_string()),
+        };
+        let user = McpLocation {
+            scope: McpScope::User,
+            project_path: None,
+        };
+
+        std::fs::write(
+            home.path().join(".claude.json"),
+            json!({
+                "mcpServers": { "db": { "command": "sh" } },
+                "projects": { "/work/app": { "allowedTools": [] } }
+            })
+            .to_string(),
+        )
+        .unwrap();
+
+        let results = transfer_in(
+            home.path(),
+            &[item("db")],
+            &user,
+            &local,
+            McpTransferMode::Copy,
+            ConflictStrategy::Skip,
+        )
+        .unwrap();
+        assert_eq!(results[0].action, ImportAction::Create);
+        assert_eq!(read_location(home.path(), &local).unwrap().len(), 1);
+        assert_eq!(read_location(home.path(), &user).unwrap().len(), 1);
+
+        // Copying again hits the existing name and leaves both sides alone
+        let results = transfer_in(
+            home.path(),
+            &[item("db")],
+            &user,
+            &local,
+            McpTransferMode::Move,
+            ConflictStrategy::Skip,
+        )
+        .unwrap();
+        assert_eq!(results[0].action, ImportAction::Skip);
+        assert_eq!(read_location(home.path(), &user).unwrap().len(), 1);
+
+        let missing = transfer_in(
+            home.path(),
+            &[item("nope")],
+            &user,
+            &local,
+            McpTransferMode::Copy,
+            ConflictStrategy::Skip,
+        );
+        assert_eq!(
+            missing.unwrap_err(),
+            SettingsError::Other("MCP server 'nope' not found".to_string())
+        );
+    }
+}
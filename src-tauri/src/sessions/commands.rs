@@ -1,8 +1,10 @@
 use super::discovery::{check_claude_installed, extract_session_id, list_session_files};
+use super::live::{LiveSession, LiveSessions};
 use super::migrate::migrate_session_models;
 use super::parser::{parse_session, parse_session_file};
 use super::resume::{cancel_session, create_session, resume_session, RunningProcesses};
 use super::types::{Session, SessionMessage};
+use crate::journal;
 use std::path::PathBuf;
 use tauri::{AppHandle, State};
 
@@ -23,12 +25,17 @@ pub async fn session_list(project_path: String) -> Result<Vec<Session>, String>
     let mut sessions = Vec::new();
 
     for (idx, file_path) in session_files.iter().enumerate() {
-        println!("📄 Parsing file {}/{}: {:?}", idx + 1, session_files.len(), file_path);
+        println!(
+            "📄 Parsing file {}/{}: {:?}",
+            idx + 1,
+            session_files.len(),
+            file_path
+        );
         match parse_session(file_path) {
             Ok(session) => {
                 println!("✅ Parsed session: {} - {}", session.id, session.title);
                 sessions.push(session);
-            },
+            }
             Err(e) => {
                 eprintln!("❌ Failed to parse session {:?}: {}", file_path, e);
                 // Continue with other sessions
@@ -111,6 +118,18 @@ pub async fn session_cancel(
     cancel_session(&session_id, processes.inner().clone()).await
 }
 
+/// Claude Code sessions reporting through hooks, across all projects,
+/// most recently active first
+#[tauri::command]
+pub async fn session_live_list(
+    live_sessions: State<'_, LiveSessions>,
+) -> Result<Vec<LiveSession>, String> {
+    let mut live_sessions = live_sessions
+        .lock()
+        .map_err(|e| format!("Failed to lock live sessions: {}", e))?;
+    Ok(live_sessions.list(chrono::Utc::now().timestamp_millis()))
+}
+
 /// Delete a session file
 #[tauri::command]
 pub async fn session_delete(project_path: String, session_id: String) -> Result<(), String> {
@@ -121,6 +140,7 @@ pub async fn session_delete(project_path: String, session_id: String) -> Result<
     for file_path in session_files {
         if let Some(id) = extract_session_id(&file_path) {
             if id == session_id {
+                journal::record("session_delete", &[&file_path])?;
                 std::fs::remove_file(&file_path)
                     .map_err(|e| format!("Failed to delete session file: {}", e))?;
                 println!("✅ Deleted session: {}", session_id);
@@ -142,13 +162,7 @@ pub async fn session_create(
 ) -> Result<String, String> {
     println!("🆕 session_create: project_path={}", project_path);
 
-    create_session(
-        app,
-        message,
-        project_path,
-        processes.inner().clone(),
-    )
-    .await
+    create_session(app, message, project_path, processes.inner().clone()).await
 }
 
 /// Migrate old model names in session files for a project
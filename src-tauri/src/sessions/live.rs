@@ -0,0 +1,224 @@
+use crate::hook_events::{HookEvent, HookPayload};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Type alias for the live sessions state shared with the hook server
+pub type LiveSessions = Arc<Mutex<LiveSessionModel>>;
+
+/// Initialize live sessions state
+pub fn init_live_sessions() -> LiveSessions {
+    Arc::new(Mutex::new(LiveSessionModel::default()))
+}
+
+/// Stopped sessions are dropped this long after they end
+const STOPPED_RETENTION_MS: i64 = 10 * 60 * 1000;
+
+/// Sessions that never sent SessionEnd (e.g. the terminal was closed) are
+/// dropped after this long without activity
+const INACTIVE_RETENTION_MS: i64 = 24 * 60 * 60 * 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LiveSessionState {
+    /// Working on a prompt
+    Running,
+    /// Blocked on a permission prompt
+    WaitingForPermission,
+    /// Waiting for the next prompt
+    Idle,
+    Stopped,
+}
+
+/// A Claude Code session as seen through its hook events
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LiveSession {
+    pub session_id: String,
+    pub cwd: String,
+    pub transcript_path: String,
+    pub state: LiveSessionState,
+    /// Tool of the latest PreToolUse or PostToolUse
+    pub last_tool: Option<String>,
+    /// Name of the latest hook event
+    pub last_event: String,
+    /// Milliseconds since the epoch
+    pub started_at: i64,
+    pub last_activity: i64,
+}
+
+#[derive(Debug, Default)]
+pub struct LiveSessionModel {
+    sessions: HashMap<String, LiveSession>,
+}
+
+/// State a session is in after `payload`
+fn next_state(event: &HookEvent, payload: &HookPayload) -> LiveSessionState {
+    match payload {
+        HookPayload::UserPromptSubmit { .. }
+        | HookPayload::PreToolUse { .. }
+        | HookPayload::PostToolUse { .. }
+        | HookPayload::SubagentStop { .. }
+        | HookPayload::PreCompact { .. } => LiveSessionState::Running,
+        HookPayload::SessionStart { .. } | HookPayload::Stop { .. } => LiveSessionState::Idle,
+        HookPayload::SessionEnd { .. } => LiveSessionState::Stopped,
+        HookPayload::Notification { message } => {
+            // Newer Claude Code versions say which prompt is showing; older ones only the message
+            let permission_prompt = match event
+                .extra
+                .get("notification_type")
+                .and_then(|t| t.as_str())
+            {
+                Some(notification_type) => notification_type == "permission_prompt",
+                None => message.contains("permission"),
+            };
+            if permission_prompt {
+                LiveSessionState::WaitingForPermission
+            } else {
+                LiveSessionState::Idle
+            }
+        }
+    }
+}
+
+impl LiveSessionModel {
+    /// Update the session `event` belongs to, returning its new state
+    pub fn apply(&mut self, event: &HookEvent, now: i64) -> Option<LiveSession> {
+        let payload = event.payload().ok()?;
+        self.prune(now);
+
+        let session = self
+            .sessions
+            .entry(event.session_id.clone())
+            .or_insert_with(|| LiveSession {
+                session_id: event.session_id.clone(),
+                cwd: event.cwd.clone(),
+                transcript_path: event.transcript_path.clone(),
+                state: LiveSessionState::Idle,
+                last_tool: None,
+                last_event: event.hook_event_name.clone(),
+                started_at: now,
+                last_activity: now,
+            });
+
+        session.cwd = event.cwd.clone();
+        session.transcript_path = event.transcript_path.clone();
+        session.last_event = event.hook_event_name.clone();
+        session.last_activity = now;
+        if let Some(tool_name) = event.tool_name() {
+            session.last_tool = Some(tool_name.to_string());
+        }
+        session.state = next_state(event, &payload);
+
+        Some(session.clone())
+    }
+
+    fn prune(&mut self, now: i64) {
+        self.sessions.retain(|_, session| {
+            let retention = match session.state {
+                LiveSessionState::Stopped => STOPPED_RETENTION_MS,
+                _ => INACTIVE_RETENTION_MS,
+            };
+            now - session.last_activity < retention
+        });
+    }
+
+    /// Known sessions, most recently active first
+    pub fn list(&mut self, now: i64) -> Vec<LiveSession> {
+        self.prune(now);
+        let mut sessions: Vec<LiveSession> = self.sessions.values().cloned().collect();
+        sessions.sort_by_key(|session| std::cmp::Reverse(session.last_activity));
+        sessions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn event(session_id: &str, fields: serde_json::Value) -> HookEvent {
+        let mut payload = json!({
+            "session_id": session_id,
+            "transcript_path": format!("/tmp/{}.jsonl", session_id),
+            "cwd": "/work/app",
+        });
+        payload
+            .as_object_mut()
+            .unwrap()
+            .extend(fields.as_object().unwrap().clone());
+        serde_json::from_value(payload).unwrap()
+    }
+
+    #[test]
+    fn test_apply_tracks_session_state() {
+        let mut model = LiveSessionModel::default();
+
+        let start = event(
+            "a",
+            json!({ "hook_event_name": "SessionStart", "source": "startup" }),
+        );
+        assert_eq!(
+            model.apply(&start, 1).unwrap().state,
+            LiveSessionState::Idle
+        );
+
+        let tool = event(
+            "a",
+            json!({ "hook_event_name": "PreToolUse", "tool_name": "Bash", "tool_input": {} }),
+        );
+        let session = model.apply(&tool, 2).unwrap();
+        assert_eq!(session.state, LiveSessionState::Running);
+        assert_eq!(session.last_tool.as_deref(), Some("Bash"));
+
+        let prompt = event(
+            "a",
+            json!({
+                "hook_event_name": "Notification",
+                "message": "Claude needs your permission to use Bash"
+            }),
+        );
+        assert_eq!(
+            model.apply(&prompt, 3).unwrap().state,
+            LiveSessionState::WaitingForPermission
+        );
+
+        let stop = event("a", json!({ "hook_event_name": "Stop" }));
+        let session = model.apply(&stop, 4).unwrap();
+        assert_eq!(session.state, LiveSessionState::Idle);
+        assert_eq!(session.last_tool.as_deref(), Some("Bash"));
+        assert_eq!(session.started_at, 1);
+
+        let other = event(
+            "b",
+            json!({ "hook_event_name": "UserPromptSubmit", "prompt": "hi" }),
+        );
+        model.apply(&other, 5);
+        let ids: Vec<String> = model.list(6).into_iter().map(|s| s.session_id).collect();
+        assert_eq!(ids, vec!["b", "a"]);
+    }
+
+    #[test]
+    fn test_list_drops_stopped_and_stale_sessions() {
+        let mut model = LiveSessionModel::default();
+
+        let end = event(
+            "a",
+            json!({ "hook_event_name": "SessionEnd", "reason": "logout" }),
+        );
+        assert_eq!(
+            model.apply(&end, 0).unwrap().state,
+            LiveSessionState::Stopped
+        );
+        model.apply(&event("b", json!({ "hook_event_name": "Stop" })), 0);
+
+        assert_eq!(model.list(STOPPED_RETENTION_MS - 1).len(), 2);
+        assert_eq!(model.list(STOPPED_RETENTION_MS).len(), 1);
+        assert!(model.list(INACTIVE_RETENTION_MS).is_empty());
+
+        // Events the model doesn't understand are ignored
+        assert!(model
+            .apply(&event("c", json!({ "hook_event_name": "Teleport" })), 0)
+            .is_none());
+    }
+}
@@ -1,6 +1,7 @@
 // Sessions module - integrates with Claude Code's native session management
 pub mod commands;
 pub mod discovery;
+pub mod live;
 pub mod migrate;
 pub mod parser;
 pub mod resume;
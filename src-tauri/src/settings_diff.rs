@@ -0,0 +1,125 @@
+//! Structural diff between two settings objects
+//!
+//! Objects are compared key by key; any other value (including arrays) is
+//! compared as a whole and reported at the path where it differs.
+
+use crate::settings_merge::KeyPath;
+use serde_json::Value;
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// A single difference between two settings values
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct SettingsChange {
+    pub path: KeyPath,
+    pub kind: ChangeKind,
+    #[serde(rename = "oldValue")]
+    pub old_value: Option<Value>,
+    #[serde(rename = "newValue")]
+    pub new_value: Option<Value>,
+}
+
+/// List every added, removed and changed path going from `old` to `new`
+pub fn diff_settings(old: &Value, new: &Value) -> Vec<SettingsChange> {
+    let mut changes = Vec::new();
+    diff_at(&mut Vec::new(), old, new, &mut changes);
+    changes
+}
+
+fn diff_at(path: &mut KeyPath, old: &Value, new: &Value, changes: &mut Vec<SettingsChange>) {
+    match (old, new) {
+        (Value::Object(old_obj), Value::Object(new_obj)) => {
+            for (key, old_value) in old_obj {
+                path.push(key.clone());
+                match new_obj.get(key) {
+                    Some(new_value) => diff_at(path, old_value, new_value, changes),
+                    None => changes.push(SettingsChange {
+                        path: path.clone(),
+                        kind: ChangeKind::Removed,
+                        old_value: Some(old_value.clone()),
+                        new_value: None,
+                    }),
+                }
+                path.pop();
+            }
+
+            for (key, new_value) in new_obj {
+                if !old_obj.contains_key(key) {
+                    let mut added_path = path.clone();
+                    added_path.push(key.clone());
+                    changes.push(SettingsChange {
+                        path: added_path,
+                        kind: ChangeKind::Added,
+                        old_value: None,
+                        new_value: Some(new_value.clone()),
+                    });
+                }
+            }
+        }
+        _ if old != new => changes.push(SettingsChange {
+            path: path.clone(),
+            kind: ChangeKind::Changed,
+            old_value: Some(old.clone()),
+            new_value: Some(new.clone()),
+        }),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_diff_settings_identical() {
+        let settings = json!({ "env": { "A": "1" }, "model": "opus" });
+        assert!(diff_settings(&settings, &settings).is_empty());
+    }
+
+    #[test]
+    fn test_diff_settings_nested_changes() {
+        let old = json!({ "env": { "A": "1", "B": "2" }, "model": "opus" });
+        let new = json!({ "env": { "A": "1", "C": "3" }, "model": "sonnet" });
+
+        let changes = diff_settings(&old, &new);
+
+        assert_eq!(changes.len(), 3);
+        assert!(changes.contains(&SettingsChange {
+            path: vec!["env".into(), "B".into()],
+            kind: ChangeKind::Removed,
+            old_value: Some(json!("2")),
+            new_value: None,
+        }));
+        assert!(changes.contains(&SettingsChange {
+            path: vec!["env".into(), "C".into()],
+            kind: ChangeKind::Added,
+            old_value: None,
+            new_value: Some(json!("3")),
+        }));
+        assert!(changes.contains(&SettingsChange {
+            path: vec!["model".into()],
+            kind: ChangeKind::Changed,
+            old_value: Some(json!("opus")),
+            new_value: Some(json!("sonnet")),
+        }));
+    }
+
+    #[test]
+    fn test_diff_settings_arrays_compared_whole() {
+        let old = json!({ "permissions": { "allow": ["Bash(ls)"] } });
+        let new = json!({ "permissions": { "allow": ["Bash(ls)", "Read"] } });
+
+        let changes = diff_settings(&old, &new);
+
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].path, vec!["permissions", "allow"]);
+        assert_eq!(changes[0].kind, ChangeKind::Changed);
+    }
+}
@@ -0,0 +1,285 @@
+//! Drift between the active ConfigStore and the live ~/.claude/settings.json
+//!
+//! Hand edits and Claude Code's own `/config` write straight to settings.json,
+//! so the active store can silently fall behind. Drift is the diff from the
+//! store's settings to the live file within the store's footprint (the paths
+//! it set or last applied), ignoring the hooks this app installs. Keys added
+//! by hand outside the footprint aren't the store's business.
+
+use crate::settings_diff::{diff_settings, ChangeKind, SettingsChange};
+use crate::settings_merge::{
+    apply_settings, get_path, is_hook_event, remove_at, set_path, KeyPath,
+};
+use serde_json::{Map, Value};
+
+/// Marker on hook entries installed by this app (see `get_latest_hook_command`)
+const MANAGED_HOOK_KEY: &str = "__ccfoundation__";
+
+pub(crate) fn is_managed_entry(entry: &Value) -> bool {
+    entry
+        .get("hooks")
+        .and_then(|h| h.as_array())
+        .is_some_and(|hooks| hooks.iter().any(|h| h.get(MANAGED_HOOK_KEY).is_some()))
+}
+
+/// Copy of `settings` without the hook entries this app installed
+pub fn strip_managed_hooks(settings: &Value) -> Value {
+    let mut stripped = settings.clone();
+
+    let Some(hooks_obj) = stripped.get_mut("hooks").and_then(|h| h.as_object_mut()) else {
+        return stripped;
+    };
+
+    for event_hooks in hooks_obj.values_mut() {
+        if let Some(entries) = event_hooks.as_array_mut() {
+            entries.retain(|entry| !is_managed_entry(entry));
+        }
+    }
+    hooks_obj.retain(|_, event_hooks| event_hooks.as_array().is_none_or(|e| !e.is_empty()));
+
+    if hooks_obj.is_empty() {
+        if let Some(obj) = stripped.as_object_mut() {
+            obj.remove("hooks");
+        }
+    }
+
+    stripped
+}
+
+/// Every difference between `settings` and the live file, including keys
+/// added by hand
+pub fn detect_drift(settings: &Value, live: &Value) -> Vec<SettingsChange> {
+    diff_settings(&strip_managed_hooks(settings), &strip_managed_hooks(live))
+}
+
+/// Paths a store owns in settings.json: the ones it sets now plus the ones
+/// recorded when it was last applied
+pub fn store_footprint(store_settings: &Value, applied_paths: &[KeyPath]) -> Vec<KeyPath> {
+    let (_, store_paths) = apply_settings(&Value::Object(Map::new()), &[], store_settings);
+
+    let mut footprint: Vec<KeyPath> = Vec::new();
+    let owned = applied_paths.iter().filter(|path| !is_hook_event(path));
+    for path in owned.chain(store_paths.iter()) {
+        if !footprint.contains(path) {
+            footprint.push(path.clone());
+        }
+    }
+
+    // A path inside another one is already covered by it
+    let covered: Vec<KeyPath> = footprint
+        .iter()
+        .filter(|path| {
+            footprint
+                .iter()
+                .any(|other| other.len() < path.len() && path.starts_with(other))
+        })
+        .cloned()
+        .collect();
+    footprint.retain(|path| !covered.contains(path));
+    footprint
+}
+
+/// Changes made to settings.json within the store's footprint since
+/// `store_settings` were applied
+pub fn detect_store_drift(
+    store_settings: &Value,
+    live: &Value,
+    applied_paths: &[KeyPath],
+) -> Vec<SettingsChange> {
+    let store = strip_managed_hooks(store_settings);
+    let live = strip_managed_hooks(live);
+
+    store_footprint(&store, applied_paths)
+        .into_iter()
+        .filter_map(|path| {
+            let old_value = get_path(&store, &path).cloned();
+            let new_value = get_path(&live, &path).cloned();
+            let kind = match (&old_value, &new_value) {
+                (None, None) => return None,
+                (Some(old), Some(new)) if old == new => return None,
+                (None, Some(_)) => ChangeKind::Added,
+                (Some(_), None) => ChangeKind::Removed,
+                (Some(_), Some(_)) => ChangeKind::Changed,
+            };
+            Some(SettingsChange {
+                path,
+                kind,
+                old_value,
+                new_value,
+            })
+        })
+        .collect()
+}
+
+/// Undo `changes` in `live`, putting the store's values back. Hook entries this
+/// app installed are kept.
+pub fn revert_drift(live: &Value, changes: &[SettingsChange]) -> Value {
+    let mut reverted = live.clone();
+
+    for change in changes {
+        match change.kind {
+            ChangeKind::Added => remove_at(&mut reverted, &change.path),
+            ChangeKind::Removed | ChangeKind::Changed => {
+                if let Some(old_value) = &change.old_value {
+                    set_path(&mut reverted, &change.path, old_value.clone());
+                }
+            }
+        }
+    }
+
+    restore_managed_hooks(live, &mut reverted);
+    reverted
+}
+
+/// `live` with the store's drift reverted and the store merged in again, and
+/// the paths the store now contributes
+pub fn reapply_store(
+    live: &Value,
+    store_settings: &Value,
+    applied_paths: &[KeyPath],
+) -> (Value, Vec<KeyPath>) {
+    let changes = detect_store_drift(store_settings, live, applied_paths);
+    let reverted = revert_drift(live, &changes);
+    apply_settings(&reverted, applied_paths, store_settings)
+}
+
+/// Put back managed hook entries from `source` that reverting a hooks array dropped
+fn restore_managed_hooks(source: &Value, target: &mut Value) {
+    let Some(source_hooks) = source.get("hooks").and_then(|h| h.as_object()) else {
+        return;
+    };
+
+    for (event, entries) in source_hooks {
+        let Some(entries) = entries.as_array() else {
+            continue;
+        };
+
+        let mut target_entries = target
+            .get("hooks")
+            .and_then(|h| h.get(event))
+            .and_then(|h| h.as_array())
+            .cloned()
+            .unwrap_or_default();
+        let original_len = target_entries.len();
+
+        for entry in entries.iter().filter(|e| is_managed_entry(e)) {
+            if !target_entries.contains(entry) {
+                target_entries.push(entry.clone());
+            }
+        }
+
+        if target_entries.len() != original_len {
+            let path = ["hooks".to_string(), event.clone()];
+            set_path(target, &path, Value::Array(target_entries));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn managed_hook() -> Value {
+        json!({
+            "hooks": [{ "type": "command", "command": "curl ...", "__ccfoundation__": true }]
+        })
+    }
+
+    #[test]
+    fn test_detect_drift_ignores_managed_hooks() {
+        let store = json!({ "env": { "A": "1" } });
+        let live = json!({ "env": { "A": "1" }, "hooks": { "Stop": [managed_hook()] } });
+
+        assert!(detect_store_drift(&store, &live, &[]).is_empty());
+    }
+
+    #[test]
+    fn test_detect_store_drift_reports_edits_within_footprint() {
+        let store = json!({ "env": { "A": "1", "B": "2" }, "model": "opus" });
+        let live = json!({ "env": { "A": "1", "C": "3", "MINE": "x" }, "model": "sonnet" });
+        // The store applied C before it was removed from the store
+        let applied = vec![vec!["env".to_string(), "C".to_string()]];
+
+        let changes = detect_store_drift(&store, &live, &applied);
+
+        assert_eq!(changes.len(), 3);
+        assert!(changes
+            .iter()
+            .any(|c| c.kind == ChangeKind::Added && c.path == vec!["env", "C"]));
+        assert!(changes
+            .iter()
+            .any(|c| c.kind == ChangeKind::Removed && c.path == vec!["env", "B"]));
+        assert!(changes
+            .iter()
+            .any(|c| c.kind == ChangeKind::Changed && c.path == vec!["model"]));
+    }
+
+    #[test]
+    fn test_revert_drift_restores_store_values() {
+        let store = json!({ "env": { "A": "1", "B": "2" }, "model": "opus" });
+        let live = json!({
+            "env": { "A": "1", "C": "3" },
+            "model": "sonnet",
+            "hooks": { "Stop": [managed_hook()] }
+        });
+
+        let applied = vec![vec!["env".to_string(), "C".to_string()]];
+
+        let reverted = revert_drift(&live, &detect_store_drift(&store, &live, &applied));
+
+        assert_eq!(
+            reverted,
+            json!({
+                "env": { "A": "1", "B": "2" },
+                "model": "opus",
+                "hooks": { "Stop": [managed_hook()] }
+            })
+        );
+    }
+
+    #[test]
+    fn test_reapply_store_keeps_keys_outside_footprint() {
+        let store = json!({ "env": { "A": "1" }, "model": "opus" });
+        let applied = vec![
+            vec!["env".to_string(), "A".to_string()],
+            vec!["model".to_string()],
+        ];
+        let live = json!({
+            "env": { "MY_VAR": "mine" },
+            "model": "sonnet",
+            "includeCoAuthoredBy": false
+        });
+
+        assert_eq!(detect_store_drift(&store, &live, &applied).len(), 2);
+        let (reapplied, applied) = reapply_store(&live, &store, &applied);
+
+        assert_eq!(applied.len(), 2);
+        assert_eq!(
+            reapplied,
+            json!({
+                "env": { "MY_VAR": "mine", "A": "1" },
+                "model": "opus",
+                "includeCoAuthoredBy": false
+            })
+        );
+    }
+
+    #[test]
+    fn test_revert_drift_restores_hook_entries_beside_other_hooks() {
+        let store_hook =
+            json!({ "matcher": "Bash", "hooks": [{ "type": "command", "command": "lint" }] });
+        let hand_hook = json!({ "hooks": [{ "type": "command", "command": "say done" }] });
+        let store = json!({ "hooks": { "Stop": [store_hook.clone()] } });
+        let live = json!({ "hooks": { "Stop": [managed_hook(), hand_hook.clone()] } });
+
+        let changes = detect_store_drift(&store, &live, &[]);
+        let reverted = revert_drift(&live, &changes);
+
+        assert_eq!(changes.len(), 1);
+        assert_eq!(
+            reverted,
+            json!({ "hooks": { "Stop": [managed_hook(), hand_hook, store_hook] } })
+        );
+    }
+}
@@ -0,0 +1,365 @@
+//! Reversible merge of ConfigStore settings into ~/.claude/settings.json
+//!
+//! Activating a store records the exact key paths it contributed. The next
+//! activation strips those paths before deep-merging the new store, so keys
+//! the previous store set don't leak, and keys added by hand are preserved.
+//!
+//! Each `hooks.<Event>` array is shared with hooks added by hand and the ones
+//! this app installs, so stores add their entries to it one by one and only
+//! those entries are removed again.
+
+use crate::settings_drift::is_managed_entry;
+use serde_json::{Map, Value};
+
+/// Top-level keys whose objects are deep-merged instead of replaced
+const NESTED_KEYS: [&str; 3] = ["env", "permissions", "hooks"];
+
+const HOOKS_KEY: &str = "hooks";
+
+/// Path to a value inside settings.json, e.g. `["env", "ANTHROPIC_BASE_URL"]`.
+/// An entry of a `hooks.<Event>` array is addressed by its JSON text, e.g.
+/// `["hooks", "Stop", "{\"hooks\":[...]}"]`, since array indexes shift.
+pub type KeyPath = Vec<String>;
+
+/// Whether `path` names a whole `hooks.<Event>` array
+pub(crate) fn is_hook_event(path: &[String]) -> bool {
+    path.len() == 2 && path[0] == HOOKS_KEY
+}
+
+/// Position of the entry a path segment names in a `hooks.<Event>` array
+fn entry_index(entries: &[Value], segment: &str) -> Option<usize> {
+    let entry: Value = serde_json::from_str(segment).ok()?;
+    entries.iter().position(|e| *e == entry)
+}
+
+/// The value at `path`, if any
+pub fn get_path<'a>(value: &'a Value, path: &[String]) -> Option<&'a Value> {
+    path.iter().try_fold(value, |current, key| match current {
+        Value::Array(entries) => entry_index(entries, key).map(|i| &entries[i]),
+        _ => current.get(key),
+    })
+}
+
+/// Set the value at `path`, creating parent objects as needed. A hook entry
+/// is added to its event array unless it's already there.
+pub fn set_path(value: &mut Value, path: &[String], new_value: Value) {
+    let Some((last, parents)) = path.split_last() else {
+        *value = new_value;
+        return;
+    };
+
+    let mut current = value;
+    for (depth, key) in parents.iter().enumerate() {
+        if !current.is_object() {
+            *current = Value::Object(Map::new());
+        }
+        let is_event = is_hook_event(&path[..=depth]) && depth + 2 == path.len();
+        current = current
+            .as_object_mut()
+            .unwrap()
+            .entry(key.clone())
+            .or_insert_with(|| {
+                if is_event {
+                    Value::Array(vec![])
+                } else {
+                    Value::Object(Map::new())
+                }
+            });
+    }
+
+    match current {
+        Value::Array(entries) => {
+            if entry_index(entries, last).is_none() {
+                entries.push(new_value);
+            }
+        }
+        _ => {
+            if !current.is_object() {
+                *current = Value::Object(Map::new());
+            }
+            current
+                .as_object_mut()
+                .unwrap()
+                .insert(last.clone(), new_value);
+        }
+    }
+}
+
+/// Remove the value at `path` like `apply_settings` does, pruning what's left empty
+pub fn remove_at(value: &mut Value, path: &[String]) {
+    match value.as_object_mut() {
+        Some(obj) => {
+            remove_path(obj, path);
+        }
+        None if path.is_empty() => *value = Value::Object(Map::new()),
+        None => {}
+    }
+}
+
+/// Remove `previous` paths from `live`, then merge `settings` into it.
+///
+/// Returns the merged settings and the paths contributed by `settings`.
+pub fn apply_settings(
+    live: &Value,
+    previous: &[KeyPath],
+    settings: &Value,
+) -> (Value, Vec<KeyPath>) {
+    // Non-object settings replace the file entirely, as before
+    let Some(settings_obj) = settings.as_object() else {
+        return (settings.clone(), vec![]);
+    };
+
+    let mut merged = match live {
+        Value::Object(obj) => obj.clone(),
+        _ => Map::new(),
+    };
+
+    // Stores only ever own single hook entries, never a whole `hooks.<event>` array
+    for path in previous.iter().filter(|path| !is_hook_event(path)) {
+        remove_path(&mut merged, path);
+    }
+
+    let mut applied = Vec::new();
+    for (key, value) in settings_obj {
+        let path = vec![key.clone()];
+        if NESTED_KEYS.contains(&key.as_str()) && value.is_object() {
+            deep_merge(&mut merged, key, value, &path, &mut applied);
+        } else {
+            merged.insert(key.clone(), value.clone());
+            applied.push(path);
+        }
+    }
+
+    (Value::Object(merged), applied)
+}
+
+/// Remove the value at `path`, pruning parent objects left empty by the removal
+fn remove_path(obj: &mut Map<String, Value>, path: &[String]) -> bool {
+    match path {
+        [] => false,
+        [key] => obj.remove(key).is_some(),
+        [key, segment] if obj.get(key).is_some_and(|v| v.is_array()) => {
+            let entries = obj.get_mut(key).and_then(|v| v.as_array_mut()).unwrap();
+            let Some(index) = entry_index(entries, segment) else {
+                return false;
+            };
+            entries.remove(index);
+            if entries.is_empty() {
+                obj.remove(key);
+            }
+            true
+        }
+        [key, rest @ ..] => {
+            let Some(child) = obj.get_mut(key).and_then(|v| v.as_object_mut()) else {
+                return false;
+            };
+            let removed = remove_path(child, rest);
+            if removed && child.is_empty() {
+                obj.remove(key);
+            }
+            removed
+        }
+    }
+}
+
+/// Add the store's entries to `target[key]`, a `hooks.<Event>` array,
+/// recording the ones it added. Entries already there are left alone, and
+/// entries of the app's own hooks aren't copied from stores.
+fn merge_hook_entries(
+    target: &mut Map<String, Value>,
+    key: &str,
+    entries: &[Value],
+    path: &KeyPath,
+    applied: &mut Vec<KeyPath>,
+) {
+    let existing = target
+        .entry(key.to_string())
+        .or_insert_with(|| Value::Array(vec![]));
+    if !existing.is_array() {
+        *existing = Value::Array(vec![]);
+    }
+    let existing = existing.as_array_mut().unwrap();
+
+    for entry in entries {
+        if is_managed_entry(entry) || existing.contains(entry) {
+            continue;
+        }
+        existing.push(entry.clone());
+
+        let mut entry_path = path.clone();
+        entry_path.push(entry.to_string());
+        applied.push(entry_path);
+    }
+
+    if existing.is_empty() {
+        target.remove(key);
+    }
+}
+
+/// Merge `value` into `target[key]`, recording every leaf path written
+fn deep_merge(
+    target: &mut Map<String, Value>,
+    key: &str,
+    value: &Value,
+    path: &KeyPath,
+    applied: &mut Vec<KeyPath>,
+) {
+    match value {
+        Value::Array(entries) if is_hook_event(path) => {
+            merge_hook_entries(target, key, entries, path, applied)
+        }
+        Value::Object(value_obj) => {
+            let entry = target
+                .entry(key.to_string())
+                .or_insert_with(|| Value::Object(Map::new()));
+            if !entry.is_object() {
+                *entry = Value::Object(Map::new());
+            }
+            let entry_obj = entry.as_object_mut().unwrap();
+
+            for (child_key, child_value) in value_obj {
+                let mut child_path = path.clone();
+                child_path.push(child_key.clone());
+                deep_merge(entry_obj, child_key, child_value, &child_path, applied);
+            }
+        }
+        _ => {
+            target.insert(key.to_string(), value.clone());
+            applied.push(path.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn path(parts: &[&str]) -> KeyPath {
+        parts.iter().map(|p| p.to_string()).collect()
+    }
+
+    #[test]
+    fn test_apply_settings_records_leaf_paths() {
+        let live = json!({});
+        let settings = json!({
+            "model": "opus",
+            "env": { "ANTHROPIC_BASE_URL": "http://proxy", "API_TIMEOUT_MS": "1000" }
+        });
+
+        let (merged, applied) = apply_settings(&live, &[], &settings);
+
+        assert_eq!(merged, settings);
+        assert!(applied.contains(&path(&["model"])));
+        assert!(applied.contains(&path(&["env", "ANTHROPIC_BASE_URL"])));
+        assert!(applied.contains(&path(&["env", "API_TIMEOUT_MS"])));
+        assert_eq!(applied.len(), 3);
+    }
+
+    #[test]
+    fn test_apply_settings_removes_previous_store_keys() {
+        let live = json!({
+            "env": { "ANTHROPIC_BASE_URL": "http://proxy", "ANTHROPIC_AUTH_TOKEN": "a" }
+        });
+        let previous = vec![
+            path(&["env", "ANTHROPIC_BASE_URL"]),
+            path(&["env", "ANTHROPIC_AUTH_TOKEN"]),
+        ];
+        let settings = json!({ "env": { "ANTHROPIC_API_KEY": "b" } });
+
+        let (merged, _) = apply_settings(&live, &previous, &settings);
+
+        assert_eq!(merged, json!({ "env": { "ANTHROPIC_API_KEY": "b" } }));
+    }
+
+    #[test]
+    fn test_apply_settings_preserves_user_keys() {
+        let live = json!({
+            "env": { "ANTHROPIC_BASE_URL": "http://proxy", "MY_VAR": "mine" },
+            "permissions": { "allow": ["Bash(ls)"], "defaultMode": "plan" },
+            "includeCoAuthoredBy": false
+        });
+        let previous = vec![
+            path(&["env", "ANTHROPIC_BASE_URL"]),
+            path(&["permissions", "allow"]),
+        ];
+        let settings = json!({ "permissions": { "deny": ["Read(.env)"] } });
+
+        let (merged, applied) = apply_settings(&live, &previous, &settings);
+
+        assert_eq!(
+            merged,
+            json!({
+                "env": { "MY_VAR": "mine" },
+                "permissions": { "defaultMode": "plan", "deny": ["Read(.env)"] },
+                "includeCoAuthoredBy": false
+            })
+        );
+        assert_eq!(applied, vec![path(&["permissions", "deny"])]);
+    }
+
+    #[test]
+    fn test_apply_settings_prunes_emptied_objects() {
+        let live = json!({ "env": { "ANTHROPIC_BASE_URL": "http://proxy" }, "model": "opus" });
+        let previous = vec![path(&["env", "ANTHROPIC_BASE_URL"]), path(&["model"])];
+
+        let (merged, applied) = apply_settings(&live, &previous, &json!({}));
+
+        assert_eq!(merged, json!({}));
+        assert!(applied.is_empty());
+    }
+
+    #[test]
+    fn test_apply_settings_replaces_non_nested_keys() {
+        let live = json!({ "statusLine": { "type": "command", "command": "a", "padding": 1 } });
+        let settings = json!({ "statusLine": { "type": "command", "command": "b" } });
+
+        let (merged, applied) = apply_settings(&live, &[], &settings);
+
+        assert_eq!(merged, settings);
+        assert_eq!(applied, vec![path(&["statusLine"])]);
+    }
+
+    #[test]
+    fn test_apply_settings_merges_hook_entries() {
+        let managed = json!({
+            "hooks": [{ "type": "command", "command": "curl ...", "__ccfoundation__": true }]
+        });
+        let mine = json!({ "hooks": [{ "type": "command", "command": "say done" }] });
+        let lint =
+            json!({ "matcher": "Bash", "hooks": [{ "type": "command", "command": "lint" }] });
+        let live = json!({ "hooks": { "Stop": [managed.clone(), mine.clone()] } });
+        let settings = json!({ "hooks": { "Stop": [lint.clone()], "PreToolUse": [lint.clone()] } });
+
+        let (merged, applied) = apply_settings(&live, &[], &settings);
+
+        assert_eq!(
+            merged,
+            json!({
+                "hooks": {
+                    "Stop": [managed.clone(), mine.clone(), lint.clone()],
+                    "PreToolUse": [lint.clone()]
+                }
+            })
+        );
+        assert_eq!(applied.len(), 2);
+        assert_eq!(get_path(&merged, &applied[0]), Some(&lint));
+
+        // Switching away removes only the store's entries
+        let (switched, applied) = apply_settings(&merged, &applied, &json!({}));
+        assert_eq!(
+            switched,
+            json!({ "hooks": { "Stop": [managed.clone(), mine] } })
+        );
+        assert!(applied.is_empty());
+    }
+
+    #[test]
+    fn test_apply_settings_non_object_replaces_everything() {
+        let live = json!({ "model": "opus" });
+        let (merged, applied) = apply_settings(&live, &[], &json!(null));
+
+        assert_eq!(merged, json!(null));
+        assert!(applied.is_empty());
+    }
+}
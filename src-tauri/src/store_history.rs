@@ -0,0 +1,294 @@
+//! Revision history for ConfigStore settings
+//!
+//! Each store keeps its own log in ~/.ccconfig/revisions/{store_id}.json. A
+//! revision holds the full settings snapshot plus the diff against the
+//! revision before it, so any two revisions can be compared or restored.
+
+use crate::managed_file;
+use crate::settings_diff::{diff_settings, SettingsChange};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+const REVISIONS_DIR: &str = ".ccconfig/revisions";
+
+/// Oldest revisions beyond this count are dropped
+const MAX_REVISIONS: usize = 100;
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct StoreRevision {
+    pub revision: u32,
+    pub timestamp: u64,
+    pub message: Option<String>,
+    pub settings: Value,
+    /// Changes relative to the previous revision (everything is "added" for the first one)
+    pub changes: Vec<SettingsChange>,
+}
+
+fn get_revisions_dir() -> Result<PathBuf, String> {
+    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
+    Ok(home_dir.join(REVISIONS_DIR))
+}
+
+fn get_revisions_path(revisions_dir: &Path, store_id: &str) -> Result<PathBuf, String> {
+    // Store IDs come from the frontend, so guard against path traversal
+    if store_id.is_empty()
+        || store_id.contains("..")
+        || store_id.contains('/')
+        || store_id.contains('\\')
+    {
+        return Err(format!("Invalid store id: {}", store_id));
+    }
+
+    Ok(revisions_dir.join(format!("{}.json", store_id)))
+}
+
+fn read_revisions_in(revisions_dir: &Path, store_id: &str) -> Result<Vec<StoreRevision>, String> {
+    let path = get_revisions_path(revisions_dir, store_id)?;
+
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read revisions file: {}", e))?;
+
+    serde_json::from_str(&content).map_err(|e| format!("Failed to parse revisions file: {}", e))
+}
+
+fn write_revisions_in(
+    revisions_dir: &Path,
+    store_id: &str,
+    revisions: &[StoreRevision],
+) -> Result<(), String> {
+    let path = get_revisions_path(revisions_dir, store_id)?;
+
+    let json_content = serde_json::to_string_pretty(revisions)
+        .map_err(|e| format!("Failed to serialize revisions: {}", e))?;
+
+    managed_file::write(&path, &json_content)
+        .map_err(|e| format!("Failed to write revisions file: {}", e))
+}
+
+fn record_revision_in(
+    revisions_dir: &Path,
+    store_id: &str,
+    settings: &Value,
+    message: Option<String>,
+) -> Result<StoreRevision, String> {
+    let mut revisions = read_revisions_in(revisions_dir, store_id)?;
+
+    let (revision, previous_settings) = match revisions.last() {
+        // Nothing changed since the last revision - don't record a duplicate
+        Some(last) if last.settings == *settings && message.is_none() => return Ok(last.clone()),
+        Some(last) => (last.revision + 1, last.settings.clone()),
+        None => (1, Value::Object(serde_json::Map::new())),
+    };
+
+    let new_revision = StoreRevision {
+        revision,
+        timestamp: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_err(|e| format!("Failed to get timestamp: {}", e))?
+            .as_secs(),
+        message,
+        settings: settings.clone(),
+        changes: diff_settings(&previous_settings, settings),
+    };
+
+    revisions.push(new_revision.clone());
+    if revisions.len() > MAX_REVISIONS {
+        let excess = revisions.len() - MAX_REVISIONS;
+        revisions.drain(..excess);
+    }
+
+    write_revisions_in(revisions_dir, store_id, &revisions)?;
+    Ok(new_revision)
+}
+
+/// Read all revisions of a store, oldest first
+pub fn read_revisions(store_id: &str) -> Result<Vec<StoreRevision>, String> {
+    read_revisions_in(&get_revisions_dir()?, store_id)
+}
+
+/// Get a single revision of a store
+pub fn get_revision(store_id: &str, revision: u32) -> Result<StoreRevision, String> {
+    read_revisions(store_id)?
+        .into_iter()
+        .find(|r| r.revision == revision)
+        .ok_or_else(|| format!("Revision {} not found for store '{}'", revision, store_id))
+}
+
+/// Append a revision for the given settings, unless they match the latest one
+pub fn record_revision(
+    store_id: &str,
+    settings: &Value,
+    message: Option<String>,
+) -> Result<StoreRevision, String> {
+    record_revision_in(&get_revisions_dir()?, store_id, settings, message)
+}
+
+pub(crate) fn replace_strings(value: &mut Value, replacements: &HashMap<String, String>) {
+    match value {
+        Value::String(s) => {
+            if let Some(replacement) = replacements.get(s.as_str()) {
+                *s = replacement.clone();
+            }
+        }
+        Value::Array(items) => items
+            .iter_mut()
+            .for_each(|item| replace_strings(item, replacements)),
+        Value::Object(obj) => obj
+            .values_mut()
+            .for_each(|item| replace_strings(item, replacements)),
+        _ => {}
+    }
+}
+
+fn replace_values_in(
+    revisions_dir: &Path,
+    store_id: &str,
+    replacements: &HashMap<String, String>,
+) -> Result<(), String> {
+    let mut revisions = read_revisions_in(revisions_dir, store_id)?;
+    if revisions.is_empty() {
+        return Ok(());
+    }
+
+    for revision in &mut revisions {
+        replace_strings(&mut revision.settings, replacements);
+        for change in &mut revision.changes {
+            for value in [&mut change.old_value, &mut change.new_value]
+                .into_iter()
+                .flatten()
+            {
+                replace_strings(value, replacements);
+            }
+        }
+    }
+
+    write_revisions_in(revisions_dir, store_id, &revisions)
+}
+
+/// Rewrite string values throughout a store's history, e.g. to swap plaintext
+/// secrets for vault references
+pub fn replace_values(
+    store_id: &str,
+    replacements: &HashMap<String, String>,
+) -> Result<(), String> {
+    replace_values_in(&get_revisions_dir()?, store_id, replacements)
+}
+
+/// Remove a store's revision log
+pub fn delete_revisions(store_id: &str) -> Result<(), String> {
+    let path = get_revisions_path(&get_revisions_dir()?, store_id)?;
+
+    if path.exists() {
+        std::fs::remove_file(&path)
+            .map_err(|e| format!("Failed to delete revisions file: {}", e))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_record_revision_increments_and_diffs() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let first =
+            record_revision_in(dir.path(), "abc123", &json!({ "model": "opus" }), None).unwrap();
+        let second = record_revision_in(
+            dir.path(),
+            "abc123",
+            &json!({ "model": "sonnet" }),
+            Some("Switch model".to_string()),
+        )
+        .unwrap();
+
+        assert_eq!(first.revision, 1);
+        assert_eq!(first.changes.len(), 1);
+        assert_eq!(second.revision, 2);
+        assert_eq!(second.message.as_deref(), Some("Switch model"));
+        assert_eq!(second.changes.len(), 1);
+        assert_eq!(second.changes[0].old_value, Some(json!("opus")));
+
+        let revisions = read_revisions_in(dir.path(), "abc123").unwrap();
+        assert_eq!(revisions.len(), 2);
+    }
+
+    #[test]
+    fn test_record_revision_skips_unchanged_settings() {
+        let dir = tempfile::tempdir().unwrap();
+        let settings = json!({ "env": { "A": "1" } });
+
+        record_revision_in(dir.path(), "abc123", &settings, None).unwrap();
+        let again = record_revision_in(dir.path(), "abc123", &settings, None).unwrap();
+
+        assert_eq!(again.revision, 1);
+        assert_eq!(read_revisions_in(dir.path(), "abc123").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_record_revision_prunes_oldest() {
+        let dir = tempfile::tempdir().unwrap();
+
+        for i in 0..(MAX_REVISIONS + 5) {
+            record_revision_in(
+                dir.path(),
+                "abc123",
+                &json!({ "cleanupPeriodDays": i }),
+                None,
+            )
+            .unwrap();
+        }
+
+        let revisions = read_revisions_in(dir.path(), "abc123").unwrap();
+        assert_eq!(revisions.len(), MAX_REVISIONS);
+        assert_eq!(revisions[0].revision, 6);
+    }
+
+    #[test]
+    fn test_replace_values_rewrites_settings_and_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        record_revision_in(
+            dir.path(),
+            "abc123",
+            &json!({ "env": { "T": "sk-1" } }),
+            None,
+        )
+        .unwrap();
+        record_revision_in(
+            dir.path(),
+            "abc123",
+            &json!({ "env": { "T": "sk-2" } }),
+            None,
+        )
+        .unwrap();
+
+        let replacements = HashMap::from([
+            ("sk-1".to_string(), "{{secret:a}}".to_string()),
+            ("sk-2".to_string(), "{{secret:b}}".to_string()),
+        ]);
+        replace_values_in(dir.path(), "abc123", &replacements).unwrap();
+
+        let content = std::fs::read_to_string(dir.path().join("abc123.json")).unwrap();
+        assert!(!content.contains("sk-1") && !content.contains("sk-2"));
+        let revisions = read_revisions_in(dir.path(), "abc123").unwrap();
+        assert_eq!(
+            revisions[1].changes[0].old_value,
+            Some(json!("{{secret:a}}"))
+        );
+    }
+
+    #[test]
+    fn test_revisions_path_rejects_traversal() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(get_revisions_path(dir.path(), "../stores").is_err());
+        assert!(get_revisions_path(dir.path(), "").is_err());
+    }
+}
@@ -0,0 +1,276 @@
+//! Layered ConfigStores
+//!
+//! A store may name a parent store. Its effective settings are the chain's
+//! settings deep-merged from the root down, so a shared base store can carry
+//! permissions and hooks while overlays only change `env`. Objects merge key
+//! by key; any other value (including arrays) from a lower layer replaces the
+//! inherited one.
+
+use crate::commands::ConfigStore;
+use crate::settings_merge::KeyPath;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+/// Which store a resolved setting came from
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct SettingSource {
+    pub path: KeyPath,
+    #[serde(rename = "storeId")]
+    pub store_id: String,
+}
+
+/// Effective settings of a store after applying its parents
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct ResolvedSettings {
+    #[serde(rename = "storeId")]
+    pub store_id: String,
+    /// Store ids from the root ancestor down to the store itself
+    pub chain: Vec<String>,
+    pub settings: Value,
+    pub sources: Vec<SettingSource>,
+}
+
+/// Walk from `store_id` up through its parents, returning the chain root first
+pub fn resolve_chain<'a>(
+    stores: &'a [ConfigStore],
+    store_id: &str,
+) -> Result<Vec<&'a ConfigStore>, String> {
+    let mut chain: Vec<&ConfigStore> = Vec::new();
+    let mut next_id = Some(store_id);
+
+    while let Some(id) = next_id {
+        if chain.iter().any(|store| store.id == id) {
+            let mut cycle: Vec<&str> = chain.iter().map(|store| store.id.as_str()).collect();
+            cycle.push(id);
+            return Err(format!("Store inheritance cycle: {}", cycle.join(" -> ")));
+        }
+
+        let store =
+            stores
+                .iter()
+                .find(|store| store.id == id)
+                .ok_or_else(|| match chain.last() {
+                    Some(child) => format!("Parent store '{}' of '{}' not found", id, child.title),
+                    None => format!("Store with id '{}' not found", id),
+                })?;
+
+        chain.push(store);
+        next_id = store.parent_id.as_deref();
+    }
+
+    chain.reverse();
+    Ok(chain)
+}
+
+/// Deep-merge the settings of `store_id` and its ancestors, tracking where each key came from
+pub fn resolve_settings(
+    stores: &[ConfigStore],
+    store_id: &str,
+) -> Result<ResolvedSettings, String> {
+    let chain = resolve_chain(stores, store_id)?;
+
+    let mut merged = Map::new();
+    let mut sources = BTreeMap::new();
+
+    for store in &chain {
+        // Settings are validated as objects on write, so anything else has nothing to layer
+        let Some(layer) = store.settings.as_object() else {
+            continue;
+        };
+        merge_layer(&mut merged, layer, &mut Vec::new(), &store.id, &mut sources);
+    }
+
+    Ok(ResolvedSettings {
+        store_id: store_id.to_string(),
+        chain: chain.iter().map(|store| store.id.clone()).collect(),
+        settings: Value::Object(merged),
+        sources: sources
+            .into_iter()
+            .map(|(path, store_id)| SettingSource { path, store_id })
+            .collect(),
+    })
+}
+
+/// Remove values from `settings` that `inherited` already provides, so a
+/// child only stores what it overrides
+pub fn strip_inherited(settings: &Value, inherited: &Value) -> Value {
+    match (settings, inherited) {
+        (Value::Object(obj), Value::Object(inherited_obj)) => {
+            let mut stripped = Map::new();
+            for (key, value) in obj {
+                match inherited_obj.get(key) {
+                    Some(inherited_value) if inherited_value == value => {}
+                    Some(inherited_value) if value.is_object() && inherited_value.is_object() => {
+                        stripped.insert(key.clone(), strip_inherited(value, inherited_value));
+                    }
+                    _ => {
+                        stripped.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+            Value::Object(stripped)
+        }
+        _ => settings.clone(),
+    }
+}
+
+fn merge_layer(
+    target: &mut Map<String, Value>,
+    layer: &Map<String, Value>,
+    path: &mut KeyPath,
+    store_id: &str,
+    sources: &mut BTreeMap<KeyPath, String>,
+) {
+    for (key, value) in layer {
+        path.push(key.clone());
+        match (target.get_mut(key), value) {
+            (Some(Value::Object(target_obj)), Value::Object(layer_obj)) => {
+                merge_layer(target_obj, layer_obj, path, store_id, sources);
+            }
+            _ => {
+                sources.retain(|source_path, _| !source_path.starts_with(path));
+                target.insert(key.clone(), value.clone());
+                record_sources(value, path, store_id, sources);
+            }
+        }
+        path.pop();
+    }
+}
+
+fn record_sources(
+    value: &Value,
+    path: &mut KeyPath,
+    store_id: &str,
+    sources: &mut BTreeMap<KeyPath, String>,
+) {
+    match value {
+        Value::Object(obj) if !obj.is_empty() => {
+            for (key, child) in obj {
+                path.push(key.clone());
+                record_sources(child, path, store_id, sources);
+                path.pop();
+            }
+        }
+        _ => {
+            sources.insert(path.clone(), store_id.to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn store(id: &str, parent_id: Option<&str>, settings: Value) -> ConfigStore {
+        ConfigStore {
+            id: id.to_string(),
+            title: id.to_string(),
+            created_at: 0,
+            settings,
+            using: false,
+            parent_id: parent_id.map(String::from),
+        }
+    }
+
+    #[test]
+    fn test_resolve_settings_merges_down_the_chain() {
+        let stores = vec![
+            store(
+                "base",
+                None,
+                json!({
+                    "permissions": { "allow": ["Bash(ls)"] },
+                    "env": { "DISABLE_TELEMETRY": "1", "ANTHROPIC_BASE_URL": "http://a" }
+                }),
+            ),
+            store(
+                "overlay",
+                Some("base"),
+                json!({ "env": { "ANTHROPIC_BASE_URL": "http://b" } }),
+            ),
+        ];
+
+        let resolved = resolve_settings(&stores, "overlay").unwrap();
+
+        assert_eq!(resolved.chain, vec!["base", "overlay"]);
+        assert_eq!(
+            resolved.settings,
+            json!({
+                "permissions": { "allow": ["Bash(ls)"] },
+                "env": { "DISABLE_TELEMETRY": "1", "ANTHROPIC_BASE_URL": "http://b" }
+            })
+        );
+
+        let source_of = |path: &[&str]| {
+            resolved
+                .sources
+                .iter()
+                .find(|s| s.path == path)
+                .map(|s| s.store_id.clone())
+        };
+        assert_eq!(
+            source_of(&["env", "ANTHROPIC_BASE_URL"]).as_deref(),
+            Some("overlay")
+        );
+        assert_eq!(
+            source_of(&["env", "DISABLE_TELEMETRY"]).as_deref(),
+            Some("base")
+        );
+        assert_eq!(
+            source_of(&["permissions", "allow"]).as_deref(),
+            Some("base")
+        );
+    }
+
+    #[test]
+    fn test_resolve_settings_replaced_object_drops_inherited_sources() {
+        let stores = vec![
+            store(
+                "base",
+                None,
+                json!({ "statusLine": { "type": "command", "command": "a" } }),
+            ),
+            store("child", Some("base"), json!({ "statusLine": "off" })),
+        ];
+
+        let resolved = resolve_settings(&stores, "child").unwrap();
+
+        assert_eq!(resolved.settings, json!({ "statusLine": "off" }));
+        assert_eq!(
+            resolved.sources,
+            vec![SettingSource {
+                path: vec!["statusLine".into()],
+                store_id: "child".into()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_resolve_chain_detects_cycles() {
+        let stores = vec![
+            store("a", Some("b"), json!({})),
+            store("b", Some("a"), json!({})),
+        ];
+
+        let err = resolve_chain(&stores, "a").unwrap_err();
+        assert!(err.contains("cycle"));
+    }
+
+    #[test]
+    fn test_resolve_chain_missing_parent() {
+        let stores = vec![store("a", Some("gone"), json!({}))];
+        assert!(resolve_chain(&stores, "a").is_err());
+    }
+
+    #[test]
+    fn test_strip_inherited_keeps_overrides_only() {
+        let inherited = json!({ "env": { "A": "1", "B": "2" }, "model": "opus" });
+        let settings = json!({ "env": { "A": "1", "B": "3" }, "model": "opus", "x": true });
+
+        assert_eq!(
+            strip_inherited(&settings, &inherited),
+            json!({ "env": { "B": "3" }, "x": true })
+        );
+    }
+}
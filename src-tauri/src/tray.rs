@@ -1,16 +1,150 @@
 use tauri::{
     image::Image,
-    menu::{MenuBuilder, MenuItemBuilder},
+    menu::{CheckMenuItemBuilder, MenuBuilder, MenuItemBuilder, Submenu, SubmenuBuilder},
     tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent},
     AppHandle, Manager, Runtime,
 };
 use tauri_plugin_notification::NotificationExt;
 
-use crate::commands::{get_store, get_stores, set_using_config};
+use crate::commands::{
+    disable_mcp_server, enable_mcp_server, get_project_registry, get_store, get_stores,
+    list_mcp_server_toggles, set_using_config,
+};
+use crate::mcp::resolver::McpScope;
+use crate::mcp::transfer::McpLocation;
 
 // Store the tray icon ID globally
 const TRAY_ID: &str = "main-tray";
 
+/// Prefix of MCP toggle ids: `mcp:{scope}:{server}:{project path}`, with `%`
+/// and `:` escaped in the server name and project path (Windows paths and
+/// server names may contain `:`)
+const MCP_ITEM_PREFIX: &str = "mcp:";
+
+fn encode_id_segment(segment: &str) -> String {
+    segment.replace('%', "%25").replace(':', "%3A")
+}
+
+fn decode_id_segment(segment: &str) -> String {
+    segment.replace("%3A", ":").replace("%25", "%")
+}
+
+fn mcp_item_id(location: &McpLocation, server_name: &str) -> String {
+    let scope = match location.scope {
+        McpScope::Project => "project",
+        McpScope::Local => "local",
+        _ => "user",
+    };
+    format!(
+        "{}{}:{}:{}",
+        MCP_ITEM_PREFIX,
+        scope,
+        encode_id_segment(server_name),
+        encode_id_segment(location.project_path.as_deref().unwrap_or_default())
+    )
+}
+
+fn parse_mcp_item_id(id: &str) -> Option<(McpLocation, String)> {
+    let parts: Vec<&str> = id.strip_prefix(MCP_ITEM_PREFIX)?.split(':').collect();
+    let [scope, server_name, project_path] = parts[..] else {
+        return None;
+    };
+    let project_path = Some(decode_id_segment(project_path));
+
+    let location = match scope {
+        "user" => McpLocation {
+            scope: McpScope::User,
+            project_path: None,
+        },
+        "local" => McpLocation {
+            scope: McpScope::Local,
+            project_path,
+        },
+        "project" => McpLocation {
+            scope: McpScope::Project,
+            project_path,
+        },
+        _ => return None,
+    };
+    Some((location, decode_id_segment(server_name)))
+}
+
+/// Check items for each server of a project's local and project scopes, or
+/// None when it has none
+async fn build_mcp_submenu<R: Runtime>(
+    app: &AppHandle<R>,
+    title: &str,
+    project_path: &str,
+) -> Result<Option<Submenu<R>>, Box<dyn std::error::Error>> {
+    let mut builder = SubmenuBuilder::new(app, title);
+    let mut empty = true;
+
+    for scope in [McpScope::Local, McpScope::Project] {
+        let location = McpLocation {
+            scope,
+            project_path: Some(project_path.to_string()),
+        };
+        for toggle in list_mcp_server_toggles(location.clone()).await? {
+            // Private servers from ~/.claude.json next to the shared .mcp.json ones
+            let label = match location.scope {
+                McpScope::Local => format!("{} (local)", toggle.name),
+                _ => toggle.name.clone(),
+            };
+            let item = CheckMenuItemBuilder::with_id(mcp_item_id(&location, &toggle.name), label)
+                .checked(toggle.enabled)
+                .build(app)?;
+            builder = builder.item(&item);
+            empty = false;
+        }
+    }
+
+    if empty {
+        return Ok(None);
+    }
+    Ok(Some(builder.build()?))
+}
+
+/// "MCP Servers" submenu with global servers and one submenu per project
+async fn build_mcp_menu<R: Runtime>(
+    app: &AppHandle<R>,
+) -> Result<Option<Submenu<R>>, Box<dyn std::error::Error>> {
+    let user = McpLocation {
+        scope: McpScope::User,
+        project_path: None,
+    };
+    let toggles = list_mcp_server_toggles(user.clone()).await?;
+
+    let mut builder = SubmenuBuilder::new(app, "MCP Servers");
+    let mut empty = true;
+    for toggle in toggles {
+        let item = CheckMenuItemBuilder::with_id(mcp_item_id(&user, &toggle.name), &toggle.name)
+            .checked(toggle.enabled)
+            .build(app)?;
+        builder = builder.item(&item);
+        empty = false;
+    }
+
+    let mut projects = get_project_registry().await.unwrap_or_default();
+    projects.sort_by(|a, b| a.title.cmp(&b.title));
+    for project in projects {
+        // A project with an unreadable .mcp.json shouldn't take the whole tray down
+        if let Ok(Some(submenu)) =
+            build_mcp_submenu(app, &project.title, &project.project_path).await
+        {
+            if !empty {
+                builder = builder.separator();
+            }
+            builder = builder.item(&submenu);
+            empty = false;
+        }
+    }
+
+    if empty {
+        return Ok(None);
+    }
+    Ok(Some(builder.build()?))
+}
+
 pub fn create_tray<R: Runtime>(app: &AppHandle<R>) -> Result<(), Box<dyn std::error::Error>> {
     println!("🔧 Creating system tray icon...");
 
@@ -146,6 +280,17 @@ pub async fn build_tray_menu<R: Runtime>(
                 let separator = tauri::menu::PredefinedMenuItem::separator(app)?;
                 builder = builder.item(&separator);
 
+                // Add MCP server toggles
+                match build_mcp_menu(app).await {
+                    Ok(Some(mcp_menu)) => {
+                        builder = builder.item(&mcp_menu);
+                        let separator = tauri::menu::PredefinedMenuItem::separator(app)?;
+                        builder = builder.item(&separator);
+                    }
+                    Ok(None) => {}
+                    Err(e) => eprintln!("Failed to load MCP servers for tray menu: {}", e),
+                }
+
                 // Add "Quit" item
                 let quit_item = MenuItemBuilder::with_id(
                     "quit_app",
@@ -213,6 +358,48 @@ pub fn handle_tray_menu_event<R: Runtime>(app_handle: &AppHandle<R>, event_id: &
             app_handle.exit(0);
             true
         }
+        id if id.starts_with(MCP_ITEM_PREFIX) => {
+            let Some((location, server_name)) = parse_mcp_item_id(id) else {
+                return false;
+            };
+            let app_clone = app_handle.clone();
+
+            tauri::async_runtime::spawn(async move {
+                let enabled = list_mcp_server_toggles(location.clone())
+                    .await
+                    .map(|toggles| toggles.iter().any(|t| t.name == server_name && t.enabled))
+                    .unwrap_or(false);
+
+                let result = if enabled {
+                    disable_mcp_server(location, server_name.clone()).await
+                } else {
+                    enable_mcp_server(location, server_name.clone()).await
+                };
+
+                match result {
+                    Ok(_) => println!(
+                        "✅ MCP server {} {}",
+                        server_name,
+                        if enabled { "disabled" } else { "enabled" }
+                    ),
+                    Err(e) => {
+                        eprintln!("❌ Failed to toggle MCP server: {}", e);
+                        let _ = app_clone
+                            .notification()
+                            .builder()
+                            .title("CC Foundation")
+                            .body(format!("Error: {}", e))
+                            .show();
+                    }
+                }
+
+                // Rebuild either way so the checkmark matches the files
+                if let Err(e) = rebuild_tray_menu(app_clone).await {
+                    eprintln!("❌ Failed to rebuild tray menu: {}", e);
+                }
+            });
+            true
+        }
         id if id.starts_with("config_") => {
             // Extract store ID from the menu item ID and convert to owned String
             let store_id = id.trim_start_matches("config_").to_string();
@@ -271,3 +458,24 @@ pub fn handle_tray_menu_event<R: Runtime>(app_handle: &AppHandle<R>, event_id: &
         _ => false,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mcp_item_id_round_trips_colons() {
+        let location = McpLocation {
+            scope: McpScope::Local,
+            project_path: Some("C:\\work\\100%:app".to_string()),
+        };
+
+        let id = mcp_item_id(&location, "db:prod");
+
+        assert_eq!(
+            parse_mcp_item_id(&id),
+            Some((location, "db:prod".to_string()))
+        );
+        assert_eq!(parse_mcp_item_id("mcp:project:a:b:c"), None);
+    }
+}
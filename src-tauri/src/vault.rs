@@ -0,0 +1,627 @@
+//! Encrypted secrets vault
+//!
+//! Secrets live in ~/.ccconfig/vault.json, each encrypted with
+//! ChaCha20-Poly1305 under a key derived from the user's passphrase with
+//! scrypt. Stores reference them as `{{secret:name}}` and the references are
+//! only replaced with plaintext when settings are written to settings.json,
+//! so stores.json, revisions and bundles never hold the raw values.
+//!
+//! The derived key stays in memory while the vault is unlocked, so switching
+//! stores from the tray works without asking for the passphrase again.
+
+use crate::managed_file::{self, Snapshot};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const VAULT_FILE: &str = ".ccconfig/vault.json";
+
+/// Bumped whenever the vault file layout changes incompatibly
+const VAULT_VERSION: u32 = 1;
+
+const KEY_LEN: usize = 32;
+const SALT_LEN: usize = 16;
+
+/// Encrypted when the vault is created, so a wrong passphrase fails to decrypt it
+const VERIFIER: &str = "ccfoundation-vault";
+
+const REF_PREFIX: &str = "{{secret:";
+const REF_SUFFIX: &str = "}}";
+
+type VaultKey = [u8; KEY_LEN];
+
+/// Key of the unlocked vault, `None` while locked
+static UNLOCKED_KEY: Mutex<Option<VaultKey>> = Mutex::new(None);
+
+/// scrypt cost parameters, stored with the vault so they can be raised later
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct KdfParams {
+    #[serde(rename = "logN")]
+    pub log_n: u8,
+    pub r: u32,
+    pub p: u32,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        KdfParams {
+            log_n: 15,
+            r: 8,
+            p: 1,
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+struct Sealed {
+    nonce: String,
+    ciphertext: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+struct VaultSecret {
+    #[serde(flatten)]
+    sealed: Sealed,
+    #[serde(rename = "createdAt")]
+    created_at: u64,
+    #[serde(rename = "updatedAt")]
+    updated_at: u64,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+struct VaultFile {
+    version: u32,
+    salt: String,
+    kdf: KdfParams,
+    verifier: Sealed,
+    #[serde(default)]
+    secrets: BTreeMap<String, VaultSecret>,
+    /// Set once plaintext tokens in stores.json have been moved into the vault
+    #[serde(default)]
+    migrated: bool,
+}
+
+/// A secret without its value
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct SecretInfo {
+    pub name: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: u64,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: u64,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct VaultStatus {
+    pub exists: bool,
+    pub unlocked: bool,
+    pub migrated: bool,
+    #[serde(rename = "secretCount")]
+    pub secret_count: usize,
+}
+
+fn now_secs() -> Result<u64, String> {
+    Ok(std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_err(|e| format!("Failed to get timestamp: {}", e))?
+        .as_secs())
+}
+
+fn validate_secret_name(name: &str) -> Result<(), String> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !valid {
+        return Err(format!(
+            "Invalid secret name '{}': use letters, digits, '-', '_' or '.'",
+            name
+        ));
+    }
+    Ok(())
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], params: KdfParams) -> Result<VaultKey, String> {
+    let params = scrypt::Params::new(params.log_n, params.r, params.p, KEY_LEN)
+        .map_err(|e| format!("Invalid vault key parameters: {}", e))?;
+
+    let mut key = [0u8; KEY_LEN];
+    scrypt::scrypt(passphrase.as_bytes(), salt, &params, &mut key)
+        .map_err(|e| format!("Failed to derive vault key: {}", e))?;
+    Ok(key)
+}
+
+/// Encrypt `plaintext`, binding it to `aad` (the secret name) so ciphertexts can't be swapped
+fn seal(key: &VaultKey, aad: &str, plaintext: &str) -> Result<Sealed, String> {
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(key));
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: plaintext.as_bytes(),
+                aad: aad.as_bytes(),
+            },
+        )
+        .map_err(|_| "Failed to encrypt secret".to_string())?;
+
+    Ok(Sealed {
+        nonce: BASE64.encode(nonce),
+        ciphertext: BASE64.encode(ciphertext),
+    })
+}
+
+fn open(key: &VaultKey, aad: &str, sealed: &Sealed) -> Result<String, String> {
+    let nonce = BASE64
+        .decode(&sealed.nonce)
+        .map_err(|e| format!("Corrupt vault entry '{}': {}", aad, e))?;
+    let ciphertext = BASE64
+        .decode(&sealed.ciphertext)
+        .map_err(|e| format!("Corrupt vault entry '{}': {}", aad, e))?;
+    if nonce.len() != 12 {
+        return Err(format!("Corrupt vault entry '{}': bad nonce", aad));
+    }
+
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(key));
+    let plaintext = cipher
+        .decrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: &ciphertext,
+                aad: aad.as_bytes(),
+            },
+        )
+        .map_err(|_| format!("Failed to decrypt vault entry '{}'", aad))?;
+
+    String::from_utf8(plaintext).map_err(|e| format!("Corrupt vault entry '{}': {}", aad, e))
+}
+
+fn read_vault_in(vault_path: &Path) -> Result<(Option<VaultFile>, Snapshot), String> {
+    let snapshot = managed_file::read(vault_path)?;
+
+    let vault = match snapshot.content() {
+        Some(content) => Some(
+            serde_json::from_str(content)
+                .map_err(|e| format!("Failed to parse vault file: {}", e))?,
+        ),
+        None => None,
+    };
+
+    Ok((vault, snapshot))
+}
+
+fn read_existing_vault_in(vault_path: &Path) -> Result<(VaultFile, Snapshot), String> {
+    match read_vault_in(vault_path)? {
+        (Some(vault), snapshot) => Ok((vault, snapshot)),
+        (None, _) => Err("Secret vault has not been created".to_string()),
+    }
+}
+
+fn write_vault_in(vault_path: &Path, snapshot: &Snapshot, vault: &VaultFile) -> Result<(), String> {
+    let json_content = serde_json::to_string_pretty(vault)
+        .map_err(|e| format!("Failed to serialize vault: {}", e))?;
+
+    managed_file::write_if_unchanged(vault_path, snapshot, &json_content)
+        .map_err(|e| format!("Failed to write vault file: {}", e))
+}
+
+fn create_vault_in(
+    vault_path: &Path,
+    passphrase: &str,
+    params: KdfParams,
+) -> Result<VaultKey, String> {
+    if passphrase.is_empty() {
+        return Err("Vault passphrase cannot be empty".to_string());
+    }
+
+    let (existing, snapshot) = read_vault_in(vault_path)?;
+    if existing.is_some() {
+        return Err("Secret vault already exists".to_string());
+    }
+
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let key = derive_key(passphrase, &salt, params)?;
+
+    let vault = VaultFile {
+        version: VAULT_VERSION,
+        salt: BASE64.encode(salt),
+        kdf: params,
+        verifier: seal(&key, VERIFIER, VERIFIER)?,
+        secrets: BTreeMap::new(),
+        migrated: false,
+    };
+
+    write_vault_in(vault_path, &snapshot, &vault)?;
+    Ok(key)
+}
+
+fn unlock_vault_in(vault_path: &Path, passphrase: &str) -> Result<VaultKey, String> {
+    let (vault, _) = read_existing_vault_in(vault_path)?;
+
+    if vault.version > VAULT_VERSION {
+        return Err(format!(
+            "Vault version {} is newer than supported version {}",
+            vault.version, VAULT_VERSION
+        ));
+    }
+
+    let salt = BASE64
+        .decode(&vault.salt)
+        .map_err(|e| format!("Corrupt vault salt: {}", e))?;
+    let key = derive_key(passphrase, &salt, vault.kdf)?;
+
+    match open(&key, VERIFIER, &vault.verifier) {
+        Ok(verifier) if verifier == VERIFIER => Ok(key),
+        _ => Err("Incorrect vault passphrase".to_string()),
+    }
+}
+
+fn list_secrets_in(vault_path: &Path) -> Result<Vec<SecretInfo>, String> {
+    let (vault, _) = read_existing_vault_in(vault_path)?;
+
+    Ok(vault
+        .secrets
+        .into_iter()
+        .map(|(name, secret)| SecretInfo {
+            name,
+            created_at: secret.created_at,
+            updated_at: secret.updated_at,
+        })
+        .collect())
+}
+
+/// Add a secret, or replace the value of an existing one when `rotate` is set
+fn store_secret_in(
+    vault_path: &Path,
+    key: &VaultKey,
+    name: &str,
+    value: &str,
+    rotate: bool,
+) -> Result<SecretInfo, String> {
+    validate_secret_name(name)?;
+
+    let (mut vault, snapshot) = read_existing_vault_in(vault_path)?;
+    let now = now_secs()?;
+
+    let created_at = match (vault.secrets.get(name), rotate) {
+        (Some(_), false) => {
+            return Err(format!(
+                "Secret '{}' already exists, rotate it instead",
+                name
+            ))
+        }
+        (None, true) => return Err(format!("Secret '{}' not found", name)),
+        (Some(existing), true) => existing.created_at,
+        (None, false) => now,
+    };
+
+    vault.secrets.insert(
+        name.to_string(),
+        VaultSecret {
+            sealed: seal(key, name, value)?,
+            created_at,
+            updated_at: now,
+        },
+    );
+    write_vault_in(vault_path, &snapshot, &vault)?;
+
+    Ok(SecretInfo {
+        name: name.to_string(),
+        created_at,
+        updated_at: now,
+    })
+}
+
+fn delete_secret_in(vault_path: &Path, name: &str) -> Result<(), String> {
+    let (mut vault, snapshot) = read_existing_vault_in(vault_path)?;
+
+    if vault.secrets.remove(name).is_none() {
+        return Err(format!("Secret '{}' not found", name));
+    }
+
+    write_vault_in(vault_path, &snapshot, &vault)
+}
+
+fn decrypt_secrets_in(
+    vault_path: &Path,
+    key: &VaultKey,
+) -> Result<BTreeMap<String, String>, String> {
+    let (vault, _) = read_existing_vault_in(vault_path)?;
+
+    vault
+        .secrets
+        .iter()
+        .map(|(name, secret)| Ok((name.clone(), open(key, name, &secret.sealed)?)))
+        .collect()
+}
+
+fn set_migrated_in(vault_path: &Path, migrated: bool) -> Result<(), String> {
+    let (mut vault, snapshot) = read_existing_vault_in(vault_path)?;
+    vault.migrated = migrated;
+    write_vault_in(vault_path, &snapshot, &vault)
+}
+
+fn get_vault_path() -> Result<PathBuf, String> {
+    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
+    Ok(home_dir.join(VAULT_FILE))
+}
+
+fn unlocked_key() -> Result<VaultKey, String> {
+    UNLOCKED_KEY
+        .lock()
+        .map_err(|_| "Vault key lock poisoned".to_string())?
+        .ok_or_else(|| "Secret vault is locked, unlock it to use secret references".to_string())
+}
+
+fn set_unlocked_key(key: Option<VaultKey>) -> Result<(), String> {
+    *UNLOCKED_KEY
+        .lock()
+        .map_err(|_| "Vault key lock poisoned".to_string())? = key;
+    Ok(())
+}
+
+pub fn status() -> Result<VaultStatus, String> {
+    let (vault, _) = read_vault_in(&get_vault_path()?)?;
+    let unlocked = unlocked_key().is_ok();
+
+    Ok(match vault {
+        Some(vault) => VaultStatus {
+            exists: true,
+            unlocked,
+            migrated: vault.migrated,
+            secret_count: vault.secrets.len(),
+        },
+        None => VaultStatus {
+            exists: false,
+            unlocked: false,
+            migrated: false,
+            secret_count: 0,
+        },
+    })
+}
+
+/// Create the vault and leave it unlocked
+pub fn create(passphrase: &str) -> Result<(), String> {
+    let key = create_vault_in(&get_vault_path()?, passphrase, KdfParams::default())?;
+    set_unlocked_key(Some(key))
+}
+
+pub fn unlock(passphrase: &str) -> Result<(), String> {
+    let key = unlock_vault_in(&get_vault_path()?, passphrase)?;
+    set_unlocked_key(Some(key))
+}
+
+pub fn lock() -> Result<(), String> {
+    set_unlocked_key(None)
+}
+
+pub fn list_secrets() -> Result<Vec<SecretInfo>, String> {
+    list_secrets_in(&get_vault_path()?)
+}
+
+pub fn set_secret(name: &str, value: &str) -> Result<SecretInfo, String> {
+    store_secret_in(&get_vault_path()?, &unlocked_key()?, name, value, false)
+}
+
+pub fn rotate_secret(name: &str, value: &str) -> Result<SecretInfo, String> {
+    store_secret_in(&get_vault_path()?, &unlocked_key()?, name, value, true)
+}
+
+pub fn delete_secret(name: &str) -> Result<(), String> {
+    delete_secret_in(&get_vault_path()?, name)
+}
+
+/// Plaintext of every secret; requires the vault to be unlocked
+pub fn decrypt_secrets() -> Result<BTreeMap<String, String>, String> {
+    decrypt_secrets_in(&get_vault_path()?, &unlocked_key()?)
+}
+
+pub fn mark_migrated() -> Result<(), String> {
+    set_migrated_in(&get_vault_path()?, true)
+}
+
+/// Allow migrating again, e.g. after an undo brought plaintext values back
+pub fn unmark_migrated() -> Result<(), String> {
+    set_migrated_in(&get_vault_path()?, false)
+}
+
+/// The reference string that stands in for secret `name`
+pub fn secret_ref(name: &str) -> String {
+    format!("{}{}{}", REF_PREFIX, name, REF_SUFFIX)
+}
+
+fn collect_ref_names(value: &Value, names: &mut BTreeSet<String>) {
+    match value {
+        Value::String(s) => {
+            let mut rest = s.as_str();
+            while let Some(start) = rest.find(REF_PREFIX) {
+                let after = &rest[start + REF_PREFIX.len()..];
+                let Some(end) = after.find(REF_SUFFIX) else {
+                    break;
+                };
+                names.insert(after[..end].to_string());
+                rest = &after[end + REF_SUFFIX.len()..];
+            }
+        }
+        Value::Array(items) => items.iter().for_each(|item| collect_ref_names(item, names)),
+        Value::Object(obj) => obj.values().for_each(|item| collect_ref_names(item, names)),
+        _ => {}
+    }
+}
+
+/// Names of all secrets referenced anywhere in `value`
+pub fn referenced_secrets(value: &Value) -> BTreeSet<String> {
+    let mut names = BTreeSet::new();
+    collect_ref_names(value, &mut names);
+    names
+}
+
+pub fn contains_refs(value: &Value) -> bool {
+    !referenced_secrets(value).is_empty()
+}
+
+/// Replace every `{{secret:name}}` in the strings of `value` with its plaintext
+pub fn substitute_refs(value: &Value, secrets: &BTreeMap<String, String>) -> Result<Value, String> {
+    Ok(match value {
+        Value::String(s) if s.contains(REF_PREFIX) => {
+            let mut resolved = s.clone();
+            for name in referenced_secrets(value) {
+                let secret = secrets
+                    .get(&name)
+                    .ok_or_else(|| format!("Secret '{}' not found in vault", name))?;
+                resolved = resolved.replace(&secret_ref(&name), secret);
+            }
+            Value::String(resolved)
+        }
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|item| substitute_refs(item, secrets))
+                .collect::<Result<_, _>>()?,
+        ),
+        Value::Object(obj) => Value::Object(
+            obj.iter()
+                .map(|(k, v)| Ok((k.clone(), substitute_refs(v, secrets)?)))
+                .collect::<Result<_, String>>()?,
+        ),
+        _ => value.clone(),
+    })
+}
+
+/// Resolve secret references in `value` using the unlocked vault. Values
+/// without references don't need the vault at all.
+pub fn resolve_refs(value: &Value) -> Result<Value, String> {
+    if !contains_refs(value) {
+        return Ok(value.clone());
+    }
+    substitute_refs(value, &decrypt_secrets()?)
+}
+
+/// Put references from `with_refs` back into `settings` wherever `settings`
+/// still holds the value they resolved to, so plaintext read back from
+/// settings.json doesn't end up in a store
+pub fn restore_refs(settings: &Value, with_refs: &Value, resolved: &Value) -> Value {
+    match (settings, with_refs, resolved) {
+        (Value::Object(obj), Value::Object(ref_obj), Value::Object(resolved_obj)) => Value::Object(
+            obj.iter()
+                .map(|(key, value)| {
+                    let restored = match (ref_obj.get(key), resolved_obj.get(key)) {
+                        (Some(ref_value), Some(resolved_value)) => {
+                            restore_refs(value, ref_value, resolved_value)
+                        }
+                        _ => value.clone(),
+                    };
+                    (key.clone(), restored)
+                })
+                .collect(),
+        ),
+        (Value::Array(items), Value::Array(ref_items), Value::Array(resolved_items))
+            if items.len() == ref_items.len() && items.len() == resolved_items.len() =>
+        {
+            Value::Array(
+                items
+                    .iter()
+                    .zip(ref_items.iter().zip(resolved_items))
+                    .map(|(item, (ref_item, resolved_item))| {
+                        restore_refs(item, ref_item, resolved_item)
+                    })
+                    .collect(),
+            )
+        }
+        (Value::String(_), Value::String(_), _)
+            if settings == resolved && contains_refs(with_refs) =>
+        {
+            with_refs.clone()
+        }
+        _ => settings.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Cheap parameters so the tests don't spend seconds in scrypt
+    const TEST_PARAMS: KdfParams = KdfParams {
+        log_n: 4,
+        r: 8,
+        p: 1,
+    };
+
+    #[test]
+    fn test_unlock_vault_checks_passphrase() {
+        let dir = tempfile::tempdir().unwrap();
+        let vault_path = dir.path().join("vault.json");
+
+        let key = create_vault_in(&vault_path, "correct horse", TEST_PARAMS).unwrap();
+
+        assert_eq!(unlock_vault_in(&vault_path, "correct horse").unwrap(), key);
+        assert_eq!(
+            unlock_vault_in(&vault_path, "wrong").unwrap_err(),
+            "Incorrect vault passphrase"
+        );
+        assert!(create_vault_in(&vault_path, "again", TEST_PARAMS).is_err());
+    }
+
+    #[test]
+    fn test_store_secret_encrypts_and_rotates() {
+        let dir = tempfile::tempdir().unwrap();
+        let vault_path = dir.path().join("vault.json");
+        let key = create_vault_in(&vault_path, "pass", TEST_PARAMS).unwrap();
+
+        store_secret_in(&vault_path, &key, "work-token", "sk-ant-1", false).unwrap();
+        assert!(!std::fs::read_to_string(&vault_path)
+            .unwrap()
+            .contains("sk-ant-1"));
+        assert!(store_secret_in(&vault_path, &key, "work-token", "sk-ant-2", false).is_err());
+        assert!(store_secret_in(&vault_path, &key, "missing", "x", true).is_err());
+
+        store_secret_in(&vault_path, &key, "work-token", "sk-ant-2", true).unwrap();
+        let secrets = decrypt_secrets_in(&vault_path, &key).unwrap();
+        assert_eq!(
+            secrets.get("work-token").map(String::as_str),
+            Some("sk-ant-2")
+        );
+
+        delete_secret_in(&vault_path, "work-token").unwrap();
+        assert!(list_secrets_in(&vault_path).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_substitute_refs_replaces_nested_references() {
+        let secrets = BTreeMap::from([("work-token".to_string(), "sk-ant-1".to_string())]);
+        let settings = json!({
+            "env": { "ANTHROPIC_AUTH_TOKEN": "{{secret:work-token}}", "A": "1" },
+            "headers": ["Bearer {{secret:work-token}}"]
+        });
+
+        assert_eq!(
+            substitute_refs(&settings, &secrets).unwrap(),
+            json!({
+                "env": { "ANTHROPIC_AUTH_TOKEN": "sk-ant-1", "A": "1" },
+                "headers": ["Bearer sk-ant-1"]
+            })
+        );
+        assert!(substitute_refs(&json!({ "x": "{{secret:missing}}" }), &secrets).is_err());
+    }
+
+    #[test]
+    fn test_restore_refs_keeps_references_for_unchanged_values() {
+        let with_refs = json!({ "env": { "TOKEN": "{{secret:t}}", "OTHER": "{{secret:o}}" } });
+        let resolved = json!({ "env": { "TOKEN": "sk-1", "OTHER": "sk-2" } });
+        let live = json!({ "env": { "TOKEN": "sk-1", "OTHER": "edited", "NEW": "1" } });
+
+        assert_eq!(
+            restore_refs(&live, &with_refs, &resolved),
+            json!({ "env": { "TOKEN": "{{secret:t}}", "OTHER": "edited", "NEW": "1" } })
+        );
+    }
+}
@@ -31,12 +31,51 @@ export interface ClaudeSettings {
 	[key: string]: any;
 }
 
+export interface SettingsValidationError {
+	path: string;
+	message: string;
+}
+
+/** Error returned by commands that validate settings before writing them */
+export interface SettingsError {
+	message: string;
+	errors: SettingsValidationError[];
+}
+
+/** Non-fatal problems found in settings that were saved anyway, e.g. typos */
+type SettingsWarnings = SettingsValidationError[];
+
+const showSettingsWarnings = (warnings: SettingsWarnings | undefined) => {
+	if (!warnings?.length) {
+		return;
+	}
+	toast.warning(
+		i18n.t("toast.settingsWarnings", {
+			warnings: warnings.map((w) => `${w.path}: ${w.message}`).join("\n"),
+		}),
+	);
+};
+
+const formatSettingsError = (error: unknown): string => {
+	if (typeof error === "object" && error !== null && "errors" in error) {
+		const { errors } = error as SettingsError;
+		return errors.map((e) => `${e.path}: ${e.message}`).join("\n");
+	}
+	return error instanceof Error ? error.message : String(error);
+};
+
 export interface ConfigStore {
 	id: string; // nanoid(6)
 	title: string;
 	createdAt: number;
 	settings: ClaudeSettings;
 	using: boolean;
+	parentId?: string; // store whose settings this one inherits
+}
+
+/** A store as returned by create_config/update_config */
+export interface SavedConfigStore extends ConfigStore {
+	warnings: SettingsWarnings;
 }
 
 export interface McpServer {
@@ -70,6 +109,7 @@ export interface ProjectSettings {
 	hasAgents: boolean;
 	hasCommands: boolean;
 	hasMcp: boolean;
+	hasLegacyMcp: boolean;
 }
 
 export interface ProjectRegistryEntry {
@@ -124,19 +164,20 @@ export const useWriteConfigFile = () => {
 		}: {
 			configType: ConfigType;
 			content: unknown;
-		}) => invoke<void>("write_config_file", { configType, content }),
-		onSuccess: (_, variables) => {
+		}) =>
+			invoke<SettingsWarnings>("write_config_file", { configType, content }),
+		onSuccess: (warnings, variables) => {
 			toast.success(
 				i18n.t("toast.configSaved", { configType: variables.configType }),
 			);
+			showSettingsWarnings(warnings);
 			queryClient.invalidateQueries({
 				queryKey: ["config-file", variables.configType],
 			});
 			queryClient.invalidateQueries({ queryKey: ["config-files"] });
 		},
 		onError: (error) => {
-			const errorMessage =
-				error instanceof Error ? error.message : String(error);
+			const errorMessage = formatSettingsError(error);
 			toast.error(i18n.t("toast.configSaveFailed", { error: errorMessage }));
 		},
 	});
@@ -191,17 +232,21 @@ export const useCreateConfig = () => {
 			settings: unknown;
 		}) => {
 			const id = nanoid(6);
-			return invoke<ConfigStore>("create_config", { id, title, settings });
+			return invoke<SavedConfigStore>("create_config", {
+				id,
+				title,
+				settings,
+			});
 		},
-		onSuccess: async () => {
+		onSuccess: async (data) => {
 			toast.success(i18n.t("toast.storeCreated"));
+			showSettingsWarnings(data.warnings);
 			queryClient.invalidateQueries({ queryKey: ["stores"] });
 			queryClient.invalidateQueries({ queryKey: ["current-store"] });
 			await rebuildTrayMenu();
 		},
 		onError: (error) => {
-			const errorMessage =
-				error instanceof Error ? error.message : String(error);
+			const errorMessage = formatSettingsError(error);
 			toast.error(i18n.t("toast.storeCreateFailed", { error: errorMessage }));
 		},
 	});
@@ -289,7 +334,8 @@ export const useUpdateConfig = () => {
 			storeId: string;
 			title: string;
 			settings: unknown;
-		}) => invoke<ConfigStore>("update_config", { storeId, title, settings }),
+		}) =>
+			invoke<SavedConfigStore>("update_config", { storeId, title, settings }),
 		onSuccess: async (data) => {
 			if (data.using) {
 				toast.success(
@@ -298,6 +344,7 @@ export const useUpdateConfig = () => {
 			} else {
 				toast.success(i18n.t("toast.storeSaved", { title: data.title }));
 			}
+			showSettingsWarnings(data.warnings);
 			queryClient.invalidateQueries({ queryKey: ["stores"] });
 			queryClient.invalidateQueries({ queryKey: ["store", data.id] });
 			queryClient.invalidateQueries({ queryKey: ["current-store"] });
@@ -307,8 +354,7 @@ export const useUpdateConfig = () => {
 			await rebuildTrayMenu();
 		},
 		onError: (error) => {
-			const errorMessage =
-				error instanceof Error ? error.message : String(error);
+			const errorMessage = formatSettingsError(error);
 			toast.error(i18n.t("toast.storeSaveFailed", { error: errorMessage }));
 		},
 	});
@@ -378,8 +424,7 @@ export const useUpdateGlobalMcpServer = () => {
 			queryClient.invalidateQueries({ queryKey: ["global-mcp-servers"] });
 		},
 		onError: (error) => {
-			const errorMessage =
-				error instanceof Error ? error.message : String(error);
+			const errorMessage = formatSettingsError(error);
 			toast.error(`Failed to update MCP server: ${errorMessage}`);
 		},
 	});
@@ -402,8 +447,7 @@ export const useAddGlobalMcpServer = () => {
 			queryClient.invalidateQueries({ queryKey: ["global-mcp-servers"] });
 		},
 		onError: (error) => {
-			const errorMessage =
-				error instanceof Error ? error.message : String(error);
+			const errorMessage = formatSettingsError(error);
 			toast.error(`Failed to add MCP server: ${errorMessage}`);
 		},
 	});
@@ -535,6 +579,7 @@ export interface ClaudeConfigFile {
 	path: string;
 	content: unknown;
 	exists: boolean;
+	fingerprint: string | null;
 }
 
 export const useClaudeConfigFile = () => {
@@ -548,8 +593,13 @@ export const useWriteClaudeConfigFile = () => {
 	const queryClient = useQueryClient();
 
 	return useMutation({
-		mutationFn: (content: unknown) =>
-			invoke<void>("write_claude_config_file", { content }),
+		mutationFn: ({
+			content,
+			fingerprint,
+		}: {
+			content: unknown;
+			fingerprint: string | null;
+		}) => invoke<void>("write_claude_config_file", { content, fingerprint }),
 		onSuccess: () => {
 			toast.success("Claude configuration saved successfully");
 			queryClient.invalidateQueries({ queryKey: ["claude-config-file"] });
@@ -948,16 +998,19 @@ export const useWriteProjectSettings = () => {
 			projectPath,
 			settings,
 		}: { projectPath: string; settings: unknown }) =>
-			invoke<void>("write_project_settings", { projectPath, settings }),
-		onSuccess: (_, variables) => {
+			invoke<SettingsWarnings>("write_project_settings", {
+				projectPath,
+				settings,
+			}),
+		onSuccess: (warnings, variables) => {
 			toast.success(i18n.t("toast.projectSettingsSaved"));
+			showSettingsWarnings(warnings);
 			queryClient.invalidateQueries({
 				queryKey: ["project-settings", variables.projectPath],
 			});
 		},
 		onError: (error) => {
-			const errorMessage =
-				error instanceof Error ? error.message : String(error);
+			const errorMessage = formatSettingsError(error);
 			toast.error(i18n.t("toast.saveFailed", { error: errorMessage }));
 		},
 	});
@@ -1119,8 +1172,7 @@ export const useWriteProjectMcp = () => {
 			});
 		},
 		onError: (error) => {
-			const errorMessage =
-				error instanceof Error ? error.message : String(error);
+			const errorMessage = formatSettingsError(error);
 			toast.error(i18n.t("toast.saveFailed", { error: errorMessage }));
 		},
 	});
@@ -1160,7 +1212,7 @@ export const useAddProjectMcpServer = () => {
 			queryClient.invalidateQueries({ queryKey: ["project-mcp", variables.projectPath] });
 		},
 		onError: (error) => {
-			const errorMessage = error instanceof Error ? error.message : String(error);
+			const errorMessage = formatSettingsError(error);
 			toast.error(`Failed to add MCP server: ${errorMessage}`);
 		},
 	});
@@ -1189,7 +1241,7 @@ export const useUpdateProjectMcpServer = () => {
 			queryClient.invalidateQueries({ queryKey: ["project-mcp", variables.projectPath] });
 		},
 		onError: (error) => {
-			const errorMessage = error instanceof Error ? error.message : String(error);
+			const errorMessage = formatSettingsError(error);
 			toast.error(`Failed to update MCP server: ${errorMessage}`);
 		},
 	});
@@ -1217,7 +1269,7 @@ export const useDeleteProjectMcpServer = () => {
 			queryClient.invalidateQueries({ queryKey: ["project-mcp", variables.projectPath] });
 		},
 		onError: (error) => {
-			const errorMessage = error instanceof Error ? error.message : String(error);
+			const errorMessage = formatSettingsError(error);
 			toast.error(`Failed to delete MCP server: ${errorMessage}`);
 		},
 	});
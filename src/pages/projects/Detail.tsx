@@ -86,7 +86,10 @@ export function Detail() {
 			}
 			updatedConfig.projects[decodedPath] = parsedContent;
 
-			writeClaudeConfig.mutate(updatedConfig);
+			writeClaudeConfig.mutate({
+				content: updatedConfig,
+				fingerprint: claudeConfig.fingerprint,
+			});
 			setHasChanges(false);
 		} catch (error) {
 			toast.error(t("projects.detail.invalidJson"));